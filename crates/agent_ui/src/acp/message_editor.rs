@@ -455,6 +455,12 @@ impl MessageEditor {
         })
     }
 
+    /// Counts a use of every rule mentioned in the message.
+    pub fn record_rule_usage(&self, cx: &mut Context<Self>) {
+        self.mention_set
+            .update(cx, |mention_set, cx| mention_set.record_rule_usage(cx));
+    }
+
     pub fn clear(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         self.editor.update(cx, |editor, cx| {
             editor.clear(window, cx);
@@ -1162,6 +1162,9 @@ impl AcpThreadView {
             }
 
             this.update_in(cx, |this, window, cx| {
+                message_editor.update(cx, |message_editor, cx| {
+                    message_editor.record_rule_usage(cx);
+                });
                 this.in_flight_prompt = Some(contents.clone());
                 this.set_editor_is_expanded(false, cx);
                 this.scroll_to_bottom(cx);
@@ -3274,6 +3277,7 @@ impl AcpThreadView {
                                 window.dispatch_action(
                                     Box::new(OpenRulesLibrary {
                                         prompt_to_select: first_user_rules_id,
+                                        ..Default::default()
                                     }),
                                     cx,
                                 )
@@ -4721,6 +4725,7 @@ impl AcpThreadView {
                     window.dispatch_action(
                         Box::new(OpenRulesLibrary {
                             prompt_to_select: Some(uuid.0),
+                            ..Default::default()
                         }),
                         cx,
                     )
@@ -940,6 +940,7 @@ impl AgentPanel {
             action
                 .prompt_to_select
                 .map(|uuid| UserPromptId(uuid).into()),
+            action.read_only,
             cx,
         )
         .detach_and_log_err(cx);
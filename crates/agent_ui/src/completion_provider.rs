@@ -1582,7 +1582,7 @@ pub(crate) fn search_rules(
             .into_iter()
             .flat_map(|metadata| {
                 // Default prompts are filtered out as they are automatically included.
-                if metadata.default {
+                if metadata.default && !metadata.disabled {
                     None
                 } else {
                     match metadata.id {
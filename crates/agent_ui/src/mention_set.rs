@@ -133,6 +133,27 @@ impl MentionSet {
         self.mentions = mentions;
     }
 
+    /// Counts a use of every rule that's mentioned, once the message
+    /// mentioning them is sent.
+    pub fn record_rule_usage(&self, cx: &mut App) {
+        let Some(prompt_store) = self.prompt_store.as_ref() else {
+            return;
+        };
+        let rule_ids = self
+            .mentions
+            .values()
+            .filter_map(|(uri, _)| match uri {
+                MentionUri::Rule { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        prompt_store.update(cx, |prompt_store, cx| {
+            for id in rule_ids {
+                prompt_store.record_usage(id, cx).detach_and_log_err(cx);
+            }
+        });
+    }
+
     pub fn clear(&mut self) -> impl Iterator<Item = (CreaseId, (MentionUri, MentionTask))> {
         self.mentions.drain()
     }
@@ -86,10 +86,11 @@ impl SlashCommand for PromptSlashCommand {
             async move |cx| {
                 let store = store.await?;
                 let body = store
-                    .read_with(cx, |store, cx| {
+                    .update(cx, |store, cx| {
                         let prompt_id = store
                             .id_for_title(&title)
                             .with_context(|| format!("no prompt found with title {:?}", title))?;
+                        store.record_usage(prompt_id, cx).detach_and_log_err(cx);
                         anyhow::Ok(store.load(prompt_id, cx))
                     })??
                     .await?;
@@ -1,17 +1,50 @@
-use anyhow::anyhow;
-use dap::Module;
+use collections::{BTreeMap, HashSet};
+use dap::{Module, ModuleId};
 use gpui::{
-    AnyElement, Entity, FocusHandle, Focusable, ScrollStrategy, Subscription, Task,
-    UniformListScrollHandle, WeakEntity, uniform_list,
+    AnyElement, ClickEvent, ClipboardItem, DismissEvent, Entity, FocusHandle, Focusable, Hsla,
+    MouseDownEvent, Pixels, Point, ScrollStrategy, Subscription, Task, TextRun,
+    UniformListScrollHandle, WeakEntity, anchored, deferred, uniform_list,
 };
-use project::{
-    ProjectItem as _, ProjectPath,
-    debugger::session::{Session, SessionEvent},
+use project::debugger::session::{ModuleChange, Session, SessionEvent};
+use std::{
+    iter,
+    ops::Range,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use ui::{
+    CommonAnimationExt as _, ContextMenu, ContextMenuEntry, Indicator, Tooltip, WithScrollbar,
+    prelude::*,
 };
-use std::{ops::Range, path::Path, sync::Arc};
-use ui::{WithScrollbar, prelude::*};
 use workspace::Workspace;
 
+/// How long to show the refresh indicator if the adapter never responds.
+const MODULE_REFRESH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a module stays highlighted after the adapter reports that it was
+/// loaded, changed or unloaded.
+const MODULE_STATE_HIGHLIGHT_DURATION: Duration = Duration::from_millis(1500);
+
+/// The label of the group for modules without a path.
+const UNKNOWN_FOLDER: &str = "Unknown";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ModuleListEntry {
+    Folder {
+        folder: SharedString,
+        module_count: usize,
+    },
+    /// An index into the module list's modules.
+    Module(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ModuleLoadState {
+    Loading,
+    Unloading,
+}
+
 pub struct ModuleList {
     scroll_handle: UniformListScrollHandle,
     selected_ix: Option<usize>,
@@ -19,7 +52,16 @@ pub struct ModuleList {
     workspace: WeakEntity<Workspace>,
     focus_handle: FocusHandle,
     entries: Vec<Module>,
+    /// The rows of the list. `selected_ix` is an index into these.
+    visible_entries: Vec<ModuleListEntry>,
+    group_by_folder: bool,
+    collapsed_folders: HashSet<SharedString>,
+    module_states: Vec<(ModuleId, ModuleLoadState, Instant)>,
+    _expire_module_states_task: Task<()>,
+    refreshing: bool,
+    _refresh_timeout_task: Task<()>,
     _rebuild_task: Option<Task<()>>,
+    open_context_menu: Option<(Entity<ContextMenu>, Point<Pixels>, Subscription)>,
     _subscription: Subscription,
 }
 
@@ -35,10 +77,16 @@ impl ModuleList {
             SessionEvent::Stopped(_)
             | SessionEvent::HistoricSnapshotSelected
             | SessionEvent::Modules => {
+                if matches!(event, SessionEvent::Modules) {
+                    this.refreshing = false;
+                }
                 if this._rebuild_task.is_some() {
                     this.schedule_rebuild(cx);
                 }
             }
+            SessionEvent::ModuleChanged { module, change } => {
+                this.on_module_changed(module, *change, cx);
+            }
             _ => {}
         });
 
@@ -50,7 +98,15 @@ impl ModuleList {
             workspace,
             focus_handle,
             entries: Vec::new(),
+            visible_entries: Vec::new(),
+            group_by_folder: false,
+            collapsed_folders: HashSet::default(),
+            module_states: Vec::new(),
+            _expire_module_states_task: Task::ready(()),
+            refreshing: false,
+            _refresh_timeout_task: Task::ready(()),
             selected_ix: None,
+            open_context_menu: None,
             _subscription,
             _rebuild_task: None,
         }
@@ -59,70 +115,391 @@ impl ModuleList {
     fn schedule_rebuild(&mut self, cx: &mut Context<Self>) {
         self._rebuild_task = Some(cx.spawn(async move |this, cx| {
             this.update(cx, |this, cx| {
-                let modules = this
+                let mut modules = this
                     .session
                     .update(cx, |session, cx| session.modules(cx).to_owned());
+                // Keep showing modules that were just unloaded until their highlight expires.
+                for (ix, module) in this.entries.iter().enumerate() {
+                    if this.module_state(&module.id) == Some(ModuleLoadState::Unloading)
+                        && !modules.iter().any(|other| other.id == module.id)
+                    {
+                        modules.insert(ix.min(modules.len()), module.clone());
+                    }
+                }
                 this.entries = modules;
+                this.rebuild_visible_entries();
                 cx.notify();
             })
             .ok();
         }));
     }
 
-    fn open_module(&mut self, path: Arc<Path>, window: &mut Window, cx: &mut Context<Self>) {
-        cx.spawn_in(window, async move |this, cx| {
-            let (worktree, relative_path) = this
-                .update(cx, |this, cx| {
-                    this.workspace.update(cx, |workspace, cx| {
-                        workspace.project().update(cx, |this, cx| {
-                            this.find_or_create_worktree(&path, false, cx)
-                        })
-                    })
-                })??
-                .await?;
-
-            let buffer = this
-                .update(cx, |this, cx| {
-                    this.workspace.update(cx, |this, cx| {
-                        this.project().update(cx, |this, cx| {
-                            let worktree_id = worktree.read(cx).id();
-                            this.open_buffer(
-                                ProjectPath {
-                                    worktree_id,
-                                    path: relative_path,
-                                },
-                                cx,
-                            )
-                        })
-                    })
-                })??
-                .await?;
-
-            this.update_in(cx, |this, window, cx| {
-                this.workspace.update(cx, |workspace, cx| {
-                    let project_path = buffer.read(cx).project_path(cx).ok_or_else(|| {
-                        anyhow!("Could not select a stack frame for unnamed buffer")
-                    })?;
-                    anyhow::Ok(workspace.open_path_preview(
-                        project_path,
-                        None,
-                        false,
-                        true,
-                        true,
-                        window,
-                        cx,
-                    ))
+    fn rebuild_visible_entries(&mut self) {
+        self.visible_entries = if self.group_by_folder {
+            group_modules_by_folder(&self.entries)
+                .into_iter()
+                .flat_map(|(folder, module_ixs)| {
+                    let collapsed = self.collapsed_folders.contains(&folder);
+                    let header = ModuleListEntry::Folder {
+                        folder,
+                        module_count: module_ixs.len(),
+                    };
+                    iter::once(header).chain(
+                        module_ixs
+                            .into_iter()
+                            .filter(move |_| !collapsed)
+                            .map(ModuleListEntry::Module),
+                    )
                 })
-            })???
-            .await?;
+                .collect()
+        } else {
+            (0..self.entries.len())
+                .map(ModuleListEntry::Module)
+                .collect()
+        };
+    }
+
+    fn toggle_group_by_folder(&mut self, cx: &mut Context<Self>) {
+        self.group_by_folder = !self.group_by_folder;
+        self.selected_ix = None;
+        self.rebuild_visible_entries();
+        cx.notify();
+    }
+
+    /// Collapses or expands a folder's group, keeping its header selected.
+    fn toggle_folder(&mut self, folder: SharedString, cx: &mut Context<Self>) {
+        if !self.collapsed_folders.remove(&folder) {
+            self.collapsed_folders.insert(folder.clone());
+        }
+        self.rebuild_visible_entries();
+        self.selected_ix = self.visible_entries.iter().position(|entry| {
+            matches!(entry, ModuleListEntry::Folder { folder: other, .. } if *other == folder)
+        });
+        cx.notify();
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        if self.is_post_mortem(cx) {
+            return;
+        }
+        self.refreshing = true;
+        self.session
+            .update(cx, |session, cx| session.refresh_modules(cx));
+        self._refresh_timeout_task = cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(MODULE_REFRESH_TIMEOUT).await;
+            this.update(cx, |this, cx| {
+                this.refreshing = false;
+                cx.notify();
+            })
+            .ok();
+        });
+        cx.notify();
+    }
 
-            anyhow::Ok(())
+    /// Whether the modules come from a core dump. They're never loaded or
+    /// unloaded then, and refreshing them would return the same table.
+    fn is_post_mortem(&self, cx: &App) -> bool {
+        self.session.read(cx).is_post_mortem()
+    }
+
+    fn render_toolbar(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let group_by_folder = IconButton::new("group-modules-by-folder", IconName::ListTree)
+            .icon_size(IconSize::Small)
+            .toggle_state(self.group_by_folder)
+            .tooltip(Tooltip::text("Group by Folder"))
+            .on_click(cx.listener(|this, _, _, cx| this.toggle_group_by_folder(cx)));
+        // Unloaded modules are kept in the list while they're highlighted.
+        let module_count = self
+            .entries
+            .iter()
+            .filter(|module| self.module_state(&module.id) != Some(ModuleLoadState::Unloading))
+            .count();
+        let module_count = Label::new(if module_count == 1 {
+            "1 module".to_string()
+        } else {
+            format!("{module_count} modules")
         })
-        .detach();
+        .size(LabelSize::Small)
+        .color(Color::Muted);
+
+        if self.is_post_mortem(cx) {
+            return h_flex()
+                .w_full()
+                .justify_between()
+                .child(
+                    h_flex()
+                        .p_1()
+                        .gap_1()
+                        .child(
+                            Icon::new(IconName::FileLock)
+                                .size(IconSize::Small)
+                                .color(Color::Muted),
+                        )
+                        .child(
+                            Label::new("Modules from Core Dump")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                        .child(module_count),
+                )
+                .child(group_by_folder)
+                .into_any_element();
+        }
+
+        h_flex()
+            .w_full()
+            .justify_between()
+            .child(div().p_1().child(module_count))
+            .child(h_flex().child(group_by_folder).child(if self.refreshing {
+                div()
+                    .p_1()
+                    .child(
+                        Icon::new(IconName::ArrowCircle)
+                            .size(IconSize::Small)
+                            .color(Color::Muted)
+                            .with_rotate_animation(2),
+                    )
+                    .into_any_element()
+            } else {
+                IconButton::new("refresh-modules", IconName::RotateCw)
+                    .icon_size(IconSize::Small)
+                    .tooltip(Tooltip::text("Refresh Modules"))
+                    .on_click(cx.listener(|this, _, _, cx| this.refresh(cx)))
+                    .into_any_element()
+            }))
+            .into_any_element()
+    }
+
+    fn on_module_changed(&mut self, module: &Module, change: ModuleChange, cx: &mut Context<Self>) {
+        if self.is_post_mortem(cx) {
+            if self._rebuild_task.is_some() {
+                self.schedule_rebuild(cx);
+            }
+            return;
+        }
+        let state = match change {
+            ModuleChange::Added | ModuleChange::Changed => ModuleLoadState::Loading,
+            ModuleChange::Removed => ModuleLoadState::Unloading,
+        };
+        self.module_states.retain(|(id, _, _)| *id != module.id);
+        self.module_states
+            .push((module.id.clone(), state, Instant::now()));
+        self.schedule_module_state_expiry(cx);
+        if self._rebuild_task.is_some() {
+            self.schedule_rebuild(cx);
+        }
+    }
+
+    fn module_state(&self, id: &ModuleId) -> Option<ModuleLoadState> {
+        self.module_states
+            .iter()
+            .find(|(module_id, _, _)| module_id == id)
+            .map(|(_, state, _)| *state)
+    }
+
+    /// Reverts modules to their normal appearance once their highlight has expired.
+    fn schedule_module_state_expiry(&mut self, cx: &mut Context<Self>) {
+        let Some(next_expiry) = self
+            .module_states
+            .iter()
+            .map(|(_, _, changed_at)| *changed_at + MODULE_STATE_HIGHLIGHT_DURATION)
+            .min()
+        else {
+            return;
+        };
+        self._expire_module_states_task = cx.spawn(async move |this, cx| {
+            let delay = next_expiry.saturating_duration_since(Instant::now());
+            cx.background_executor().timer(delay).await;
+            this.update(cx, |this, cx| {
+                let now = Instant::now();
+                this.module_states.retain(|(_, _, changed_at)| {
+                    now.duration_since(*changed_at) < MODULE_STATE_HIGHLIGHT_DURATION
+                });
+                this.schedule_module_state_expiry(cx);
+                this.schedule_rebuild(cx);
+            })
+            .ok();
+        });
+    }
+
+    /// Opens the module's file if it's in one of the workspace's worktrees.
+    /// Modules outside of the project are usually binaries or system
+    /// libraries, so those are revealed in the system's file manager instead.
+    fn open_module(&mut self, path: Arc<Path>, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(workspace) = self.workspace.upgrade() else {
+            return;
+        };
+        let Some(project_path) = workspace
+            .read(cx)
+            .project()
+            .read(cx)
+            .find_project_path(&path, cx)
+        else {
+            cx.reveal_path(&path);
+            return;
+        };
+        workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path_preview(project_path, None, false, true, true, window, cx)
+            })
+            .detach_and_log_err(cx);
     }
 
-    fn render_entry(&mut self, ix: usize, cx: &mut Context<Self>) -> AnyElement {
-        let module = self.entries[ix].clone();
+    fn deploy_module_context_menu(
+        &mut self,
+        ix: usize,
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(module) = self.entries.get(ix) else {
+            return;
+        };
+        let name = module.name.clone();
+        let path = module.path.clone();
+        let context_menu = ContextMenu::build(window, cx, |menu, _, _| {
+            menu.item(
+                ContextMenuEntry::new("Copy Path")
+                    .disabled(path.is_none())
+                    .handler(move |_, cx| {
+                        if let Some(path) = path.clone() {
+                            cx.write_to_clipboard(ClipboardItem::new_string(path));
+                        }
+                    }),
+            )
+            .entry("Copy Name", None, move |_, cx| {
+                cx.write_to_clipboard(ClipboardItem::new_string(name.clone()));
+            })
+            .context(self.focus_handle.clone())
+        });
+
+        cx.focus_view(&context_menu, window);
+        let subscription = cx.subscribe_in(
+            &context_menu,
+            window,
+            |this, _, _: &DismissEvent, window, cx| {
+                if this.open_context_menu.as_ref().is_some_and(|context_menu| {
+                    context_menu.0.focus_handle(cx).contains_focused(window, cx)
+                }) {
+                    cx.focus_self(window);
+                }
+                this.open_context_menu.take();
+                cx.notify();
+            },
+        );
+
+        self.open_context_menu = Some((context_menu, position, subscription));
+        cx.notify();
+    }
+
+    /// Whether the path is too wide to fit in a row of the list, as of the last layout.
+    fn is_path_truncated(&self, path: &str, window: &mut Window, cx: &App) -> bool {
+        let Some(row_width) = self
+            .scroll_handle
+            .0
+            .borrow()
+            .last_item_size
+            .map(|size| size.item.width)
+        else {
+            return false;
+        };
+        // Rows are padded with `p_1` on both sides.
+        let row_padding = rems(0.5).to_pixels(window.rem_size());
+        let font_size = TextSize::XSmall.rems(cx).to_pixels(window.rem_size());
+        let font = window.text_style().font();
+        let path_width = window
+            .text_system()
+            .shape_line(
+                SharedString::from(path.to_string()),
+                font_size,
+                &[TextRun {
+                    len: path.len(),
+                    font,
+                    color: Hsla::default(),
+                    ..Default::default()
+                }],
+                None,
+            )
+            .width;
+        path_width > row_width - row_padding
+    }
+
+    fn render_entry(
+        &mut self,
+        ix: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        match self.visible_entries[ix].clone() {
+            ModuleListEntry::Folder {
+                folder,
+                module_count,
+            } => self.render_folder(ix, folder, module_count, cx),
+            ModuleListEntry::Module(module_ix) => self.render_module(ix, module_ix, window, cx),
+        }
+    }
+
+    fn render_folder(
+        &self,
+        ix: usize,
+        folder: SharedString,
+        module_count: usize,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let collapsed = self.collapsed_folders.contains(&folder);
+
+        h_flex()
+            .id(("module-folder", ix))
+            .w_full()
+            .p_1()
+            .gap_1()
+            .rounded_md()
+            .on_any_mouse_down(|_, _, cx| {
+                cx.stop_propagation();
+            })
+            .on_click({
+                let folder = folder.clone();
+                cx.listener(move |this, _, window, cx| {
+                    window.focus(&this.focus_handle);
+                    this.toggle_folder(folder.clone(), cx);
+                })
+            })
+            .hover(|s| s.bg(cx.theme().colors().element_hover))
+            .when(Some(ix) == self.selected_ix, |s| {
+                s.bg(cx.theme().colors().element_hover)
+            })
+            .child(
+                Icon::new(if collapsed {
+                    IconName::ChevronRight
+                } else {
+                    IconName::ChevronDown
+                })
+                .size(IconSize::Small)
+                .color(Color::Muted),
+            )
+            .child(Label::new(folder).size(LabelSize::Small).truncate())
+            .child(
+                Label::new(module_count.to_string())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .into_any()
+    }
+
+    fn render_module(
+        &mut self,
+        ix: usize,
+        module_ix: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let module = self.entries[module_ix].clone();
+        let state = self.module_state(&module.id);
+        let post_mortem = self.is_post_mortem(cx);
+        let path_is_truncated = module
+            .path
+            .as_deref()
+            .is_some_and(|path| self.is_path_truncated(path, window, cx));
 
         v_flex()
             .rounded_md()
@@ -132,36 +509,131 @@ impl ModuleList {
             .on_any_mouse_down(|_, _, cx| {
                 cx.stop_propagation();
             })
-            .when(module.path.is_some(), |this| {
-                this.on_click({
-                    let path = module
-                        .path
-                        .as_deref()
-                        .map(|path| Arc::<Path>::from(Path::new(path)));
-                    cx.listener(move |this, _, window, cx| {
-                        this.selected_ix = Some(ix);
-                        if let Some(path) = path.as_ref() {
-                            this.open_module(path.clone(), window, cx);
-                        }
-                        cx.notify();
-                    })
+            .on_secondary_mouse_down(cx.listener(
+                move |this, event: &MouseDownEvent, window, cx| {
+                    this.selected_ix = Some(ix);
+                    this.deploy_module_context_menu(module_ix, event.position, window, cx);
+                },
+            ))
+            .on_click({
+                let path = module
+                    .path
+                    .as_deref()
+                    .map(|path| Arc::<Path>::from(Path::new(path)));
+                cx.listener(move |this, click: &ClickEvent, window, cx| {
+                    // Rows stop mouse downs from reaching the list, so it has to be
+                    // focused here for the keyboard to move the selection.
+                    window.focus(&this.focus_handle);
+                    this.selected_ix = Some(ix);
+                    if click.click_count() >= 2
+                        && let Some(path) = path.as_ref()
+                    {
+                        this.open_module(path.clone(), window, cx);
+                    }
+                    cx.notify();
                 })
             })
             .p_1()
+            .when(self.group_by_folder, |s| s.pl_5())
             .hover(|s| s.bg(cx.theme().colors().element_hover))
             .when(Some(ix) == self.selected_ix, |s| {
                 s.bg(cx.theme().colors().element_hover)
             })
-            .child(h_flex().gap_0p5().text_ui_sm(cx).child(module.name.clone()))
             .child(
                 h_flex()
-                    .text_ui_xs(cx)
-                    .text_color(cx.theme().colors().text_muted)
-                    .when_some(module.path, |this, path| this.child(path)),
+                    .w_full()
+                    .justify_between()
+                    .gap_2()
+                    .child(
+                        h_flex()
+                            .gap_0p5()
+                            .min_w_0()
+                            .child(
+                                Label::new(module.name.clone())
+                                    .size(LabelSize::Small)
+                                    .map(|label| match state {
+                                        Some(ModuleLoadState::Loading) => {
+                                            label.color(Color::Accent)
+                                        }
+                                        Some(ModuleLoadState::Unloading) => {
+                                            label.color(Color::Muted).strikethrough()
+                                        }
+                                        None => label,
+                                    }),
+                            )
+                            .when_some(module.symbol_status.clone(), |this, symbol_status| {
+                                let loaded = symbols_loaded(&symbol_status);
+                                this.child(
+                                    div()
+                                        .id(("module-symbol-status", ix))
+                                        .child(Indicator::dot().color(if loaded {
+                                            Color::Success
+                                        } else {
+                                            Color::Muted
+                                        }))
+                                        .tooltip(move |_window, cx| {
+                                            if loaded {
+                                                Tooltip::with_meta(
+                                                    "Symbols Loaded",
+                                                    None,
+                                                    symbol_status.clone(),
+                                                    cx,
+                                                )
+                                            } else {
+                                                Tooltip::with_meta(
+                                                    "Symbols Not Loaded",
+                                                    None,
+                                                    format!(
+                                                        "{symbol_status}\nBreakpoints in this module may not bind."
+                                                    ),
+                                                    cx,
+                                                )
+                                            }
+                                        }),
+                                )
+                            })
+                            .when(post_mortem, |this| {
+                                this.child(
+                                    Label::new("from core")
+                                        .size(LabelSize::XSmall)
+                                        .color(Color::Muted),
+                                )
+                            }),
+                    )
+                    // Shown at the end of the first line rather than on a line
+                    // of its own, so every row stays the same height.
+                    .when_some(module.address_range.as_deref(), |this, address_range| {
+                        this.child(
+                            div().flex_none().child(
+                                Label::new(format_address_range(address_range))
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted)
+                                    .buffer_font(cx),
+                            ),
+                        )
+                    }),
             )
+            .when_some(module.path, |this, path| {
+                this.child(
+                    div()
+                        .id(("module-path", ix))
+                        .text_ui_xs(cx)
+                        .text_color(cx.theme().colors().text_muted)
+                        .truncate()
+                        .when(path_is_truncated, |this| {
+                            this.tooltip(Tooltip::text(path.clone()))
+                        })
+                        .child(path),
+                )
+            })
             .into_any()
     }
 
+    #[cfg(test)]
+    pub(crate) fn post_mortem(&self, cx: &App) -> bool {
+        self.is_post_mortem(cx)
+    }
+
     #[cfg(test)]
     pub(crate) fn modules(&self, cx: &mut Context<Self>) -> Vec<dap::Module> {
         self.session
@@ -170,8 +642,13 @@ impl ModuleList {
 
     fn confirm(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
         let Some(ix) = self.selected_ix else { return };
-        let Some(entry) = self.entries.get(ix) else {
-            return;
+        let entry = match self.visible_entries.get(ix) {
+            Some(ModuleListEntry::Folder { folder, .. }) => {
+                self.toggle_folder(folder.clone(), cx);
+                return;
+            }
+            Some(ModuleListEntry::Module(module_ix)) => &self.entries[*module_ix],
+            None => return,
         };
         let Some(path) = entry.path.as_deref() else {
             return;
@@ -191,10 +668,10 @@ impl ModuleList {
 
     fn select_next(&mut self, _: &menu::SelectNext, _window: &mut Window, cx: &mut Context<Self>) {
         let ix = match self.selected_ix {
-            _ if self.entries.is_empty() => None,
+            _ if self.visible_entries.is_empty() => None,
             None => Some(0),
             Some(ix) => {
-                if ix == self.entries.len() - 1 {
+                if ix == self.visible_entries.len() - 1 {
                     Some(0)
                 } else {
                     Some(ix + 1)
@@ -211,11 +688,11 @@ impl ModuleList {
         cx: &mut Context<Self>,
     ) {
         let ix = match self.selected_ix {
-            _ if self.entries.is_empty() => None,
-            None => Some(self.entries.len() - 1),
+            _ if self.visible_entries.is_empty() => None,
+            None => Some(self.visible_entries.len() - 1),
             Some(ix) => {
                 if ix == 0 {
-                    Some(self.entries.len() - 1)
+                    Some(self.visible_entries.len() - 1)
                 } else {
                     Some(ix - 1)
                 }
@@ -230,7 +707,7 @@ impl ModuleList {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let ix = if !self.entries.is_empty() {
+        let ix = if !self.visible_entries.is_empty() {
             Some(0)
         } else {
             None
@@ -239,8 +716,8 @@ impl ModuleList {
     }
 
     fn select_last(&mut self, _: &menu::SelectLast, _window: &mut Window, cx: &mut Context<Self>) {
-        let ix = if !self.entries.is_empty() {
-            Some(self.entries.len() - 1)
+        let ix = if !self.visible_entries.is_empty() {
+            Some(self.visible_entries.len() - 1)
         } else {
             None
         };
@@ -248,18 +725,83 @@ impl ModuleList {
     }
 
     fn render_list(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        // Every row is as tall as the measured one, so measure a module rather
+        // than a folder header, which is only a single line.
+        let first_module_ix = self
+            .visible_entries
+            .iter()
+            .position(|entry| matches!(entry, ModuleListEntry::Module(_)));
         uniform_list(
             "module-list",
-            self.entries.len(),
-            cx.processor(|this, range: Range<usize>, _window, cx| {
-                range.map(|ix| this.render_entry(ix, cx)).collect()
+            self.visible_entries.len(),
+            cx.processor(|this, range: Range<usize>, window, cx| {
+                range.map(|ix| this.render_entry(ix, window, cx)).collect()
             }),
         )
+        .with_width_from_item(first_module_ix)
         .track_scroll(&self.scroll_handle)
         .size_full()
     }
 }
 
+/// Buckets modules by the folder that contains them, sorted by folder, and
+/// returns the indices of each folder's modules. Modules without a path are
+/// put in an "Unknown" group at the end.
+pub(crate) fn group_modules_by_folder(modules: &[Module]) -> Vec<(SharedString, Vec<usize>)> {
+    let mut folders = BTreeMap::<SharedString, Vec<usize>>::default();
+    let mut unknown = Vec::new();
+    for (ix, module) in modules.iter().enumerate() {
+        let folder = module
+            .path
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .map(|folder| folder.to_string_lossy())
+            .filter(|folder| !folder.is_empty());
+        match folder {
+            Some(folder) => folders
+                .entry(SharedString::from(folder.into_owned()))
+                .or_default()
+                .push(ix),
+            None => unknown.push(ix),
+        }
+    }
+    let mut groups = folders.into_iter().collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        groups.push((UNKNOWN_FOLDER.into(), unknown));
+    }
+    groups
+}
+
+/// Whether an adapter's symbol status, which is free-form text like "Symbols
+/// loaded." or "Cannot find or open the PDB file.", says symbols were loaded.
+pub(crate) fn symbols_loaded(symbol_status: &str) -> bool {
+    let symbol_status = symbol_status.to_lowercase();
+    symbol_status.contains("loaded")
+        && !["not", "no ", "can't", "failed", "unloaded"]
+            .iter()
+            .any(|negation| symbol_status.contains(negation))
+}
+
+/// Formats a module's address range as its start and end address and its
+/// size in hex. Adapters describe the range however they like, so ranges that
+/// aren't a pair of hex addresses, like `0x1000-0x2000`, are shown as-is.
+pub(crate) fn format_address_range(address_range: &str) -> String {
+    let parse_address = |address: &str| {
+        let address = address.trim();
+        let digits = address
+            .strip_prefix("0x")
+            .or_else(|| address.strip_prefix("0X"))
+            .unwrap_or(address);
+        u64::from_str_radix(digits, 16).ok()
+    };
+    address_range
+        .split_once('-')
+        .and_then(|(start, end)| Some((parse_address(start)?, parse_address(end)?)))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| format!("{start:#x}–{end:#x} ({:#x} bytes)", end - start))
+        .unwrap_or_else(|| address_range.to_string())
+}
+
 impl Focusable for ModuleList {
     fn focus_handle(&self, _: &gpui::App) -> gpui::FocusHandle {
         self.focus_handle.clone()
@@ -272,6 +814,7 @@ impl Render for ModuleList {
             self.schedule_rebuild(cx);
         }
         div()
+            .key_context("ModuleList")
             .track_focus(&self.focus_handle)
             .on_action(cx.listener(Self::select_last))
             .on_action(cx.listener(Self::select_first))
@@ -280,7 +823,22 @@ impl Render for ModuleList {
             .on_action(cx.listener(Self::confirm))
             .size_full()
             .p_1()
-            .child(self.render_list(window, cx))
-            .vertical_scrollbar_for(&self.scroll_handle, window, cx)
+            .child(
+                v_flex().size_full().child(self.render_toolbar(cx)).child(
+                    div()
+                        .size_full()
+                        .child(self.render_list(window, cx))
+                        .vertical_scrollbar_for(&self.scroll_handle, window, cx),
+                ),
+            )
+            .children(self.open_context_menu.as_ref().map(|(menu, position, _)| {
+                deferred(
+                    anchored()
+                        .position(*position)
+                        .anchor(gpui::Corner::TopLeft)
+                        .child(menu.clone()),
+                )
+                .with_priority(1)
+            }))
     }
 }
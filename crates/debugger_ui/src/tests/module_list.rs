@@ -1,22 +1,39 @@
 use crate::{
     debugger_panel::DebugPanel,
     persistence::DebuggerPaneItem,
-    tests::{active_debug_session_panel, init_test, init_test_workspace, start_debug_session},
+    session::running::{
+        RunningState,
+        module_list::{format_address_range, group_modules_by_folder, symbols_loaded},
+    },
+    tests::{active_debug_session_panel, init_test, init_test_workspace, start_debug_session_with},
 };
 use dap::{
     StoppedEvent,
+    adapters::DebugTaskDefinition,
+    client::DebugAdapterClient,
     requests::{Initialize, Modules},
 };
-use gpui::{BackgroundExecutor, TestAppContext, VisualTestContext};
+use gpui::{BackgroundExecutor, Entity, TestAppContext, VisualTestContext, WindowHandle};
 use project::{FakeFs, Project};
+use serde_json::json;
 use std::sync::{
     Arc,
     atomic::{AtomicBool, AtomicI32, Ordering},
 };
 use util::path;
+use workspace::Workspace;
 
-#[gpui::test]
-async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+/// Starts a debug session with the given launch config, against an adapter
+/// that supports the modules request.
+async fn start_session(
+    executor: BackgroundExecutor,
+    config: serde_json::Value,
+    cx: &mut TestAppContext,
+) -> (
+    WindowHandle<Workspace>,
+    Arc<DebugAdapterClient>,
+    VisualTestContext,
+) {
     init_test(cx);
 
     let fs = FakeFs::new(executor.clone());
@@ -28,68 +45,38 @@ async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext)
             workspace.focus_panel::<DebugPanel>(window, cx);
         })
         .unwrap();
-    let cx = &mut VisualTestContext::from_window(*workspace, cx);
-
-    let session = start_debug_session(&workspace, cx, |client| {
-        client.on_request::<Initialize, _>(move |_, _| {
-            Ok(dap::Capabilities {
-                supports_modules_request: Some(true),
-                ..Default::default()
-            })
-        });
-    })
-    .unwrap();
+    let mut cx = VisualTestContext::from_window(*workspace, cx);
 
-    let client = session.update(cx, |session, _| session.adapter_client().unwrap());
-
-    let called_modules = Arc::new(AtomicBool::new(false));
-    let modules = vec![
-        dap::Module {
-            id: dap::ModuleId::Number(1),
-            name: "First Module".into(),
-            address_range: None,
-            date_time_stamp: None,
-            path: None,
-            symbol_file_path: None,
-            symbol_status: None,
-            version: None,
-            is_optimized: None,
-            is_user_code: None,
+    let session = start_debug_session_with(
+        &workspace,
+        &mut cx,
+        DebugTaskDefinition {
+            adapter: "fake-adapter".into(),
+            label: "test".into(),
+            config,
+            tcp_connection: None,
         },
-        dap::Module {
-            id: dap::ModuleId::Number(2),
-            name: "Second Module".into(),
-            address_range: None,
-            date_time_stamp: None,
-            path: None,
-            symbol_file_path: None,
-            symbol_status: None,
-            version: None,
-            is_optimized: None,
-            is_user_code: None,
+        |client| {
+            client.on_request::<Initialize, _>(move |_, _| {
+                Ok(dap::Capabilities {
+                    supports_modules_request: Some(true),
+                    ..Default::default()
+                })
+            });
         },
-    ];
-
-    client.on_request::<Modules, _>({
-        let called_modules = called_modules.clone();
-        let modules_request_count = AtomicI32::new(0);
-        let modules = modules.clone();
-        move |_, _| {
-            modules_request_count.fetch_add(1, Ordering::SeqCst);
-            assert_eq!(
-                1,
-                modules_request_count.load(Ordering::SeqCst),
-                "This request should only be called once from the host"
-            );
-            called_modules.store(true, Ordering::SeqCst);
+    )
+    .unwrap();
 
-            Ok(dap::ModulesResponse {
-                modules: modules.clone(),
-                total_modules: Some(2u64),
-            })
-        }
-    });
+    let client = session.update(&mut cx, |session, _| session.adapter_client().unwrap());
+    (workspace, client, cx)
+}
 
+/// Stops the session and opens its module list, which requests the modules.
+async fn show_module_list(
+    workspace: WindowHandle<Workspace>,
+    client: &DebugAdapterClient,
+    cx: &mut VisualTestContext,
+) -> Entity<RunningState> {
     client
         .fake_event(dap::messages::Events::Stopped(StoppedEvent {
             reason: dap::StoppedEventReason::Pause,
@@ -116,6 +103,54 @@ async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext)
     });
 
     cx.run_until_parked();
+    running_state
+}
+
+fn module(id: u32, name: &str) -> dap::Module {
+    dap::Module {
+        id: dap::ModuleId::Number(id),
+        name: name.into(),
+        address_range: None,
+        date_time_stamp: None,
+        path: None,
+        symbol_file_path: None,
+        symbol_status: None,
+        version: None,
+        is_optimized: None,
+        is_user_code: None,
+    }
+}
+
+#[gpui::test]
+async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    let (workspace, client, mut cx) =
+        start_session(executor, json!({ "request": "launch" }), cx).await;
+    let cx = &mut cx;
+
+    let called_modules = Arc::new(AtomicBool::new(false));
+    let modules = vec![module(1, "First Module"), module(2, "Second Module")];
+
+    client.on_request::<Modules, _>({
+        let called_modules = called_modules.clone();
+        let modules_request_count = AtomicI32::new(0);
+        let modules = modules.clone();
+        move |_, _| {
+            modules_request_count.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(
+                1,
+                modules_request_count.load(Ordering::SeqCst),
+                "This request should only be called once from the host"
+            );
+            called_modules.store(true, Ordering::SeqCst);
+
+            Ok(dap::ModulesResponse {
+                modules: modules.clone(),
+                total_modules: Some(2u64),
+            })
+        }
+    });
+
+    let running_state = show_module_list(workspace, &client, cx).await;
 
     assert!(
         called_modules.load(std::sync::atomic::Ordering::SeqCst),
@@ -135,18 +170,7 @@ async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext)
     // Changed
     // Removed
 
-    let new_module = dap::Module {
-        id: dap::ModuleId::Number(3),
-        name: "Third Module".into(),
-        address_range: None,
-        date_time_stamp: None,
-        path: None,
-        symbol_file_path: None,
-        symbol_status: None,
-        version: None,
-        is_optimized: None,
-        is_user_code: None,
-    };
+    let new_module = module(3, "Third Module");
 
     client
         .fake_event(dap::messages::Events::Module(dap::ModuleEvent {
@@ -165,18 +189,7 @@ async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext)
         assert!(actual_modules.contains(&new_module));
     });
 
-    let changed_module = dap::Module {
-        id: dap::ModuleId::Number(2),
-        name: "Modified Second Module".into(),
-        address_range: None,
-        date_time_stamp: None,
-        path: None,
-        symbol_file_path: None,
-        symbol_status: None,
-        version: None,
-        is_optimized: None,
-        is_user_code: None,
-    };
+    let changed_module = module(2, "Modified Second Module");
 
     client
         .fake_event(dap::messages::Events::Module(dap::ModuleEvent {
@@ -214,3 +227,88 @@ async fn test_module_list(executor: BackgroundExecutor, cx: &mut TestAppContext)
         assert!(!actual_modules.contains(&changed_module));
     });
 }
+
+#[gpui::test]
+async fn test_module_list_from_core_dump(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    let config = json!({
+        "request": "launch",
+        "coreFile": path!("/project/core"),
+    });
+    let (workspace, client, mut cx) = start_session(executor, config, cx).await;
+    let cx = &mut cx;
+
+    let modules = vec![module(1, "First Module")];
+
+    client.on_request::<Modules, _>({
+        let modules = modules.clone();
+        move |_, _| {
+            Ok(dap::ModulesResponse {
+                modules: modules.clone(),
+                total_modules: Some(1u64),
+            })
+        }
+    });
+
+    let running_state = show_module_list(workspace, &client, cx).await;
+
+    running_state.update(cx, |state, cx| {
+        state.module_list().update(cx, |list, cx| {
+            assert!(list.post_mortem(cx));
+            assert_eq!(list.modules(cx), modules);
+        })
+    });
+}
+
+#[test]
+fn test_format_address_range() {
+    assert_eq!(
+        format_address_range("0x7ff6a0000000-0x7ff6a0012000"),
+        "0x7ff6a0000000–0x7ff6a0012000 (0x12000 bytes)"
+    );
+    assert_eq!(
+        format_address_range("1000 - 3000"),
+        "0x1000–0x3000 (0x2000 bytes)"
+    );
+    assert_eq!(format_address_range("0x2000-0x1000"), "0x2000-0x1000");
+    assert_eq!(format_address_range("main.exe+0x10"), "main.exe+0x10");
+}
+
+#[test]
+fn test_symbols_loaded() {
+    assert!(symbols_loaded("Symbols loaded."));
+    assert!(!symbols_loaded("Symbols not loaded."));
+    assert!(!symbols_loaded("Cannot find or open the PDB file."));
+    assert!(!symbols_loaded("Skipped loading symbols."));
+    assert!(!symbols_loaded("Symbols unloaded"));
+}
+
+#[test]
+fn test_group_modules_by_folder() {
+    let module = |name: &str, path: Option<&str>| dap::Module {
+        id: dap::ModuleId::String(name.into()),
+        name: name.into(),
+        address_range: None,
+        date_time_stamp: None,
+        path: path.map(Into::into),
+        symbol_file_path: None,
+        symbol_status: None,
+        version: None,
+        is_optimized: None,
+        is_user_code: None,
+    };
+    let modules = vec![
+        module("libc.so", Some("/usr/lib/libc.so")),
+        module("app", Some("/project/target/app")),
+        module("vdso", None),
+        module("libm.so", Some("/usr/lib/libm.so")),
+        module("relative.so", Some("relative.so")),
+    ];
+    assert_eq!(
+        group_modules_by_folder(&modules),
+        vec![
+            ("/project/target".into(), vec![1]),
+            ("/usr/lib".into(), vec![0, 3]),
+            ("Unknown".into(), vec![2, 4]),
+        ]
+    );
+}
@@ -7,10 +7,10 @@ use crate::{
     linked_editing_ranges::LinkedEditingRanges,
     scroll::scroll_amount::ScrollAmount,
     test::{
-        assert_text_with_selections, build_editor,
+        assert_text_with_selections, assert_text_with_selections_and_display, build_editor,
         editor_lsp_test_context::{EditorLspTestContext, git_commit_lang},
         editor_test_context::EditorTestContext,
-        select_ranges,
+        marked_editor_with_language, select_ranges,
     },
 };
 use buffer_diff::{BufferDiff, DiffHunkSecondaryStatus, DiffHunkStatus, DiffHunkStatusKind};
@@ -18,7 +18,7 @@ use collections::HashMap;
 use futures::{StreamExt, channel::oneshot};
 use gpui::{
     BackgroundExecutor, DismissEvent, Rgba, TestAppContext, UpdateGlobal, VisualTestContext,
-    WindowBounds, WindowOptions, div,
+    WindowBounds, WindowHandle, WindowOptions, div,
 };
 use indoc::indoc;
 use language::{
@@ -1036,6 +1036,84 @@ fn test_cancel(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+fn test_marked_editor_with_language(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    let cx = cx.add_empty_window();
+
+    let (editor, markers) = cx.update(|window, cx| {
+        marked_editor_with_language("# Heading\nˇbody", "Markdown", window, cx)
+    });
+    assert_eq!(markers, vec![DisplayPoint::new(DisplayRow(1), 0)]);
+    editor.update(cx, |editor, cx| {
+        assert_eq!(editor.text(cx), "# Heading\nbody");
+        let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+        assert_eq!(
+            buffer.read(cx).language().map(|language| language.name()),
+            Some("Markdown".into())
+        );
+    });
+
+    // Unknown languages fall back to plain text.
+    let (editor, markers) = cx.update(|window, cx| {
+        marked_editor_with_language("plain ˇtext", "Not A Language", window, cx)
+    });
+    assert_eq!(markers, vec![DisplayPoint::new(DisplayRow(0), 6)]);
+    editor.update(cx, |editor, cx| {
+        assert_eq!(editor.text(cx), "plain text");
+        let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+        assert!(buffer.read(cx).language().is_none());
+    });
+}
+
+#[gpui::test]
+fn test_assert_text_with_selections_and_display(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    let editor = build_folded_editor(cx);
+
+    _ = editor.update(cx, |editor, window, cx| {
+        select_ranges(editor, "one\ntwo\nthrˇee", window, cx);
+        assert_text_with_selections_and_display(editor, "one\ntwo\nthrˇee", Some("o⋯o\nthree"), cx);
+    });
+}
+
+#[gpui::test]
+#[should_panic(expected = "display text doesn't match")]
+fn test_assert_text_with_selections_and_display_mismatch(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    let editor = build_folded_editor(cx);
+
+    _ = editor.update(cx, |editor, window, cx| {
+        select_ranges(editor, "one\ntwo\nthrˇee", window, cx);
+        // The text matches, but the display text has the fold applied.
+        assert_text_with_selections_and_display(
+            editor,
+            "one\ntwo\nthrˇee",
+            Some("one\ntwo\nthree"),
+            cx,
+        );
+    });
+}
+
+fn build_folded_editor(cx: &mut TestAppContext) -> WindowHandle<Editor> {
+    let editor = cx.add_window(|window, cx| {
+        let buffer = MultiBuffer::build_simple("one\ntwo\nthree", cx);
+        build_editor(buffer, window, cx)
+    });
+    _ = editor.update(cx, |editor, window, cx| {
+        editor.fold_creases(
+            vec![Crease::simple(
+                Point::new(0, 1)..Point::new(1, 2),
+                FoldPlaceholder::test(),
+            )],
+            true,
+            window,
+            cx,
+        );
+    });
+    editor
+}
+
 #[gpui::test]
 fn test_fold_action(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -1,7 +1,10 @@
 pub mod editor_lsp_test_context;
 pub mod editor_test_context;
 
-use std::{rc::Rc, sync::LazyLock};
+use std::{
+    rc::Rc,
+    sync::{Arc, LazyLock},
+};
 
 pub use crate::rust_analyzer_ext::expand_macro_recursively;
 use crate::{
@@ -12,10 +15,12 @@ use crate::{
     },
 };
 use collections::HashMap;
+use futures::FutureExt as _;
 use gpui::{
     AppContext as _, Context, Entity, EntityId, Font, FontFeatures, FontStyle, FontWeight, Pixels,
     VisualTestContext, Window, font, size,
 };
+use language::{Buffer, LanguageRegistry};
 use multi_buffer::{MultiBufferOffset, ToPoint};
 use pretty_assertions::assert_eq;
 use project::{Project, project_settings::DiagnosticSeverity};
@@ -84,6 +89,44 @@ pub fn marked_display_snapshot(
     (snapshot, markers)
 }
 
+/// Builds an editor over a single buffer using the test language named `language_name`
+/// (e.g. "Markdown" or "Rust"), returning it with the display points for each `ˇ` marker.
+/// Unknown languages fall back to plain text. Call `run_until_parked` before asserting on
+/// anything that depends on the syntax tree.
+#[track_caller]
+pub fn marked_editor_with_language(
+    marked_text: &str,
+    language_name: &str,
+    window: &mut Window,
+    cx: &mut App,
+) -> (Entity<Editor>, Vec<DisplayPoint>) {
+    let (unmarked_text, markers) = marked_text_offsets(marked_text);
+
+    let language_registry = Arc::new(LanguageRegistry::test(cx.background_executor().clone()));
+    language_registry.add(language::markdown_lang());
+    language_registry.add(language::rust_lang());
+    let language = language_registry
+        .language_for_name(language_name)
+        .now_or_never()
+        .and_then(Result::ok);
+
+    let buffer = cx.new(|cx| {
+        let mut buffer = Buffer::local(unmarked_text, cx);
+        buffer.set_language_registry(language_registry);
+        buffer.set_language(language, cx);
+        buffer
+    });
+    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx));
+    let editor = cx.new(|cx| build_editor(buffer, window, cx));
+    let snapshot = editor.update(cx, |editor, cx| editor.display_snapshot(cx));
+    let markers = markers
+        .into_iter()
+        .map(|offset| MultiBufferOffset(offset).to_display_point(&snapshot))
+        .collect();
+
+    (editor, markers)
+}
+
 #[track_caller]
 pub fn select_ranges(
     editor: &mut Editor,
@@ -107,9 +150,28 @@ pub fn assert_text_with_selections(
     editor: &mut Editor,
     marked_text: &str,
     cx: &mut Context<Editor>,
+) {
+    assert_text_with_selections_and_display(editor, marked_text, None, cx);
+}
+
+/// Like [`assert_text_with_selections`], but also checks the editor's display text
+/// (with wraps and folds applied) when `expected_display_text` is provided.
+#[track_caller]
+pub fn assert_text_with_selections_and_display(
+    editor: &mut Editor,
+    marked_text: &str,
+    expected_display_text: Option<&str>,
+    cx: &mut Context<Editor>,
 ) {
     let (unmarked_text, _text_ranges) = marked_text_ranges(marked_text, true);
     assert_eq!(editor.text(cx), unmarked_text, "text doesn't match");
+    if let Some(expected_display_text) = expected_display_text {
+        assert_eq!(
+            editor.display_text(cx),
+            expected_display_text,
+            "display text doesn't match"
+        );
+    }
     let actual = generate_marked_text(
         &editor.text(cx),
         &editor
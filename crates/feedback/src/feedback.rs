@@ -1,6 +1,13 @@
-use gpui::{App, ClipboardItem, PromptLevel, actions};
-use system_specs::{CopySystemSpecsIntoClipboard, SystemSpecs};
-use util::ResultExt;
+use collections::HashMap;
+use extension_host::ExtensionStore;
+use fs::Fs;
+use gpui::{
+    App, AppContext as _, ClipboardItem, Context, Global, PromptLevel, SharedString, Task, Window,
+    actions,
+};
+use std::{collections::VecDeque, fmt::Write as _, sync::Arc};
+use system_specs::{CopySystemSpecsIntoClipboard, ProjectSummary, SystemSpecs};
+use util::{ResultExt, redact::should_redact};
 use workspace::Workspace;
 use zed_actions::feedback::{EmailZed, FileBugReport, RequestFeature};
 
@@ -9,6 +16,12 @@ actions!(
     [
         /// Opens the Zed repository on GitHub.
         OpenZedRepo,
+        /// Copies a redacted bundle of system specs, settings, extensions and
+        /// recent logs to the clipboard for attaching to a report.
+        CopyDiagnosticBundle,
+        /// Saves a redacted bundle of system specs, settings, extensions and
+        /// recent logs to a file for attaching to a report.
+        SaveDiagnosticBundle,
     ]
 );
 
@@ -16,6 +29,77 @@ const ZED_REPO_URL: &str = "https://github.com/zed-industries/zed";
 
 const REQUEST_FEATURE_URL: &str = "https://github.com/zed-industries/zed/discussions/new/choose";
 
+/// How many files to look at when guessing a project's primary languages.
+const MAX_FILES_TO_SAMPLE: usize = 10_000;
+const MAX_PRIMARY_LANGUAGES: usize = 3;
+
+const DIAGNOSTIC_BUNDLE_LOG_LINES: usize = 200;
+const DIAGNOSTIC_BUNDLE_FILE_NAME: &str = "zed-diagnostics.txt";
+
+/// An extra feedback entry, such as "Contact Support", that opens a URL. Zed
+/// distributions register these at init time with [`register_feedback_link`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeedbackLink {
+    pub label: SharedString,
+    pub url: SharedString,
+}
+
+impl FeedbackLink {
+    /// The action that opens the link, for menus and other feedback UI.
+    pub fn action(&self) -> zed_actions::OpenBrowser {
+        zed_actions::OpenBrowser {
+            url: self.url.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FeedbackLinks(Vec<FeedbackLink>);
+
+impl Global for FeedbackLinks {}
+
+impl FeedbackLinks {
+    /// Adds the link, or points an existing link with the same label at the
+    /// new URL, so registering twice never shows the entry twice.
+    fn register(&mut self, link: FeedbackLink) {
+        match self
+            .0
+            .iter_mut()
+            .find(|existing| existing.label == link.label)
+        {
+            Some(existing) if existing.url != link.url => {
+                log::warn!(
+                    "feedback link {:?} was registered again with a different URL",
+                    link.label
+                );
+                existing.url = link.url;
+            }
+            Some(_) => {}
+            None => self.0.push(link),
+        }
+    }
+}
+
+/// Adds an entry to the feedback UI that opens `url`, after the built-in
+/// ones. Entries keep the order they were first registered in.
+pub fn register_feedback_link(
+    label: impl Into<SharedString>,
+    url: impl Into<SharedString>,
+    cx: &mut App,
+) {
+    cx.default_global::<FeedbackLinks>().register(FeedbackLink {
+        label: label.into(),
+        url: url.into(),
+    });
+}
+
+/// The feedback links registered so far.
+pub fn feedback_links(cx: &App) -> Vec<FeedbackLink> {
+    cx.try_global::<FeedbackLinks>()
+        .map(|links| links.0.clone())
+        .unwrap_or_default()
+}
+
 fn file_bug_report_url(specs: &SystemSpecs) -> String {
     format!(
         concat!(
@@ -41,6 +125,167 @@ fn email_body(specs: &SystemSpecs) -> String {
     urlencoding::encode(&body).to_string()
 }
 
+fn project_summary(workspace: &Workspace, cx: &App) -> Option<ProjectSummary> {
+    let project = workspace.project().read(cx);
+    let worktrees = project.visible_worktrees(cx).collect::<Vec<_>>();
+    if worktrees.is_empty() {
+        return None;
+    }
+
+    let mut file_count = 0;
+    let mut extension_counts = HashMap::<String, usize>::default();
+    for worktree in &worktrees {
+        let worktree = worktree.read(cx);
+        file_count += worktree.file_count();
+        for entry in worktree.files(false, 0).take(MAX_FILES_TO_SAMPLE) {
+            if let Some(extension) = entry.path.extension() {
+                *extension_counts.entry(extension.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    let languages = project.languages();
+    let mut language_counts = HashMap::<String, usize>::default();
+    for (extension, count) in extension_counts {
+        if let Some(language) = languages.language_name_for_extension(&extension) {
+            *language_counts.entry(language.to_string()).or_default() += count;
+        }
+    }
+    let mut language_counts = language_counts.into_iter().collect::<Vec<_>>();
+    language_counts.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+    });
+    let primary_languages = language_counts
+        .into_iter()
+        .take(MAX_PRIMARY_LANGUAGES)
+        .map(|(name, _)| name)
+        .collect();
+
+    Some(ProjectSummary::new(
+        primary_languages,
+        file_count,
+        project.is_remote(),
+    ))
+}
+
+/// Gathers the system specs for a report, asking whether to also include a
+/// redacted summary of the current project. The summary is shown in the
+/// prompt so it can be reviewed before it's sent.
+fn system_specs_for_report(
+    workspace: &Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Task<SystemSpecs> {
+    let specs = SystemSpecs::new(window, cx);
+    let Some(summary) = project_summary(workspace, cx) else {
+        return specs;
+    };
+
+    let detail = format!("{summary}\n\nFile paths and names are never included.");
+    let answer = window.prompt(
+        PromptLevel::Info,
+        "Include a summary of this project?",
+        Some(&detail),
+        &["Include", "Don't Include"],
+        cx,
+    );
+    cx.background_spawn(async move {
+        let specs = specs.await;
+        if answer.await == Ok(0) {
+            specs.with_project_summary(summary)
+        } else {
+            specs
+        }
+    })
+}
+
+/// Assembles system specs, installed extensions, user settings and the tail
+/// of the log into one report. Every section goes through [`redact_line`].
+fn diagnostic_bundle(
+    fs: Arc<dyn Fs>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Task<String> {
+    let specs = SystemSpecs::new(window, cx);
+    let extensions = ExtensionStore::try_global(cx)
+        .map(|store| {
+            store
+                .read(cx)
+                .installed_extensions()
+                .values()
+                .map(|extension| {
+                    format!(
+                        "{} {}{}",
+                        extension.manifest.id,
+                        extension.manifest.version,
+                        if extension.dev { " (dev)" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+
+    cx.background_spawn(async move {
+        let (settings, log) =
+            futures::join!(fs.load(paths::settings_file()), fs.load(paths::log_file()));
+        let log = log.map(|log| {
+            let mut lines = VecDeque::with_capacity(DIAGNOSTIC_BUNDLE_LOG_LINES);
+            for line in log.lines() {
+                if lines.len() == DIAGNOSTIC_BUNDLE_LOG_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+            lines.into_iter().collect::<Vec<_>>().join("\n")
+        });
+
+        let sections = [
+            ("System Specs", specs.await.to_string()),
+            ("Extensions", extensions),
+            ("Settings", settings.unwrap_or_default()),
+            ("Log", log.unwrap_or_default()),
+        ];
+        let home_dir = paths::home_dir().to_string_lossy().into_owned();
+        let mut bundle = String::new();
+        for (title, contents) in sections {
+            writeln!(bundle, "===== {title} =====").ok();
+            if contents.trim().is_empty() {
+                bundle.push_str("(none)\n");
+            }
+            for line in contents.lines() {
+                bundle.push_str(&redact_line(line, &home_dir));
+                bundle.push('\n');
+            }
+            bundle.push('\n');
+        }
+        bundle
+    })
+}
+
+/// Replaces the home directory with `~` and drops the value following any
+/// key that looks like a secret (`"api_key": ...`, `GITHUB_TOKEN=...`).
+fn redact_line(line: &str, home_dir: &str) -> String {
+    let mut line = if home_dir.is_empty() {
+        line.to_string()
+    } else {
+        line.replace(home_dir, "~")
+    };
+    let secret_start = line.match_indices([':', '=']).find_map(|(ix, _)| {
+        let key = line[..ix]
+            .split_whitespace()
+            .next_back()
+            .unwrap_or_default()
+            .trim_matches(['"', '\'']);
+        should_redact(&key.to_ascii_uppercase()).then_some(ix + 1)
+    });
+    if let Some(secret_start) = secret_start {
+        line.truncate(secret_start);
+        line.push_str(" <redacted>");
+    }
+    line
+}
+
 pub fn init(cx: &mut App) {
     cx.observe_new(|workspace: &mut Workspace, _, _| {
         workspace
@@ -68,8 +313,8 @@ pub fn init(cx: &mut App) {
             .register_action(|_, _: &RequestFeature, _, cx| {
                 cx.open_url(REQUEST_FEATURE_URL);
             })
-            .register_action(move |_, _: &FileBugReport, window, cx| {
-                let specs = SystemSpecs::new(window, cx);
+            .register_action(move |workspace, _: &FileBugReport, window, cx| {
+                let specs = system_specs_for_report(workspace, window, cx);
                 cx.spawn_in(window, async move |_, cx| {
                     let specs = specs.await;
                     cx.update(|_, cx| {
@@ -79,8 +324,8 @@ pub fn init(cx: &mut App) {
                 })
                 .detach();
             })
-            .register_action(move |_, _: &EmailZed, window, cx| {
-                let specs = SystemSpecs::new(window, cx);
+            .register_action(move |workspace, _: &EmailZed, window, cx| {
+                let specs = system_specs_for_report(workspace, window, cx);
                 cx.spawn_in(window, async move |_, cx| {
                     let specs = specs.await;
                     cx.update(|_, cx| {
@@ -92,7 +337,75 @@ pub fn init(cx: &mut App) {
             })
             .register_action(move |_, _: &OpenZedRepo, _, cx| {
                 cx.open_url(ZED_REPO_URL);
+            })
+            .register_action(|workspace, _: &CopyDiagnosticBundle, window, cx| {
+                let fs = workspace.app_state().fs.clone();
+                let bundle = diagnostic_bundle(fs, window, cx);
+                cx.spawn_in(window, async move |_, cx| {
+                    let bundle = bundle.await;
+                    cx.update(|_, cx| cx.write_to_clipboard(ClipboardItem::new_string(bundle)))
+                        .log_err();
+                })
+                .detach();
+            })
+            .register_action(|workspace, _: &SaveDiagnosticBundle, window, cx| {
+                let fs = workspace.app_state().fs.clone();
+                let bundle = diagnostic_bundle(fs.clone(), window, cx);
+                let path =
+                    cx.prompt_for_new_path(paths::home_dir(), Some(DIAGNOSTIC_BUNDLE_FILE_NAME));
+                cx.spawn_in(window, async move |_, _| {
+                    let Some(path) = path.await?? else {
+                        return anyhow::Ok(());
+                    };
+                    fs.atomic_write(path, bundle.await).await
+                })
+                .detach_and_log_err(cx);
             });
     })
     .detach();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_feedback_link() {
+        let link = |label: &'static str, url: &'static str| FeedbackLink {
+            label: label.into(),
+            url: url.into(),
+        };
+        let mut links = FeedbackLinks::default();
+        links.register(link("Contact Support", "https://example.com/support"));
+        links.register(link("Community Forum", "https://example.com/forum"));
+        links.register(link("Contact Support", "https://example.com/support"));
+        links.register(link("Community Forum", "https://example.com/community"));
+        assert_eq!(
+            links.0,
+            vec![
+                link("Contact Support", "https://example.com/support"),
+                link("Community Forum", "https://example.com/community"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redact_line() {
+        assert_eq!(
+            redact_line(r#"  "api_key": "sk-123","#, "/home/me"),
+            r#"  "api_key": <redacted>"#
+        );
+        assert_eq!(
+            redact_line("2024-01-01T00:00:00 INFO GITHUB_TOKEN=abc", "/home/me"),
+            "2024-01-01T00:00:00 INFO GITHUB_TOKEN= <redacted>"
+        );
+        assert_eq!(
+            redact_line("opened /home/me/project/main.rs", "/home/me"),
+            "opened ~/project/main.rs"
+        );
+        assert_eq!(
+            redact_line(r#""theme": "One Dark","#, "/home/me"),
+            r#""theme": "One Dark","#
+        );
+    }
+}
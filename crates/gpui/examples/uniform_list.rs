@@ -1,37 +1,71 @@
 use gpui::{
-    App, Application, Bounds, Context, Window, WindowBounds, WindowOptions, div, prelude::*, px,
-    rgb, size, uniform_list,
+    App, Application, Bounds, Context, Pixels, Point, UniformListScrollbar, Window, WindowBounds,
+    WindowOptions, anchored, deferred, div, prelude::*, px, rgb, size, uniform_list,
 };
 
-struct UniformListExample {}
+struct UniformListExample {
+    /// The right-clicked item and where it was clicked.
+    context_menu: Option<(usize, Point<Pixels>)>,
+}
 
 impl Render for UniformListExample {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        div().size_full().bg(rgb(0xffffff)).child(
-            uniform_list(
-                "entries",
-                50,
-                cx.processor(|_this, range, _window, _cx| {
-                    let mut items = Vec::new();
-                    for ix in range {
-                        let item = ix + 1;
+        div()
+            .size_full()
+            .bg(rgb(0xffffff))
+            .child(
+                uniform_list(
+                    "entries",
+                    50,
+                    cx.processor(|_this, range, _window, _cx| {
+                        let mut items = Vec::new();
+                        for ix in range {
+                            let item = ix + 1;
 
-                        items.push(
-                            div()
-                                .id(ix)
-                                .px_2()
-                                .cursor_pointer()
-                                .on_click(move |_event, _window, _cx| {
-                                    println!("clicked Item {item:?}");
-                                })
-                                .child(format!("Item {item}")),
-                        );
+                            items.push(
+                                div()
+                                    .id(ix)
+                                    .px_2()
+                                    .cursor_pointer()
+                                    .on_click(move |_event, _window, _cx| {
+                                        println!("clicked Item {item:?}");
+                                    })
+                                    .child(format!("Item {item}")),
+                            );
+                        }
+                        items
+                    }),
+                )
+                .on_secondary_click({
+                    let this = cx.entity().downgrade();
+                    move |ix, event, _window, cx| {
+                        this.update(cx, |this, cx| {
+                            this.context_menu = Some((ix, event.position));
+                            cx.notify();
+                        })
+                        .ok();
                     }
-                    items
-                }),
+                })
+                .scrollbar(UniformListScrollbar::default().width(px(6.)).autohide())
+                .h_full(),
             )
-            .h_full(),
-        )
+            .children(self.context_menu.map(|(ix, position)| {
+                deferred(
+                    anchored().position(position).child(
+                        div()
+                            .id("context-menu")
+                            .p_1()
+                            .bg(rgb(0xeeeeee))
+                            .border_1()
+                            .border_color(rgb(0x888888))
+                            .on_mouse_down_out(cx.listener(|this, _, _, cx| {
+                                this.context_menu = None;
+                                cx.notify();
+                            }))
+                            .child(format!("Item {}", ix + 1)),
+                    ),
+                )
+            }))
     }
 }
 
@@ -43,7 +77,7 @@ fn main() {
                 window_bounds: Some(WindowBounds::Windowed(bounds)),
                 ..Default::default()
             },
-            |_, cx| cx.new(|_| UniformListExample {}),
+            |_, cx| cx.new(|_| UniformListExample { context_menu: None }),
         )
         .unwrap();
     });
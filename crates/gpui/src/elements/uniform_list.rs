@@ -5,13 +5,21 @@
 //! elements with uniform height.
 
 use crate::{
-    AnyElement, App, AvailableSpace, Bounds, ContentMask, Element, ElementId, Entity,
-    GlobalElementId, Hitbox, InspectorElementId, InteractiveElement, Interactivity, IntoElement,
-    IsZero, LayoutId, ListSizingBehavior, Overflow, Pixels, Point, ScrollHandle, Size,
-    StyleRefinement, Styled, Window, point, size,
+    AnyElement, App, AvailableSpace, Bounds, ContentMask, DispatchPhase, Element, ElementId,
+    Entity, GlobalElementId, Hitbox, HitboxBehavior, InspectorElementId, InteractiveElement,
+    Interactivity, IntoElement, IsZero, LayoutId, ListSizingBehavior, MouseButton, MouseDownEvent,
+    MouseMoveEvent, Overflow, Pixels, Point, ScrollHandle, Size, StyleRefinement, Styled, Window,
+    fill, hsla, point, px, size,
 };
 use smallvec::SmallVec;
-use std::{cell::RefCell, cmp, ops::Range, rc::Rc, usize};
+use std::{
+    cell::RefCell,
+    cmp,
+    ops::Range,
+    rc::Rc,
+    time::{Duration, Instant},
+    usize,
+};
 
 use super::ListHorizontalSizingBehavior;
 
@@ -42,6 +50,8 @@ where
         item_count,
         item_to_measure_index: 0,
         render_items: Box::new(render_range),
+        item_key: None,
+        scrollbar: None,
         decorations: Vec::new(),
         interactivity: Interactivity {
             element_id: Some(id),
@@ -51,6 +61,8 @@ where
         scroll_handle: None,
         sizing_behavior: ListSizingBehavior::default(),
         horizontal_sizing_behavior: ListHorizontalSizingBehavior::default(),
+        on_end_reached: None,
+        on_secondary_click: None,
     }
 }
 
@@ -61,17 +73,135 @@ pub struct UniformList {
     render_items: Box<
         dyn for<'a> Fn(Range<usize>, &'a mut Window, &'a mut App) -> SmallVec<[AnyElement; 64]>,
     >,
+    item_key: Option<Box<dyn Fn(usize, &mut Window, &mut App) -> ElementId>>,
+    scrollbar: Option<UniformListScrollbar>,
     decorations: Vec<Box<dyn UniformListDecoration>>,
     interactivity: Interactivity,
     scroll_handle: Option<UniformListScrollHandle>,
     sizing_behavior: ListSizingBehavior,
     horizontal_sizing_behavior: ListHorizontalSizingBehavior,
+    on_end_reached: Option<EndReachedListener>,
+    on_secondary_click: Option<Rc<SecondaryClickListener>>,
+}
+
+type SecondaryClickListener = dyn Fn(usize, &MouseDownEvent, &mut Window, &mut App);
+
+struct EndReachedListener {
+    threshold: usize,
+    callback: Rc<dyn Fn(&mut Window, &mut App)>,
+}
+
+/// Tracks which item count [`UniformList::on_end_reached`] last fired for, so
+/// that it fires once per batch of items rather than on every frame.
+#[derive(Default)]
+struct EndReachedState {
+    fired_at_item_count: Option<usize>,
+}
+
+/// How long an auto-hiding scrollbar stays visible after the list stops scrolling.
+const SCROLLBAR_HIDE_DELAY: Duration = Duration::from_secs(1);
+/// How long an auto-hiding scrollbar takes to fade out once the delay has passed.
+const SCROLLBAR_FADE_DURATION: Duration = Duration::from_millis(400);
+const SCROLLBAR_MIN_THUMB_HEIGHT: Pixels = px(16.);
+
+/// The vertical scrollbar of a [`UniformList`], added with [`UniformList::scrollbar`].
+/// It's painted over the right edge of the list, and clicking its track scrolls there.
+#[derive(Clone, Copy, Debug)]
+pub struct UniformListScrollbar {
+    width: Pixels,
+    autohide: bool,
+}
+
+impl Default for UniformListScrollbar {
+    fn default() -> Self {
+        Self {
+            width: px(8.),
+            autohide: false,
+        }
+    }
+}
+
+impl UniformListScrollbar {
+    /// Sets the thickness of the scrollbar, which defaults to 8px.
+    pub fn width(mut self, width: Pixels) -> Self {
+        self.width = width.max(px(1.));
+        self
+    }
+
+    /// Hides the scrollbar unless the list is hovered or was just scrolled, fading
+    /// it out once scrolling stops. Clicking the track still scrolls the list
+    /// while the scrollbar is hidden.
+    pub fn autohide(mut self) -> Self {
+        self.autohide = true;
+        self
+    }
+
+    /// How opaque the scrollbar is, given whether the list is hovered and how
+    /// long ago it was last scrolled.
+    fn opacity(&self, hovered: bool, since_scrolled: Option<Duration>) -> f32 {
+        if !self.autohide || hovered {
+            return 1.;
+        }
+        let Some(since_scrolled) = since_scrolled else {
+            return 0.;
+        };
+        let fading_for = since_scrolled.saturating_sub(SCROLLBAR_HIDE_DELAY);
+        (1. - fading_for.as_secs_f32() / SCROLLBAR_FADE_DURATION.as_secs_f32()).clamp(0., 1.)
+    }
+}
+
+/// Where the scrollbar was laid out in the last prepaint.
+struct ScrollbarLayout {
+    track_hitbox: Hitbox,
+    thumb: Bounds<Pixels>,
+    /// How far the list can scroll, which the thumb's travel along the track spans.
+    max_scroll: Pixels,
+    scroll_offset: Point<Pixels>,
+}
+
+/// Tracks when the list was last scrolled, so that an auto-hiding scrollbar can
+/// be shown while scrolling.
+#[derive(Default)]
+struct ScrollbarState {
+    scroll_offset: Option<Point<Pixels>>,
+    scrolled_at: Option<Instant>,
 }
 
 /// Frame state used by the [UniformList].
 pub struct UniformListFrameState {
     items: SmallVec<[AnyElement; 32]>,
+    item_keys: SmallVec<[Option<ElementId>; 32]>,
     decorations: SmallVec<[AnyElement; 2]>,
+    item_layout: Option<ItemLayout>,
+    scrollbar: Option<ScrollbarLayout>,
+}
+
+/// Where the items were laid out in the last prepaint, used to find the item
+/// under the mouse.
+#[derive(Clone, Copy)]
+struct ItemLayout {
+    /// The origin of the first item, including the scroll offset.
+    origin: Point<Pixels>,
+    item_height: Pixels,
+    item_count: usize,
+    y_flipped: bool,
+}
+
+impl ItemLayout {
+    fn item_ix_at(&self, position: Point<Pixels>) -> Option<usize> {
+        if position.y < self.origin.y || self.item_height <= Pixels::ZERO {
+            return None;
+        }
+        let ix = ((position.y - self.origin.y) / self.item_height).floor() as usize;
+        if ix >= self.item_count {
+            return None;
+        }
+        Some(if self.y_flipped {
+            self.item_count - ix - 1
+        } else {
+            ix
+        })
+    }
 }
 
 /// A handle for controlling the scroll position of a uniform list.
@@ -115,8 +245,12 @@ pub struct DeferredScrollToItem {
 pub struct UniformListScrollState {
     pub base_handle: ScrollHandle,
     pub deferred_scroll_to_item: Option<DeferredScrollToItem>,
+    /// Scroll offset to apply during the next layout, clamped to the list's content.
+    pub deferred_scroll_offset: Option<Point<Pixels>>,
     /// Size of the item, captured during last layout.
     pub last_item_size: Option<ItemSize>,
+    /// Height of a single list item, measured during last layout.
+    pub last_item_height: Option<Pixels>,
     /// Whether the list was vertically flipped during last layout.
     pub y_flipped: bool,
 }
@@ -137,11 +271,32 @@ impl UniformListScrollHandle {
         Self(Rc::new(RefCell::new(UniformListScrollState {
             base_handle: ScrollHandle::new(),
             deferred_scroll_to_item: None,
+            deferred_scroll_offset: None,
             last_item_size: None,
+            last_item_height: None,
             y_flipped: false,
         })))
     }
 
+    /// Create a scroll handle that starts at the given offset, such as one saved with
+    /// [`Self::scroll_offset`] before the list was recreated.
+    pub fn with_scroll_offset(offset: Point<Pixels>) -> Self {
+        let handle = Self::new();
+        handle.set_scroll_offset(offset);
+        handle
+    }
+
+    /// Get the current scroll offset of the list.
+    pub fn scroll_offset(&self) -> Point<Pixels> {
+        self.0.borrow().base_handle.offset()
+    }
+
+    /// Scroll the list to the given offset. The offset is applied during the next layout,
+    /// before any items are painted, and is clamped to the list's content.
+    pub fn set_scroll_offset(&self, offset: Point<Pixels>) {
+        self.0.borrow_mut().deferred_scroll_offset = Some(offset);
+    }
+
     /// Scroll the list so that the given item index is visible.
     ///
     /// This uses non-strict scrolling: if the item is already fully visible, no scrolling occurs.
@@ -236,6 +391,14 @@ impl UniformListScrollHandle {
         }
     }
 
+    /// The total height of the contents of a list with `item_count` items,
+    /// without rendering it. Returns `None` until the list has measured its
+    /// items during its first layout.
+    pub fn content_height(&self, item_count: usize) -> Option<Pixels> {
+        let item_height = self.0.borrow().last_item_height?;
+        Some(content_height(item_height, item_count))
+    }
+
     /// Scroll to the bottom of the list.
     pub fn scroll_to_bottom(&self) {
         self.scroll_to_item(usize::MAX, ScrollStrategy::Bottom);
@@ -311,7 +474,10 @@ impl Element for UniformList {
             layout_id,
             UniformListFrameState {
                 items: SmallVec::new(),
+                item_keys: SmallVec::new(),
                 decorations: SmallVec::new(),
+                item_layout: None,
+                scrollbar: None,
             },
         )
     }
@@ -352,7 +518,7 @@ impl Element for UniformList {
         };
         let content_size = Size {
             width: content_width,
-            height: longest_item_size.height * self.item_count,
+            height: content_height(longest_item_size.height, self.item_count),
         };
 
         let shared_scroll_offset = self.interactivity.scroll_offset.clone().unwrap();
@@ -363,10 +529,16 @@ impl Element for UniformList {
                 item: padded_bounds.size,
                 contents: content_size,
             });
+            handle.last_item_height = Some(item_height);
             handle.deferred_scroll_to_item.take()
         });
+        let deferred_scroll_offset = self
+            .scroll_handle
+            .as_mut()
+            .and_then(|handle| handle.0.borrow_mut().deferred_scroll_offset.take());
 
-        self.interactivity.prepaint(
+        let mut reached_end = false;
+        let hitbox = self.interactivity.prepaint(
             global_id,
             inspector_id,
             bounds,
@@ -381,8 +553,18 @@ impl Element for UniformList {
                     false
                 };
 
+                if let Some(offset) = deferred_scroll_offset {
+                    let max_scroll_offset =
+                        (content_size - padded_bounds.size).max(&Size::default());
+                    scroll_offset = point(
+                        offset.x.clamp(-max_scroll_offset.width, Pixels::ZERO),
+                        offset.y.clamp(-max_scroll_offset.height, Pixels::ZERO),
+                    );
+                    *shared_scroll_offset.borrow_mut() = scroll_offset;
+                }
+
                 if self.item_count > 0 {
-                    let content_height = item_height * self.item_count;
+                    let content_height = content_size.height;
 
                     let is_scrolled_vertically = !scroll_offset.y.is_zero();
                     let max_scroll_offset = padded_bounds.size.height - content_height;
@@ -458,6 +640,13 @@ impl Element for UniformList {
                         scroll_offset = *updated_scroll_offset
                     }
 
+                    frame_state.item_layout = Some(ItemLayout {
+                        origin: padded_bounds.origin + scroll_offset,
+                        item_height,
+                        item_count: self.item_count,
+                        y_flipped,
+                    });
+
                     let first_visible_element_ix =
                         (-(scroll_offset.y + padding.top) / item_height).floor() as usize;
                     let last_visible_element_ix = ((-scroll_offset.y + padded_bounds.size.height)
@@ -467,6 +656,14 @@ impl Element for UniformList {
                     let visible_range = first_visible_element_ix
                         ..cmp::min(last_visible_element_ix, self.item_count);
 
+                    if let Some(listener) = &self.on_end_reached {
+                        reached_end = if y_flipped {
+                            visible_range.start <= listener.threshold
+                        } else {
+                            visible_range.end + listener.threshold >= self.item_count
+                        };
+                    }
+
                     let items = if y_flipped {
                         let flipped_range = self.item_count.saturating_sub(visible_range.end)
                             ..self.item_count.saturating_sub(visible_range.start);
@@ -493,9 +690,20 @@ impl Element for UniformList {
                                 AvailableSpace::Definite(available_width),
                                 AvailableSpace::Definite(item_height),
                             );
-                            item.layout_as_root(available_space, window, cx);
-                            item.prepaint_at(item_origin, window, cx);
+                            let key = self.item_key.as_ref().map(|item_key| {
+                                let item_ix = if y_flipped {
+                                    self.item_count.saturating_sub(ix + 1)
+                                } else {
+                                    ix
+                                };
+                                item_key(item_ix, window, cx)
+                            });
+                            with_item_namespace(key.as_ref(), window, |window| {
+                                item.layout_as_root(available_space, window, cx);
+                                item.prepaint_at(item_origin, window, cx);
+                            });
                             frame_state.items.push(item);
+                            frame_state.item_keys.push(key);
                         }
 
                         let bounds =
@@ -523,7 +731,54 @@ impl Element for UniformList {
 
                 hitbox
             },
-        )
+        );
+
+        // Inserted after the items' hitboxes, so that the track is in front of them.
+        let max_scroll = content_size.height - padded_bounds.size.height;
+        if let Some(scrollbar) = self.scrollbar
+            && max_scroll > Pixels::ZERO
+        {
+            let scroll_offset = *shared_scroll_offset.borrow();
+            let track = Bounds::new(
+                point(bounds.right() - scrollbar.width, bounds.top()),
+                size(scrollbar.width, bounds.size.height),
+            );
+            let thumb_height = (track.size.height
+                * (padded_bounds.size.height / content_size.height))
+                .max(SCROLLBAR_MIN_THUMB_HEIGHT)
+                .min(track.size.height);
+            let scrolled_fraction = (-scroll_offset.y / max_scroll).clamp(0., 1.);
+            let thumb = Bounds::new(
+                point(
+                    track.left(),
+                    track.top() + (track.size.height - thumb_height) * scrolled_fraction,
+                ),
+                size(track.size.width, thumb_height),
+            );
+            frame_state.scrollbar = Some(ScrollbarLayout {
+                track_hitbox: window.insert_hitbox(track, HitboxBehavior::Normal),
+                thumb,
+                max_scroll,
+                scroll_offset,
+            });
+        }
+
+        if let Some((global_id, listener)) = global_id.zip(self.on_end_reached.as_ref()) {
+            let item_count = self.item_count;
+            let should_fire =
+                window.with_element_state(global_id, |state: Option<EndReachedState>, _| {
+                    let mut state = state.unwrap_or_default();
+                    let should_fire = reached_end && state.fired_at_item_count != Some(item_count);
+                    state.fired_at_item_count = reached_end.then_some(item_count);
+                    (should_fire, state)
+                });
+            if should_fire {
+                let callback = listener.callback.clone();
+                window.defer(cx, move |window, cx| callback(window, cx));
+            }
+        }
+
+        hitbox
     }
 
     fn paint(
@@ -536,6 +791,25 @@ impl Element for UniformList {
         window: &mut Window,
         cx: &mut App,
     ) {
+        // Registered before the items paint, so that this runs after their own handlers
+        // in the bubble phase and doesn't fire if one of them stopped propagation.
+        if let Some((listener, (hitbox, item_layout))) = self
+            .on_secondary_click
+            .clone()
+            .zip(hitbox.clone().zip(request_layout.item_layout))
+        {
+            window.on_mouse_event(move |event: &MouseDownEvent, phase, window, cx| {
+                if phase == DispatchPhase::Bubble
+                    && event.button == MouseButton::Right
+                    && hitbox.is_hovered(window)
+                    && !cx.has_active_drag()
+                    && let Some(ix) = item_layout.item_ix_at(event.position)
+                {
+                    listener(ix, event, window, cx);
+                }
+            });
+        }
+
         self.interactivity.paint(
             global_id,
             inspector_id,
@@ -544,14 +818,33 @@ impl Element for UniformList {
             window,
             cx,
             |_, window, cx| {
-                for item in &mut request_layout.items {
-                    item.paint(window, cx);
+                for (item, key) in request_layout
+                    .items
+                    .iter_mut()
+                    .zip(&request_layout.item_keys)
+                {
+                    with_item_namespace(key.as_ref(), window, |window| item.paint(window, cx));
                 }
                 for decoration in &mut request_layout.decorations {
                     decoration.paint(window, cx);
                 }
             },
-        )
+        );
+
+        if let Some((scrollbar, layout)) = self.scrollbar.zip(request_layout.scrollbar.take()) {
+            self.paint_scrollbar(scrollbar, layout, global_id, hitbox.as_ref(), window, cx);
+        }
+    }
+}
+
+fn with_item_namespace<R>(
+    key: Option<&ElementId>,
+    window: &mut Window,
+    f: impl FnOnce(&mut Window) -> R,
+) -> R {
+    match key {
+        Some(key) => window.with_element_namespace(key.clone(), f),
+        None => f(window),
     }
 }
 
@@ -637,12 +930,142 @@ impl UniformList {
         self
     }
 
+    /// Keys each item's element state by the id returned for its index, rather than by
+    /// the index itself. Use this when items can be reordered, so that state such as
+    /// hover, focus or scroll position follows the data instead of the position in the list.
+    ///
+    /// Keys must be unique among the items of the list.
+    pub fn with_item_key(
+        mut self,
+        item_key: impl 'static + Fn(usize, &mut Window, &mut App) -> ElementId,
+    ) -> Self {
+        self.item_key = Some(Box::new(item_key));
+        self
+    }
+
+    /// Calls `callback` once the list is scrolled to within `threshold` items of its
+    /// end, e.g. to load more items. It fires once per item count: it won't fire
+    /// again while the list sits at the end, and re-arms once the item count
+    /// changes or the list is scrolled away from the end.
+    pub fn on_end_reached(
+        mut self,
+        threshold: usize,
+        callback: impl Fn(&mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_end_reached = Some(EndReachedListener {
+            threshold,
+            callback: Rc::new(callback),
+        });
+        self
+    }
+
+    /// Calls `callback` with the index of the item that was right-clicked, e.g. to
+    /// open a context menu for it. The index accounts for the scroll offset and
+    /// [`UniformList::y_flipped`], so anchor the menu to the event's position.
+    ///
+    /// This doesn't stop the event from propagating, and doesn't fire while
+    /// something is being dragged or when an item handles the right click itself
+    /// and stops propagation.
+    pub fn on_secondary_click(
+        mut self,
+        callback: impl Fn(usize, &MouseDownEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_secondary_click = Some(Rc::new(callback));
+        self
+    }
+
+    /// Shows a vertical scrollbar over the right edge of the list while its
+    /// items don't fit.
+    pub fn scrollbar(mut self, scrollbar: UniformListScrollbar) -> Self {
+        self.scrollbar = Some(scrollbar);
+        self
+    }
+
     /// Adds a decoration element to the list.
     pub fn with_decoration(mut self, decoration: impl UniformListDecoration + 'static) -> Self {
         self.decorations.push(Box::new(decoration));
         self
     }
 
+    fn paint_scrollbar(
+        &self,
+        scrollbar: UniformListScrollbar,
+        layout: ScrollbarLayout,
+        global_id: Option<&GlobalElementId>,
+        list_hitbox: Option<&Hitbox>,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        let opacity = if scrollbar.autohide {
+            let hovered = list_hitbox.is_some_and(|hitbox| hitbox.is_hovered(window));
+            let now = cx.background_executor().now();
+            let since_scrolled = global_id.and_then(|global_id| {
+                window.with_element_state(global_id, |state: Option<ScrollbarState>, _| {
+                    let mut state = state.unwrap_or_default();
+                    if state
+                        .scroll_offset
+                        .is_some_and(|offset| offset != layout.scroll_offset)
+                    {
+                        state.scrolled_at = Some(now);
+                    }
+                    state.scroll_offset = Some(layout.scroll_offset);
+                    let since_scrolled = state.scrolled_at.map(|at| now.duration_since(at));
+                    (since_scrolled, state)
+                })
+            });
+            let opacity = scrollbar.opacity(hovered, since_scrolled);
+            if !hovered && opacity > 0. {
+                window.request_animation_frame();
+            }
+            if let Some(list_hitbox) = list_hitbox.cloned() {
+                window.on_mouse_event(move |_: &MouseMoveEvent, phase, window, _| {
+                    if phase == DispatchPhase::Capture && list_hitbox.is_hovered(window) != hovered
+                    {
+                        window.refresh();
+                    }
+                });
+            }
+            opacity
+        } else {
+            1.
+        };
+        if opacity > 0. {
+            window.paint_quad(
+                fill(layout.thumb, hsla(0., 0., 0.5, 0.5 * opacity))
+                    .corner_radii(layout.thumb.size.width / 2.),
+            );
+        }
+
+        let scroll_offset = self.interactivity.scroll_offset.clone();
+        let ScrollbarLayout {
+            track_hitbox,
+            thumb,
+            max_scroll,
+            ..
+        } = layout;
+        window.on_mouse_event(move |event: &MouseDownEvent, phase, window, cx| {
+            if phase == DispatchPhase::Bubble
+                && event.button == MouseButton::Left
+                && track_hitbox.is_hovered(window)
+            {
+                // Center the thumb on the click, as far as the track allows.
+                let track = track_hitbox.bounds;
+                let travel = track.size.height - thumb.size.height;
+                let fraction = if travel > Pixels::ZERO {
+                    ((event.position.y - track.top() - thumb.size.height / 2.) / travel)
+                        .clamp(0., 1.)
+                } else {
+                    0.
+                };
+                if let Some(scroll_offset) = &scroll_offset {
+                    scroll_offset.borrow_mut().y = -max_scroll * fraction;
+                }
+                window.refresh();
+                cx.stop_propagation();
+            }
+        });
+    }
+
     fn measure_item(
         &self,
         list_width: Option<Pixels>,
@@ -705,9 +1128,15 @@ impl InteractiveElement for UniformList {
     }
 }
 
+/// The height of the list's contents. Anything laid out besides the items
+/// themselves, such as headers or separators, must be accounted for here.
+fn content_height(item_height: Pixels, item_count: usize) -> Pixels {
+    item_height * item_count
+}
+
 #[cfg(test)]
 mod test {
-    use crate::TestAppContext;
+    use crate::{Pixels, TestAppContext};
 
     #[gpui::test]
     fn test_scroll_strategy_nearest(cx: &mut TestAppContext) {
@@ -850,4 +1279,479 @@ mod test {
             })
         }
     }
+
+    #[gpui::test]
+    fn test_on_end_reached(cx: &mut TestAppContext) {
+        use crate::{
+            Context, FocusHandle, ScrollStrategy, UniformListScrollHandle, Window, actions, div,
+            prelude::*, px, uniform_list,
+        };
+        use std::ops::Range;
+
+        actions!(example, [ScrollToEnd, LoadMore, Redraw]);
+
+        struct TestView {
+            length: usize,
+            end_reached_count: usize,
+            scroll_handle: UniformListScrollHandle,
+            focus_handle: FocusHandle,
+        }
+
+        impl Render for TestView {
+            fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+                div()
+                    .id("list-example")
+                    .track_focus(&self.focus_handle)
+                    .on_action(cx.listener(|this, _: &ScrollToEnd, window, _| {
+                        this.scroll_handle
+                            .scroll_to_item(this.length - 1, ScrollStrategy::Bottom);
+                        window.refresh();
+                    }))
+                    .on_action(cx.listener(|this, _: &LoadMore, window, _| {
+                        this.length += 20;
+                        window.refresh();
+                    }))
+                    .on_action(cx.listener(|_, _: &Redraw, window, _| window.refresh()))
+                    .size_full()
+                    .child(
+                        uniform_list("entries", self.length, |range: Range<usize>, _, _| {
+                            range
+                                .map(|ix| div().id(ix).h(px(20.0)).child(format!("Item {ix}")))
+                                .collect()
+                        })
+                        .on_end_reached(2, {
+                            let view = cx.entity().downgrade();
+                            move |_, cx| {
+                                view.update(cx, |view, _| view.end_reached_count += 1).ok();
+                            }
+                        })
+                        .track_scroll(&self.scroll_handle)
+                        .h(px(200.0)),
+                    )
+            }
+        }
+
+        let (view, cx) = cx.add_window_view(|window, cx| {
+            let focus_handle = cx.focus_handle();
+            window.focus(&focus_handle);
+            TestView {
+                length: 20,
+                end_reached_count: 0,
+                scroll_handle: UniformListScrollHandle::new(),
+                focus_handle,
+            }
+        });
+
+        // 10 out of 20 items are visible, which isn't within 2 items of the end.
+        cx.dispatch_action(Redraw);
+        view.read_with(cx, |view, _| assert_eq!(view.end_reached_count, 0));
+
+        cx.dispatch_action(ScrollToEnd);
+        view.read_with(cx, |view, _| assert_eq!(view.end_reached_count, 1));
+
+        // Sitting at the end doesn't fire again.
+        cx.dispatch_action(Redraw);
+        view.read_with(cx, |view, _| assert_eq!(view.end_reached_count, 1));
+
+        // Loading more items re-arms the callback.
+        cx.dispatch_action(LoadMore);
+        cx.dispatch_action(ScrollToEnd);
+        view.read_with(cx, |view, _| assert_eq!(view.end_reached_count, 2));
+    }
+
+    #[gpui::test]
+    fn test_on_secondary_click(cx: &mut TestAppContext) {
+        use crate::{
+            Context, Modifiers, MouseButton, UniformListScrollHandle, Window, div, point,
+            prelude::*, px, uniform_list,
+        };
+        use std::ops::Range;
+
+        struct TestView {
+            clicked: Vec<usize>,
+            scroll_handle: UniformListScrollHandle,
+        }
+
+        impl Render for TestView {
+            fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+                div().size_full().child(
+                    uniform_list("entries", 47, |range: Range<usize>, _, _| {
+                        range
+                            .map(|ix| {
+                                div()
+                                    .id(ix)
+                                    .h(px(20.0))
+                                    .when(ix == 6, |this| {
+                                        this.on_mouse_down(MouseButton::Right, |_, _, cx| {
+                                            cx.stop_propagation()
+                                        })
+                                    })
+                                    .child(format!("Item {ix}"))
+                            })
+                            .collect()
+                    })
+                    .on_secondary_click({
+                        let view = cx.entity().downgrade();
+                        move |ix, _, _, cx| {
+                            view.update(cx, |view, _| view.clicked.push(ix)).ok();
+                        }
+                    })
+                    .track_scroll(&self.scroll_handle)
+                    .h(px(200.0)),
+                )
+            }
+        }
+
+        let (view, cx) = cx.add_window_view(|_, _| TestView {
+            clicked: Vec::new(),
+            scroll_handle: UniformListScrollHandle::with_scroll_offset(point(px(0.), px(-100.))),
+        });
+
+        // The list is scrolled by 5 items, so the first visible row is item 5.
+        cx.simulate_mouse_down(
+            point(px(10.), px(10.)),
+            MouseButton::Right,
+            Modifiers::none(),
+        );
+        cx.simulate_mouse_down(
+            point(px(10.), px(50.)),
+            MouseButton::Right,
+            Modifiers::none(),
+        );
+        // Left clicks and items that handle the right click themselves are ignored.
+        cx.simulate_mouse_down(
+            point(px(10.), px(10.)),
+            MouseButton::Left,
+            Modifiers::none(),
+        );
+        cx.simulate_mouse_down(
+            point(px(10.), px(30.)),
+            MouseButton::Right,
+            Modifiers::none(),
+        );
+        view.read_with(cx, |view, _| assert_eq!(view.clicked, vec![5, 7]));
+    }
+
+    #[gpui::test]
+    fn test_initial_scroll_offset(cx: &mut TestAppContext) {
+        use crate::{
+            Context, Point, UniformListScrollHandle, Window, div, point, prelude::*, px,
+            uniform_list,
+        };
+        use std::ops::Range;
+
+        struct TestView {
+            scroll_handle: UniformListScrollHandle,
+            visible_range: Range<usize>,
+        }
+
+        impl Render for TestView {
+            fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+                div().size_full().child(
+                    uniform_list(
+                        "entries",
+                        47,
+                        cx.processor(|this, range: Range<usize>, _window, _cx| {
+                            this.visible_range = range.clone();
+                            range
+                                .map(|ix| div().id(ix).h(px(20.0)).child(format!("Item {ix}")))
+                                .collect()
+                        }),
+                    )
+                    .track_scroll(&self.scroll_handle)
+                    .h(px(200.0)),
+                )
+            }
+        }
+
+        let new_view = |offset: Point<Pixels>, cx: &mut TestAppContext| {
+            let (view, cx) = cx.add_window_view(|_, _| TestView {
+                scroll_handle: UniformListScrollHandle::with_scroll_offset(offset),
+                visible_range: 0..0,
+            });
+            view.read_with(cx, |view, _| view.visible_range.clone())
+        };
+
+        // The offset is applied before the first paint.
+        assert_eq!(new_view(point(px(0.), px(-100.)), cx), 5..15);
+
+        // Offsets beyond the content are clamped.
+        assert_eq!(new_view(point(px(0.), px(-10_000.)), cx), 37..47);
+        assert_eq!(new_view(point(px(0.), px(100.)), cx), 0..10);
+    }
+
+    #[gpui::test]
+    fn test_content_height(cx: &mut TestAppContext) {
+        use crate::{Context, UniformListScrollHandle, Window, div, prelude::*, px, uniform_list};
+        use std::ops::Range;
+
+        struct TestView {
+            scroll_handle: UniformListScrollHandle,
+        }
+
+        impl Render for TestView {
+            fn render(
+                &mut self,
+                _window: &mut Window,
+                _cx: &mut Context<Self>,
+            ) -> impl IntoElement {
+                div().size_full().child(
+                    uniform_list("entries", 47, |range: Range<usize>, _, _| {
+                        range
+                            .map(|ix| div().id(ix).h(px(20.0)).child(format!("Item {ix}")))
+                            .collect()
+                    })
+                    .track_scroll(&self.scroll_handle)
+                    .h(px(200.0)),
+                )
+            }
+        }
+
+        let scroll_handle = UniformListScrollHandle::new();
+        assert_eq!(scroll_handle.content_height(47), None);
+
+        let (_view, cx) = cx.add_window_view(|_, _| TestView {
+            scroll_handle: scroll_handle.clone(),
+        });
+        cx.run_until_parked();
+
+        assert_eq!(scroll_handle.content_height(47), Some(px(940.)));
+        // Other item counts are measured with the same item height.
+        assert_eq!(scroll_handle.content_height(3), Some(px(60.)));
+        assert_eq!(scroll_handle.content_height(0), Some(px(0.)));
+    }
+
+    #[test]
+    fn test_scrollbar_opacity() {
+        use super::{SCROLLBAR_FADE_DURATION, SCROLLBAR_HIDE_DELAY, UniformListScrollbar};
+        use std::time::Duration;
+
+        let scrollbar = UniformListScrollbar::default();
+        assert_eq!(scrollbar.opacity(false, None), 1.);
+
+        let scrollbar = scrollbar.autohide();
+        assert_eq!(scrollbar.opacity(false, None), 0.);
+        assert_eq!(scrollbar.opacity(true, None), 1.);
+        assert_eq!(scrollbar.opacity(false, Some(Duration::ZERO)), 1.);
+        assert_eq!(scrollbar.opacity(false, Some(SCROLLBAR_HIDE_DELAY)), 1.);
+        assert_eq!(
+            scrollbar.opacity(
+                false,
+                Some(SCROLLBAR_HIDE_DELAY + SCROLLBAR_FADE_DURATION / 2)
+            ),
+            0.5
+        );
+        assert_eq!(
+            scrollbar.opacity(false, Some(SCROLLBAR_HIDE_DELAY + SCROLLBAR_FADE_DURATION)),
+            0.
+        );
+        assert_eq!(
+            scrollbar.opacity(true, Some(SCROLLBAR_HIDE_DELAY + SCROLLBAR_FADE_DURATION)),
+            1.
+        );
+    }
+
+    #[gpui::test]
+    fn test_scrollbar_click_to_scroll(cx: &mut TestAppContext) {
+        use crate::{
+            Context, Modifiers, UniformListScrollHandle, UniformListScrollbar, VisualTestContext,
+            Window, div, point, prelude::*, px, uniform_list,
+        };
+        use std::ops::Range;
+
+        struct TestView {
+            scroll_handle: UniformListScrollHandle,
+            clicked: Vec<usize>,
+        }
+
+        impl Render for TestView {
+            fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+                div().size_full().child(
+                    uniform_list("entries", 100, {
+                        let view = cx.entity().downgrade();
+                        move |range: Range<usize>, _, _| {
+                            range
+                                .map(|ix| {
+                                    let view = view.clone();
+                                    div()
+                                        .id(ix)
+                                        .h(px(20.0))
+                                        .on_click(move |_, _, cx| {
+                                            view.update(cx, |view, _| view.clicked.push(ix)).ok();
+                                        })
+                                        .child(format!("Item {ix}"))
+                                })
+                                .collect()
+                        }
+                    })
+                    .scrollbar(UniformListScrollbar::default().width(px(12.)).autohide())
+                    .track_scroll(&self.scroll_handle)
+                    .w(px(100.0))
+                    .h(px(200.0)),
+                )
+            }
+        }
+
+        let scroll_handle = UniformListScrollHandle::new();
+        let (view, cx) = cx.add_window_view(|_, _| TestView {
+            scroll_handle: scroll_handle.clone(),
+            clicked: Vec::new(),
+        });
+        let click = |x: f32, y: f32, cx: &mut VisualTestContext| {
+            cx.simulate_click(point(px(x), px(y)), Modifiers::none());
+            scroll_handle.0.borrow().base_handle.offset().y
+        };
+
+        // 10 of the 100 items are visible, so the thumb is 20px tall and the
+        // list scrolls by 1800px. The track is hidden until the list is hovered
+        // or scrolled, but clicking it scrolls the list either way.
+        assert_eq!(click(90., 190., cx), px(-1800.));
+        assert_eq!(click(90., 100., cx), px(-900.));
+        assert_eq!(click(90., 5., cx), px(0.));
+        view.read_with(cx, |view, _| assert!(view.clicked.is_empty()));
+
+        // Clicks left of the 12px track go to the items instead.
+        assert_eq!(click(85., 190., cx), px(0.));
+        view.read_with(cx, |view, _| assert_eq!(view.clicked, vec![9]));
+    }
+
+    #[gpui::test]
+    fn test_with_item_key(cx: &mut TestAppContext) {
+        use crate::{
+            App, Bounds, Context, Element, ElementId, FocusHandle, GlobalElementId,
+            InspectorElementId, LayoutId, Style, Window, actions, div, prelude::*, px,
+            uniform_list,
+        };
+        use std::{cell::RefCell, ops::Range, panic, rc::Rc};
+
+        actions!(example, [Reverse]);
+
+        type Seen = Rc<RefCell<Vec<(&'static str, Option<&'static str>)>>>;
+
+        /// Records the label it was painted with last frame, as stored in its element state.
+        struct Item {
+            label: &'static str,
+            seen: Seen,
+        }
+
+        impl IntoElement for Item {
+            type Element = Self;
+
+            fn into_element(self) -> Self::Element {
+                self
+            }
+        }
+
+        impl Element for Item {
+            type RequestLayoutState = ();
+            type PrepaintState = ();
+
+            fn id(&self) -> Option<ElementId> {
+                Some("item".into())
+            }
+
+            fn source_location(&self) -> Option<&'static panic::Location<'static>> {
+                None
+            }
+
+            fn request_layout(
+                &mut self,
+                _id: Option<&GlobalElementId>,
+                _inspector_id: Option<&InspectorElementId>,
+                window: &mut Window,
+                cx: &mut App,
+            ) -> (LayoutId, Self::RequestLayoutState) {
+                let mut style = Style::default();
+                style.size.height = px(20.).into();
+                (window.request_layout(style, None, cx), ())
+            }
+
+            fn prepaint(
+                &mut self,
+                id: Option<&GlobalElementId>,
+                _inspector_id: Option<&InspectorElementId>,
+                _bounds: Bounds<Pixels>,
+                _request_layout: &mut Self::RequestLayoutState,
+                window: &mut Window,
+                _cx: &mut App,
+            ) {
+                window.with_element_state(id.unwrap(), |previous, _| {
+                    self.seen.borrow_mut().push((self.label, previous));
+                    ((), self.label)
+                });
+            }
+
+            fn paint(
+                &mut self,
+                _id: Option<&GlobalElementId>,
+                _inspector_id: Option<&InspectorElementId>,
+                _bounds: Bounds<Pixels>,
+                _request_layout: &mut Self::RequestLayoutState,
+                _prepaint: &mut Self::PrepaintState,
+                _window: &mut Window,
+                _cx: &mut App,
+            ) {
+            }
+        }
+
+        struct TestView {
+            labels: Vec<&'static str>,
+            seen: Seen,
+            focus_handle: FocusHandle,
+        }
+
+        impl Render for TestView {
+            fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+                let labels = self.labels.clone();
+                let seen = self.seen.clone();
+                div()
+                    .id("list-example")
+                    .track_focus(&self.focus_handle)
+                    .on_action(cx.listener(|this, _: &Reverse, window, _| {
+                        this.labels.reverse();
+                        window.refresh();
+                    }))
+                    .size_full()
+                    .child(
+                        uniform_list(
+                            "entries",
+                            self.labels.len(),
+                            move |range: Range<usize>, _, _| {
+                                range
+                                    .map(|ix| Item {
+                                        label: labels[ix],
+                                        seen: seen.clone(),
+                                    })
+                                    .collect()
+                            },
+                        )
+                        .with_item_key({
+                            let labels = self.labels.clone();
+                            move |ix, _, _| labels[ix].into()
+                        })
+                        .h(px(200.0)),
+                    )
+            }
+        }
+
+        let seen = Seen::default();
+        let (_view, cx) = cx.add_window_view(|window, cx| {
+            let focus_handle = cx.focus_handle();
+            window.focus(&focus_handle);
+            TestView {
+                labels: vec!["a", "b", "c"],
+                seen: seen.clone(),
+                focus_handle,
+            }
+        });
+        assert_eq!(seen.take(), [("a", None), ("b", None), ("c", None)]);
+
+        // After reordering, each item gets back the state it stored under its
+        // own key rather than the state of the item previously at its index.
+        cx.dispatch_action(Reverse);
+        assert_eq!(
+            seen.take(),
+            [("c", Some("c")), ("b", Some("b")), ("a", Some("a"))]
+        );
+    }
 }
@@ -65,6 +65,8 @@ const NSWindowStyleMaskNonactivatingPanel: NSWindowStyleMask =
 #[allow(non_upper_case_globals)]
 const NSNormalWindowLevel: NSInteger = 0;
 #[allow(non_upper_case_globals)]
+const NSFloatingWindowLevel: NSInteger = 3;
+#[allow(non_upper_case_globals)]
 const NSPopUpWindowLevel: NSInteger = 101;
 #[allow(non_upper_case_globals)]
 const NSTrackingMouseEnteredAndExited: NSUInteger = 0x01;
@@ -1321,6 +1323,18 @@ impl PlatformWindow for MacWindow {
         self.0.lock().move_traffic_light();
     }
 
+    fn set_always_on_top(&self, always_on_top: bool) {
+        let level = if always_on_top {
+            NSFloatingWindowLevel
+        } else {
+            NSNormalWindowLevel
+        };
+        unsafe {
+            let window = self.0.lock().native_window;
+            window.setLevel_(level);
+        }
+    }
+
     fn show_character_palette(&self) {
         let this = self.0.lock();
         let window = this.native_window;
@@ -1870,6 +1870,11 @@ impl Window {
         self.platform_window.set_edited(edited);
     }
 
+    /// Keep the window above other applications' windows, where the platform supports it.
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.platform_window.set_always_on_top(always_on_top);
+    }
+
     /// Determine the display on which the window is visible.
     pub fn display(&self, cx: &App) -> Option<Rc<dyn PlatformDisplay>> {
         cx.platform
@@ -5,7 +5,7 @@ pub mod popover_menu;
 use anyhow::Result;
 use editor::{
     Editor, SelectionEffects,
-    actions::{MoveDown, MoveUp},
+    actions::{MoveDown, MoveUp, SelectAll},
     scroll::Autoscroll,
 };
 use gpui::{
@@ -375,6 +375,14 @@ impl<D: PickerDelegate> Picker<D> {
         self.focus_handle(cx).focus(window);
     }
 
+    /// Focuses the picker and selects its query, so that typing replaces it.
+    pub fn focus_and_select_query(&self, window: &mut Window, cx: &mut App) {
+        self.focus(window, cx);
+        if let Head::Editor(editor) = &self.head {
+            editor.update(cx, |editor, cx| editor.select_all(&SelectAll, window, cx));
+        }
+    }
+
     /// Handles the selecting an index, and passing the change to the delegate.
     /// If `fallback_direction` is set to `None`, the index will not be selected
     /// if the element at that index cannot be selected.
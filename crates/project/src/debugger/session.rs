@@ -791,9 +791,21 @@ impl CompletionsQuery {
     }
 }
 
+/// How a module changed, as reported by a `module` event from the debug adapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModuleChange {
+    Added,
+    Changed,
+    Removed,
+}
+
 #[derive(Debug)]
 pub enum SessionEvent {
     Modules,
+    ModuleChanged {
+        module: Module,
+        change: ModuleChange,
+    },
     LoadedSources,
     Stopped(Option<ThreadId>),
     StackTrace,
@@ -1582,6 +1594,12 @@ impl Session {
                 store.update_session_breakpoint(self.session_id(), event.reason, event.breakpoint);
             }),
             Events::Module(event) => {
+                let module = event.module.clone();
+                let change = match event.reason {
+                    dap::ModuleEventReason::New => ModuleChange::Added,
+                    dap::ModuleEventReason::Changed => ModuleChange::Changed,
+                    dap::ModuleEventReason::Removed => ModuleChange::Removed,
+                };
                 match event.reason {
                     dap::ModuleEventReason::New => {
                         self.active_snapshot.modules.push(event.module);
@@ -1605,6 +1623,8 @@ impl Session {
 
                 // todo(debugger): We should only send the invalidate command to downstream clients.
                 // self.invalidate_state(&ModulesCommand.into());
+                cx.emit(SessionEvent::ModuleChanged { module, change });
+                cx.notify();
             }
             Events::LoadedSource(_) => {
                 self.invalidate_state(&LoadedSourcesCommand.into());
@@ -1832,6 +1852,13 @@ impl Session {
             .collect()
     }
 
+    /// Discards the cached modules and requests them from the debug adapter again.
+    pub fn refresh_modules(&mut self, cx: &mut Context<Self>) {
+        self.invalidate_command_type::<ModulesCommand>();
+        self.modules(cx);
+        cx.notify();
+    }
+
     pub fn modules(&mut self, cx: &mut Context<Self>) -> &[Module] {
         self.fetch(
             dap_command::ModulesCommand,
@@ -2802,6 +2829,23 @@ impl Session {
         local_mode.binary.request_args.request == StartDebuggingRequestArgumentsRequest::Attach
     }
 
+    /// Whether the session debugs a core dump rather than a live process, going
+    /// by the launch configuration keys adapters use for the core file.
+    pub fn is_post_mortem(&self) -> bool {
+        const CORE_FILE_KEYS: &[&str] = &["coreFile", "coreDumpPath"];
+
+        let SessionState::Running(local_mode) = &self.state else {
+            return false;
+        };
+        let configuration = &local_mode.binary.request_args.configuration;
+        CORE_FILE_KEYS.iter().any(|key| {
+            configuration
+                .get(key)
+                .and_then(|value| value.as_str())
+                .is_some_and(|path| !path.is_empty())
+        })
+    }
+
     pub fn disconnect_client(&mut self, cx: &mut Context<Self>) {
         let command = DisconnectCommand {
             restart: Some(false),
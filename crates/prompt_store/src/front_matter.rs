@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+
+use crate::PromptMetadata;
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Renders a prompt as a Markdown file whose YAML front matter carries the
+/// metadata that isn't part of the body, so it can be kept outside Zed.
+pub fn markdown_with_front_matter(metadata: &PromptMetadata, body: &str) -> String {
+    let mut text = String::with_capacity(body.len() + 128);
+    writeln!(text, "{FRONT_MATTER_DELIMITER}").ok();
+    writeln!(text, "id: {}", metadata.id).ok();
+    writeln!(text, "default: {}", metadata.default).ok();
+    writeln!(text, "saved_at: {}", metadata.saved_at.to_rfc3339()).ok();
+    writeln!(text, "{FRONT_MATTER_DELIMITER}").ok();
+    text.push_str(body);
+    if !body.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// The metadata read back from a front matter block. Ids aren't read back,
+/// since imported prompts always get new ones.
+#[derive(Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    pub default: bool,
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+/// Splits text written by [`markdown_with_front_matter`] into its front matter
+/// and body. Text that doesn't start with a complete front matter block is
+/// returned whole as the body. Unknown keys and unparsable values are ignored.
+pub fn parse_front_matter(text: &str) -> (Option<FrontMatter>, &str) {
+    let Some(rest) = text
+        .strip_prefix(FRONT_MATTER_DELIMITER)
+        .and_then(|rest| rest.strip_prefix('\n'))
+    else {
+        return (None, text);
+    };
+
+    let mut front_matter = FrontMatter::default();
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == FRONT_MATTER_DELIMITER {
+            return (Some(front_matter), &rest[offset..]);
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "default" => {
+                if let Ok(default) = value.parse() {
+                    front_matter.default = default;
+                }
+            }
+            "saved_at" => {
+                front_matter.saved_at = DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|saved_at| saved_at.with_timezone(&Utc));
+            }
+            _ => {}
+        }
+    }
+    (None, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PromptId, UserPromptId};
+    use chrono::TimeZone as _;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_markdown_with_front_matter() {
+        let id = PromptId::from(UserPromptId(Uuid::nil()));
+        let metadata = PromptMetadata {
+            title: Some("Style Guide".into()),
+            default: true,
+            saved_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            ..PromptMetadata::new(id)
+        };
+        assert_eq!(
+            markdown_with_front_matter(&metadata, "Be concise."),
+            concat!(
+                "---\n",
+                "id: 00000000-0000-0000-0000-000000000000\n",
+                "default: true\n",
+                "saved_at: 2024-05-01T12:30:00+00:00\n",
+                "---\n",
+                "Be concise.\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        let id = PromptId::from(UserPromptId(Uuid::nil()));
+        let saved_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        let metadata = PromptMetadata {
+            default: true,
+            saved_at,
+            ..PromptMetadata::new(id)
+        };
+        let text = markdown_with_front_matter(&metadata, "# Rules\n\nBe concise.\n");
+        assert_eq!(
+            parse_front_matter(&text),
+            (
+                Some(FrontMatter {
+                    default: true,
+                    saved_at: Some(saved_at),
+                }),
+                "# Rules\n\nBe concise.\n"
+            )
+        );
+
+        assert_eq!(
+            parse_front_matter("---\ndefault: maybe\nauthor: me\n---\nBody"),
+            (Some(FrontMatter::default()), "Body")
+        );
+        assert_eq!(parse_front_matter("Be concise."), (None, "Be concise."));
+        assert_eq!(
+            parse_front_matter("---\ndefault: true\n"),
+            (None, "---\ndefault: true\n")
+        );
+        assert_eq!(
+            parse_front_matter("--- not front matter\n---\n"),
+            (None, "--- not front matter\n---\n")
+        );
+    }
+}
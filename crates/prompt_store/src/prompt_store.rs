@@ -1,46 +1,145 @@
+mod front_matter;
 mod prompts;
+mod render;
+mod rules_library_settings;
 
 use anyhow::{Context as _, Result, anyhow};
 use chrono::{DateTime, Utc};
-use collections::HashMap;
-use futures::FutureExt as _;
+use collections::{HashMap, HashSet};
+pub use front_matter::*;
+use fs::Fs;
 use futures::future::Shared;
+use futures::{FutureExt as _, StreamExt as _};
 use fuzzy::StringMatchCandidate;
 use gpui::{
     App, AppContext, Context, Entity, EventEmitter, Global, ReadGlobal, SharedString, Task,
 };
 use heed::{
     Database, RoTxn,
-    types::{SerdeBincode, SerdeJson, Str},
+    types::{Bytes, SerdeBincode, SerdeJson, Str},
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 pub use prompts::*;
+pub use render::*;
 use rope::Rope;
+pub use rules_library_settings::*;
 use serde::{Deserialize, Serialize};
+use settings::{RulesLibrarySortOrder, Settings as _, SettingsStore};
+use sha2::{Digest as _, Sha256};
 use std::{
     cmp::Reverse,
+    collections::VecDeque,
     future::Future,
     path::PathBuf,
-    sync::{Arc, atomic::AtomicBool},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
 };
 use text::LineEnding;
 use util::ResultExt;
 use uuid::Uuid;
 
+const MAX_RECENTLY_OPENED: usize = 10;
+
+/// Saves whose write transaction takes longer than this are logged.
+const SLOW_COMMIT_THRESHOLD: Duration = Duration::from_millis(50);
+/// At most one slow save is logged per interval, so a slow disk doesn't flood the log.
+const SLOW_COMMIT_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The most named databases the environment can hold. LMDB only uses this
+/// while the environment is open rather than storing it, so raising it is
+/// safe for existing environments.
+const MAX_DBS: u32 = 16;
+
+const METADATA_DB_NAME: &str = "metadata.v2";
+const BODIES_DB_NAME: &str = "bodies.v2";
+const TRASH_DB_NAME: &str = "trash.v2";
+
+/// How long deleted prompts are kept in the trash before they're purged.
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Bumped whenever a database is renamed, with an entry in [`DB_RENAMES`].
+const SCHEMA_VERSION: u32 = 2;
+const SCHEMA_VERSION_KEY: &str = "version";
+/// Set in the schema database once every v1 prompt has been migrated.
+const V1_MIGRATED_KEY: &str = "v1_migrated";
+
+/// How many v1 prompts are migrated per write transaction. Each batch is
+/// committed on its own, so an interrupted migration keeps what it finished.
+const V1_MIGRATION_BATCH_SIZE: usize = 100;
+
+/// `(schema version, previous name, name)` for every database rename, oldest
+/// first. Renames newer than the environment's schema version are applied
+/// when the store is opened.
+const DB_RENAMES: &[(u32, &str, &str)] = &[];
+
 /// Init starts loading the PromptStore in the background and assigns
 /// a shared future to a global.
 pub fn init(cx: &mut App) {
     let db_path = paths::prompts_dir().join("prompts-library-db.0.mdb");
-    let prompt_store_task = PromptStore::new(db_path, cx);
+    let migration_progress = Arc::new(MigrationProgress::default());
+    let prompt_store_task = PromptStore::new(db_path, migration_progress.clone(), cx);
     let prompt_store_entity_task = cx
         .spawn(async move |cx| {
             prompt_store_task
                 .await
-                .and_then(|prompt_store| cx.new(|_cx| prompt_store))
+                .and_then(|prompt_store| {
+                    cx.new(|cx| {
+                        // Follow changes to the sort order made in the settings file.
+                        cx.observe_global::<SettingsStore>(|this: &mut PromptStore, cx| {
+                            let sort_order = RulesLibrarySettings::get_global(cx).sort_order;
+                            this.apply_sort_order(sort_order, cx);
+                        })
+                        .detach();
+                        prompt_store
+                    })
+                })
                 .map_err(Arc::new)
         })
         .shared();
-    cx.set_global(GlobalPromptStore(prompt_store_entity_task))
+    cx.set_global(GlobalPromptStore(
+        prompt_store_entity_task,
+        migration_progress,
+    ))
+}
+
+/// How far the migration of prompts saved by older versions of Zed has come.
+/// It's shared with the UI while the store loads, so it can show progress and
+/// let the user stop the migration.
+#[derive(Default)]
+pub struct MigrationProgress {
+    migrated: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl MigrationProgress {
+    pub fn migrated(&self) -> usize {
+        self.migrated.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Whether prompts are being migrated right now.
+    pub fn is_running(&self) -> bool {
+        let total = self.total();
+        total > 0 && self.migrated() < total && !self.is_cancelled()
+    }
+
+    /// Stops the migration once the batch in progress has been committed. The
+    /// prompts that weren't migrated yet are migrated the next time the store
+    /// is opened.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -49,9 +148,310 @@ pub struct PromptMetadata {
     pub title: Option<SharedString>,
     pub default: bool,
     pub saved_at: DateTime<Utc>,
+    /// When the prompt was created. Unknown for prompts created before this
+    /// was recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// How many times the prompt was inserted into a thread, with
+    /// [`PromptStore::record_usage`]. Default prompts being included
+    /// automatically doesn't count.
+    #[serde(default)]
+    pub usage_count: u64,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Locked prompts can't be edited until they're unlocked.
+    #[serde(default)]
+    pub locked: bool,
+    /// Archived prompts are hidden unless asked for, and are never defaults.
+    #[serde(default)]
+    pub archived: bool,
+    /// Pinned prompts are listed before all others, in any sort order and
+    /// search.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Disabled default prompts stay default, but are left out of threads
+    /// until they're enabled again.
+    #[serde(default)]
+    pub disabled: bool,
+    /// A short summary shown under the title in the rules library. Like the
+    /// notes, it's never sent to a model.
+    #[serde(default)]
+    pub description: Option<SharedString>,
+    /// Free-form notes about the prompt. These are never sent to a model.
+    #[serde(default)]
+    pub notes: Option<SharedString>,
+    /// The language the body is highlighted as, by name. Markdown if unset.
+    #[serde(default)]
+    pub language: Option<SharedString>,
+    /// Tags for organizing prompts, as the user wrote them.
+    #[serde(default)]
+    pub tags: Vec<SharedString>,
+    /// The file this prompt's body was last saved to, if it was kept linked to it.
+    #[serde(default)]
+    pub linked_path: Option<PathBuf>,
+    /// Data attached by extensions, keyed however they like. Zed itself never
+    /// reads it, and keeps it intact when the prompt is saved.
+    #[serde(default)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PromptMetadata {
+    fn new(id: PromptId) -> Self {
+        Self {
+            id,
+            title: None,
+            default: false,
+            saved_at: Utc::now(),
+            created_at: Some(Utc::now()),
+            usage_count: 0,
+            last_used_at: None,
+            locked: false,
+            archived: false,
+            pinned: false,
+            disabled: false,
+            description: None,
+            notes: None,
+            language: None,
+            tags: Vec::new(),
+            linked_path: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// How [`PromptStore::search_with_positions`] matches the query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PromptSearchOptions {
+    /// Whether letters only match letters of the same case.
+    pub case_sensitive: bool,
+    /// Whether the query has to appear as-is in a single field, instead of
+    /// being fuzzy matched. Useful for punctuation like `{{`.
+    pub exact: bool,
+}
+
+/// A prompt returned by [`PromptStore::search_with_positions`].
+#[derive(Clone, Debug)]
+pub struct PromptMatch {
+    pub metadata: PromptMetadata,
+    /// Byte offsets of the characters in the title that matched the query.
+    pub title_positions: Vec<usize>,
+    /// Byte offsets of the characters in the description that matched the query.
+    pub description_positions: Vec<usize>,
+    /// Byte offsets of the characters in the notes that matched the query.
+    pub notes_positions: Vec<usize>,
+}
+
+/// A set of prompts encoded as text, so that they can be copied between rules
+/// libraries (including ones belonging to other Zed instances).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PromptBundle {
+    pub version: u32,
+    pub prompts: Vec<PromptBundleEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PromptBundleEntry {
+    pub title: Option<SharedString>,
+    pub default: bool,
+    /// When the prompt was last saved, if known. Prompts without one are
+    /// treated as saved when they're inserted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<SharedString>,
+    pub body: String,
+}
+
+/// What [`PromptStore::import_plain_text_dir`] does with subdirectories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubdirectoryImport {
+    Skip,
+    Include,
+    /// Imports the files in subdirectories, tagged with the names of the
+    /// subdirectories they're in.
+    IncludeAsTags,
+}
+
+/// The result of [`PromptStore::import_plain_text_dir`].
+#[derive(Debug, Default)]
+pub struct PlainTextImport {
+    pub prompt_ids: Vec<PromptId>,
+    /// Files that couldn't be read, or that weren't valid UTF-8.
+    pub skipped_paths: Vec<PathBuf>,
+}
+
+impl PromptBundle {
+    const VERSION: u32 = 1;
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let bundle: Self = serde_json::from_str(text).context("not a prompt bundle")?;
+        anyhow::ensure!(
+            bundle.version == Self::VERSION,
+            "unsupported prompt bundle version {}",
+            bundle.version
+        );
+        Ok(bundle)
+    }
+}
+
+/// Every prompt in a library with its full metadata, written by
+/// [`PromptStore::backup`]. Unlike a [`PromptBundle`], prompts keep their ids,
+/// so restoring an archive updates prompts instead of duplicating them.
+#[derive(Serialize, Deserialize)]
+struct PromptArchive {
+    version: u32,
+    prompts: Vec<ArchivedPrompt>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedPrompt {
+    metadata: PromptMetadata,
+    /// Built-in prompts only store their metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl PromptArchive {
+    const VERSION: u32 = 1;
+}
+
+/// Removes trailing whitespace from every line outside of fenced code blocks,
+/// along with any trailing blank lines.
+fn trim_trailing_whitespace_outside_code_blocks(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut fence: Option<&str> = None;
+    for line in text.split_inclusive('\n') {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| content.trim_start().starts_with(marker));
+        let was_in_code_block = fence.is_some();
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {}
+        }
+
+        if was_in_code_block && fence.is_some() {
+            result.push_str(line);
+        } else {
+            result.push_str(content.trim_end());
+            if line.ends_with('\n') {
+                result.push('\n');
+            }
+        }
+    }
+
+    let trimmed_len = result.trim_end().len();
+    if trimmed_len < result.len() {
+        result.truncate(trimmed_len);
+        if text.ends_with('\n') {
+            result.push('\n');
+        }
+    }
+    result
+}
+
+/// The fields of a prompt that searches match, in the order they're joined
+/// with spaces into a single candidate.
+fn searchable_fields(metadata: &PromptMetadata) -> [Option<&str>; 3] {
+    [
+        metadata.title.as_deref(),
+        metadata.description.as_deref(),
+        metadata.notes.as_deref(),
+    ]
+}
+
+/// Byte offsets of the characters of the first occurrence of `query` in
+/// `text`, if it occurs.
+fn find_exact(text: &str, query: &str, case_sensitive: bool) -> Option<Vec<usize>> {
+    let query = query.chars().collect::<Vec<_>>();
+    if query.is_empty() {
+        return None;
+    }
+    let chars_match = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+    let text = text.char_indices().collect::<Vec<_>>();
+    text.windows(query.len())
+        .find(|window| {
+            window
+                .iter()
+                .zip(&query)
+                .all(|((_, text_char), query_char)| chars_match(*text_char, *query_char))
+        })
+        .map(|window| window.iter().map(|(ix, _)| *ix).collect())
+}
+
+/// Whether each character of `query` matched a character of the same case at
+/// the corresponding byte offset in `text`. Smart case only holds uppercase
+/// letters in the query to their case, so this rules out matches where a
+/// lowercase letter matched an uppercase one.
+fn positions_match_case(text: &str, positions: &[usize], query: &str) -> bool {
+    positions
+        .iter()
+        .zip(query.chars())
+        .all(|(position, query_char)| {
+            text.get(*position..)
+                .is_some_and(|rest| rest.starts_with(query_char))
+        })
+}
+
+/// Splits the positions matched in the joined candidate into positions within
+/// each of the fields it was joined from. Matched separators are dropped.
+fn split_field_positions(fields: [Option<&str>; 3], positions: &[usize]) -> [Vec<usize>; 3] {
+    let mut field_positions = <[Vec<usize>; 3]>::default();
+    let mut start = 0;
+    for (field, field_positions) in fields.into_iter().zip(&mut field_positions) {
+        let Some(field) = field else {
+            continue;
+        };
+        let range = start..start + field.len();
+        *field_positions = positions
+            .iter()
+            .filter(|position| range.contains(position))
+            .map(|position| position - start)
+            .collect();
+        start = range.end + 1;
+    }
+    field_positions
+}
+
+/// Replaces the characters that can't appear in a file name on some platform,
+/// path separators included, so a title can be used as a file stem.
+pub fn file_stem_for_title(title: &str) -> String {
+    title.replace(
+        |c: char| matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'),
+        "-",
+    )
+}
+
+/// Returns `title` unchanged if it isn't in `existing_titles`, otherwise
+/// appends the smallest numeric suffix that makes it unique.
+pub fn unique_title(title: &str, existing_titles: &HashSet<String>) -> String {
+    if !existing_titles.contains(title) {
+        return title.to_string();
+    }
+
+    let mut i = 1;
+    loop {
+        let new_title = format!("{title} {i}");
+        if !existing_titles.contains(&new_title) {
+            return new_title;
+        }
+        i += 1;
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(tag = "kind")]
 pub enum PromptId {
     User { uuid: UserPromptId },
@@ -68,13 +468,34 @@ impl PromptId {
     }
 }
 
+/// A prompt that ships with Zed rather than being written by the user.
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltInPrompt {
+    pub id: PromptId,
+    pub title: &'static str,
+    pub body: &'static str,
+}
+
+/// Built-in prompts that used to ship with Zed and are removed from existing stores.
+const RETIRED_BUILT_IN_PROMPTS: &[PromptId] = &[
+    // Removed in favor of opting into edit workflows using a slash command.
+    PromptId::EditWorkflow,
+];
+
+/// Every built-in prompt. This is the single source of truth for both seeding
+/// the store and generating documentation, so built-ins shouldn't be defined
+/// anywhere else.
+pub fn built_in_prompts() -> &'static [BuiltInPrompt] {
+    &[]
+}
+
 impl From<UserPromptId> for PromptId {
     fn from(uuid: UserPromptId) -> Self {
         PromptId::User { uuid }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct UserPromptId(pub Uuid);
 
@@ -99,29 +520,112 @@ impl std::fmt::Display for PromptId {
     }
 }
 
+/// The id prompts had in the databases written by older versions of Zed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
+struct PromptIdV1(Uuid);
+
+/// The metadata prompts had in the databases written by older versions of Zed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PromptMetadataV1 {
+    id: PromptIdV1,
+    title: Option<SharedString>,
+    default: bool,
+    saved_at: DateTime<Utc>,
+}
+
 pub struct PromptStore {
     env: heed::Env,
     metadata_cache: RwLock<MetadataCache>,
     metadata: Database<SerdeJson<PromptId>, SerdeJson<PromptMetadata>>,
     bodies: Database<SerdeJson<PromptId>, Str>,
+    trash: Database<SerdeJson<PromptId>, SerdeJson<TrashedPrompt>>,
+    recently_opened: VecDeque<PromptId>,
+    /// Token counts for the active model, cached for this session.
+    token_counts: HashMap<PromptId, u64>,
+    slow_commit_log: Arc<SlowCommitLog>,
+}
+
+/// A deleted prompt, kept so that its deletion can be undone with
+/// [`PromptStore::restore`] until it's purged.
+#[derive(Serialize, Deserialize)]
+struct TrashedPrompt {
+    metadata: PromptMetadata,
+    body: String,
+    deleted_at: DateTime<Utc>,
+}
+
+/// Logs saves whose write transaction was slow enough to cause stutter while
+/// typing, e.g. on networked file systems. Only enabled when debug logging is
+/// on for this crate (`"log": {"prompt_store": "debug"}` in settings).
+#[derive(Default)]
+struct SlowCommitLog(Mutex<SlowCommitLogState>);
+
+#[derive(Default)]
+struct SlowCommitLogState {
+    last_logged_at: Option<Instant>,
+    /// Slow commits since the last one that was logged.
+    unlogged: usize,
+}
+
+impl SlowCommitLog {
+    fn enabled() -> bool {
+        log::log_enabled!(log::Level::Debug)
+    }
+
+    fn record(&self, operation: &str, duration: Duration) {
+        if duration < SLOW_COMMIT_THRESHOLD {
+            return;
+        }
+
+        let mut state = self.0.lock();
+        let now = Instant::now();
+        if state
+            .last_logged_at
+            .is_some_and(|last_logged_at| now - last_logged_at < SLOW_COMMIT_LOG_INTERVAL)
+        {
+            state.unlogged += 1;
+            return;
+        }
+
+        log::debug!(
+            "prompt store {operation} took {duration:?} to commit ({} other slow commits since the last report)",
+            state.unlogged
+        );
+        state.last_logged_at = Some(now);
+        state.unlogged = 0;
+    }
 }
 
 pub struct PromptsUpdatedEvent;
 
 impl EventEmitter<PromptsUpdatedEvent> for PromptStore {}
 
+/// Emitted when cached token counts are dropped, so that views showing them
+/// can count again.
+pub enum TokenCountsInvalidatedEvent {
+    Prompt(PromptId),
+    All,
+}
+
+impl EventEmitter<TokenCountsInvalidatedEvent> for PromptStore {}
+
 #[derive(Default)]
 struct MetadataCache {
     metadata: Vec<PromptMetadata>,
     metadata_by_id: HashMap<PromptId, PromptMetadata>,
+    sort_order: RulesLibrarySortOrder,
 }
 
 impl MetadataCache {
     fn from_db(
         db: Database<SerdeJson<PromptId>, SerdeJson<PromptMetadata>>,
         txn: &RoTxn,
+        sort_order: RulesLibrarySortOrder,
     ) -> Result<Self> {
-        let mut cache = MetadataCache::default();
+        let mut cache = MetadataCache {
+            sort_order,
+            ..MetadataCache::default()
+        };
         for result in db.iter(txn)? {
             let (prompt_id, metadata) = result?;
             cache.metadata.push(metadata.clone());
@@ -141,52 +645,151 @@ impl MetadataCache {
         self.sort();
     }
 
+    fn get_or_new(&self, id: PromptId) -> PromptMetadata {
+        self.metadata_by_id
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| PromptMetadata::new(id))
+    }
+
     fn remove(&mut self, id: PromptId) {
         self.metadata.retain(|metadata| metadata.id != id);
         self.metadata_by_id.remove(&id);
     }
 
+    fn remove_many(&mut self, ids: &HashSet<PromptId>) {
+        self.metadata.retain(|metadata| !ids.contains(&metadata.id));
+        self.metadata_by_id.retain(|id, _| !ids.contains(id));
+    }
+
     fn sort(&mut self) {
+        let sort_order = self.sort_order;
         self.metadata.sort_unstable_by(|a, b| {
-            a.title
-                .cmp(&b.title)
-                .then_with(|| b.saved_at.cmp(&a.saved_at))
+            let by_title = || a.title.cmp(&b.title);
+            let by_saved_at = || b.saved_at.cmp(&a.saved_at);
+            let ordering = match sort_order {
+                RulesLibrarySortOrder::TitleAsc => by_title().then_with(by_saved_at),
+                RulesLibrarySortOrder::UpdatedDesc => by_saved_at().then_with(by_title),
+                RulesLibrarySortOrder::CreatedDesc => {
+                    let a_created_at = a.created_at.unwrap_or(a.saved_at);
+                    let b_created_at = b.created_at.unwrap_or(b.saved_at);
+                    b_created_at.cmp(&a_created_at).then_with(by_title)
+                }
+                RulesLibrarySortOrder::MostUsed => b
+                    .usage_count
+                    .cmp(&a.usage_count)
+                    .then_with(|| b.last_used_at.cmp(&a.last_used_at))
+                    .then_with(by_title),
+            };
+            b.pinned
+                .cmp(&a.pinned)
+                .then(ordering)
+                // Break remaining ties by id, so the order doesn't change between sessions.
+                .then_with(|| a.id.cmp(&b.id))
         });
     }
 }
 
+fn user_prompt_ids(cache: &MetadataCache) -> Vec<PromptId> {
+    cache
+        .metadata
+        .iter()
+        .map(|metadata| metadata.id)
+        .filter(|id| !id.is_built_in())
+        .collect()
+}
+
+fn rewrite_title_includes(
+    txn: &mut heed::RwTxn,
+    bodies: Database<SerdeJson<PromptId>, Str>,
+    ids: Vec<PromptId>,
+    old_title: &str,
+    new_title: &str,
+) -> Result<Vec<PromptId>> {
+    let mut updated_ids = Vec::new();
+    for id in ids {
+        let Some(body) = bodies.get(txn, &id)? else {
+            continue;
+        };
+        if let Some(body) = rename_title_includes(body, old_title, new_title) {
+            bodies.put(txn, &id, &body)?;
+            updated_ids.push(id);
+        }
+    }
+    Ok(updated_ids)
+}
+
 impl PromptStore {
     pub fn global(cx: &App) -> impl Future<Output = Result<Entity<Self>>> + use<> {
         let store = GlobalPromptStore::global(cx).0.clone();
         async move { store.await.map_err(|err| anyhow!(err)) }
     }
 
-    pub fn new(db_path: PathBuf, cx: &App) -> Task<Result<Self>> {
+    /// Returns the progress of the migration that runs while the global store
+    /// loads.
+    pub fn migration_progress(cx: &App) -> Option<Arc<MigrationProgress>> {
+        Some(cx.try_global::<GlobalPromptStore>()?.1.clone())
+    }
+
+    /// Returns the global store if it has finished loading.
+    pub fn try_global(cx: &App) -> Option<Entity<Self>> {
+        cx.try_global::<GlobalPromptStore>()?
+            .0
+            .peek()?
+            .as_ref()
+            .ok()
+            .cloned()
+    }
+
+    pub fn new(
+        db_path: PathBuf,
+        migration_progress: Arc<MigrationProgress>,
+        cx: &App,
+    ) -> Task<Result<Self>> {
+        let sort_order = RulesLibrarySettings::get_global(cx).sort_order;
         cx.background_spawn(async move {
             std::fs::create_dir_all(&db_path)?;
 
             let db_env = unsafe {
                 heed::EnvOpenOptions::new()
                     .map_size(1024 * 1024 * 1024) // 1GB
-                    .max_dbs(4) // Metadata and bodies (possibly v1 of both as well)
+                    .max_dbs(MAX_DBS)
                     .open(db_path)?
             };
 
             let mut txn = db_env.write_txn()?;
-            let metadata = db_env.create_database(&mut txn, Some("metadata.v2"))?;
-            let bodies = db_env.create_database(&mut txn, Some("bodies.v2"))?;
-
-            // Remove edit workflow prompt, as we decided to opt into it using
-            // a slash command instead.
-            metadata.delete(&mut txn, &PromptId::EditWorkflow).ok();
-            bodies.delete(&mut txn, &PromptId::EditWorkflow).ok();
+            let schema = db_env.create_database::<Str, SerdeJson<u32>>(&mut txn, Some("schema"))?;
+            // Environments created before the schema version was recorded are at version 2.
+            let schema_version = schema.get(&txn, SCHEMA_VERSION_KEY)?.unwrap_or(2);
+            if schema_version > SCHEMA_VERSION {
+                log::warn!(
+                    "prompt store schema version {schema_version} is newer than {SCHEMA_VERSION}"
+                );
+            } else {
+                for (version, previous_name, name) in DB_RENAMES {
+                    if *version > schema_version {
+                        Self::rename_db(&db_env, &mut txn, previous_name, name)?;
+                    }
+                }
+                schema.put(&mut txn, SCHEMA_VERSION_KEY, &SCHEMA_VERSION)?;
+            }
+            let metadata = db_env.create_database(&mut txn, Some(METADATA_DB_NAME))?;
+            let bodies = db_env.create_database(&mut txn, Some(BODIES_DB_NAME))?;
+            let trash = db_env.create_database(&mut txn, Some(TRASH_DB_NAME))?;
+            Self::purge_trash(&mut txn, trash)?;
+
+            for id in RETIRED_BUILT_IN_PROMPTS {
+                metadata.delete(&mut txn, id).ok();
+                bodies.delete(&mut txn, id).ok();
+            }
+            Self::seed_built_in_prompts(&mut txn, metadata, bodies)?;
 
             txn.commit()?;
 
-            Self::upgrade_dbs(&db_env, metadata, bodies).log_err();
+            Self::upgrade_dbs(&db_env, schema, metadata, bodies, &migration_progress).log_err();
 
             let txn = db_env.read_txn()?;
-            let metadata_cache = MetadataCache::from_db(metadata, &txn)?;
+            let metadata_cache = MetadataCache::from_db(metadata, &txn, sort_order)?;
             txn.commit()?;
 
             Ok(PromptStore {
@@ -194,27 +797,103 @@ impl PromptStore {
                 metadata_cache: RwLock::new(metadata_cache),
                 metadata,
                 bodies,
+                trash,
+                recently_opened: VecDeque::new(),
+                token_counts: HashMap::default(),
+                slow_commit_log: Arc::default(),
             })
         })
     }
 
-    fn upgrade_dbs(
+    /// Deletes the prompts that have been in the trash for longer than
+    /// [`TRASH_RETENTION_DAYS`].
+    fn purge_trash(
+        txn: &mut heed::RwTxn,
+        trash: Database<SerdeJson<PromptId>, SerdeJson<TrashedPrompt>>,
+    ) -> Result<()> {
+        let cutoff = Utc::now() - chrono::Duration::days(TRASH_RETENTION_DAYS);
+        let expired_ids = trash
+            .iter(txn)?
+            .filter_map(|entry| {
+                let (id, trashed) = entry.log_err()?;
+                (trashed.deleted_at < cutoff).then_some(id)
+            })
+            .collect::<Vec<_>>();
+        for id in expired_ids {
+            trash.delete(txn, &id)?;
+        }
+        Ok(())
+    }
+
+    /// Copies every entry of the database `previous_name` into the database
+    /// `name`, creating it if needed, then empties `previous_name`. Entries are
+    /// copied as they're stored, so any change to their encoding needs its own
+    /// migration. Both happen in `txn`, so the old entries are only gone once
+    /// the copy has been committed. heed can't delete a named database, only
+    /// clear it, so an empty `previous_name` is left behind with its pages
+    /// freed; running the rename again copies nothing.
+    fn rename_db(
         env: &heed::Env,
+        txn: &mut heed::RwTxn,
+        previous_name: &str,
+        name: &str,
+    ) -> Result<()> {
+        let Some(previous_db) = env.open_database::<Bytes, Bytes>(txn, Some(previous_name))? else {
+            return Ok(());
+        };
+        let db = env.create_database::<Bytes, Bytes>(txn, Some(name))?;
+        let entries = previous_db
+            .iter(txn)?
+            .map(|entry| entry.map(|(key, value)| (key.to_vec(), value.to_vec())))
+            .collect::<heed::Result<Vec<_>>>()?;
+        for (key, value) in entries {
+            db.put(txn, &key, &value)?;
+        }
+        previous_db.clear(txn)?;
+        Ok(())
+    }
+
+    /// Writes the current title and body of every built-in prompt, keeping
+    /// whether the user made it a default or pinned it, and any data attached
+    /// by extensions.
+    fn seed_built_in_prompts(
+        txn: &mut heed::RwTxn,
         metadata_db: heed::Database<SerdeJson<PromptId>, SerdeJson<PromptMetadata>>,
         bodies_db: heed::Database<SerdeJson<PromptId>, Str>,
     ) -> Result<()> {
-        #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
-        pub struct PromptIdV1(Uuid);
-
-        #[derive(Clone, Debug, Serialize, Deserialize)]
-        pub struct PromptMetadataV1 {
-            pub id: PromptIdV1,
-            pub title: Option<SharedString>,
-            pub default: bool,
-            pub saved_at: DateTime<Utc>,
+        for prompt in built_in_prompts() {
+            let (default, pinned, extra) = metadata_db
+                .get(txn, &prompt.id)?
+                .map(|metadata| (metadata.default, metadata.pinned, metadata.extra))
+                .unwrap_or_default();
+            let metadata = PromptMetadata {
+                title: Some(prompt.title.into()),
+                default,
+                pinned,
+                extra,
+                ..PromptMetadata::new(prompt.id)
+            };
+            metadata_db.put(txn, &prompt.id, &metadata)?;
+            bodies_db.put(txn, &prompt.id, prompt.body)?;
         }
+        Ok(())
+    }
 
-        let mut txn = env.write_txn()?;
+    /// Copies the prompts saved by older versions of Zed into the current
+    /// databases, in batches that are each committed on their own. A prompt is
+    /// only copied if it was saved more recently than its current version, so
+    /// a migration that was cancelled or interrupted can simply run again.
+    fn upgrade_dbs(
+        env: &heed::Env,
+        schema_db: heed::Database<Str, SerdeJson<u32>>,
+        metadata_db: heed::Database<SerdeJson<PromptId>, SerdeJson<PromptMetadata>>,
+        bodies_db: heed::Database<SerdeJson<PromptId>, Str>,
+        progress: &MigrationProgress,
+    ) -> Result<()> {
+        let txn = env.read_txn()?;
+        if schema_db.get(&txn, V1_MIGRATED_KEY)?.is_some() {
+            return Ok(());
+        }
         let Some(bodies_v1_db) = env
             .open_database::<SerdeBincode<PromptIdV1>, SerdeBincode<String>>(
                 &txn,
@@ -237,32 +916,50 @@ impl PromptStore {
         };
         let metadata_v1 = metadata_v1_db
             .iter(&txn)?
-            .collect::<heed::Result<HashMap<_, _>>>()?;
+            .collect::<heed::Result<Vec<_>>>()?;
+        txn.commit()?;
 
-        for (prompt_id_v1, metadata_v1) in metadata_v1 {
-            let prompt_id_v2 = UserPromptId(prompt_id_v1.0).into();
-            let Some(body_v1) = bodies_v1.remove(&prompt_id_v1) else {
-                continue;
-            };
+        progress.total.store(metadata_v1.len(), Ordering::Relaxed);
+        for batch in metadata_v1.chunks(V1_MIGRATION_BATCH_SIZE) {
+            if progress.is_cancelled() {
+                log::info!(
+                    "prompt migration cancelled after {} of {} prompts",
+                    progress.migrated(),
+                    progress.total()
+                );
+                return Ok(());
+            }
 
-            if metadata_db
-                .get(&txn, &prompt_id_v2)?
-                .is_none_or(|metadata_v2| metadata_v1.saved_at > metadata_v2.saved_at)
-            {
-                metadata_db.put(
-                    &mut txn,
-                    &prompt_id_v2,
-                    &PromptMetadata {
-                        id: prompt_id_v2,
-                        title: metadata_v1.title.clone(),
-                        default: metadata_v1.default,
-                        saved_at: metadata_v1.saved_at,
-                    },
-                )?;
-                bodies_db.put(&mut txn, &prompt_id_v2, &body_v1)?;
+            let mut txn = env.write_txn()?;
+            for (prompt_id_v1, metadata_v1) in batch {
+                let prompt_id_v2 = UserPromptId(prompt_id_v1.0).into();
+                let Some(body_v1) = bodies_v1.remove(prompt_id_v1) else {
+                    continue;
+                };
+
+                if metadata_db
+                    .get(&txn, &prompt_id_v2)?
+                    .is_none_or(|metadata_v2| metadata_v1.saved_at > metadata_v2.saved_at)
+                {
+                    metadata_db.put(
+                        &mut txn,
+                        &prompt_id_v2,
+                        &PromptMetadata {
+                            title: metadata_v1.title.clone(),
+                            default: metadata_v1.default,
+                            saved_at: metadata_v1.saved_at,
+                            ..PromptMetadata::new(prompt_id_v2)
+                        },
+                    )?;
+                    bodies_db.put(&mut txn, &prompt_id_v2, &body_v1)?;
+                }
             }
+            txn.commit()?;
+            progress.migrated.fetch_add(batch.len(), Ordering::Relaxed);
         }
 
+        let mut txn = env.write_txn()?;
+        schema_db.put(&mut txn, V1_MIGRATED_KEY, &1)?;
         txn.commit()?;
 
         Ok(())
@@ -279,34 +976,116 @@ impl PromptStore {
         })
     }
 
-    pub fn all_prompt_metadata(&self) -> Vec<PromptMetadata> {
-        self.metadata_cache.read().metadata.clone()
+    /// Produces the text a model would see for the given prompt: includes are
+    /// expanded, snippet markers are stripped and `{{variables}}` are substituted.
+    /// Fails with [`UnresolvedVariables`] if a variable has no value.
+    pub fn render(
+        &self,
+        id: PromptId,
+        variables: HashMap<String, String>,
+        cx: &App,
+    ) -> Task<Result<String>> {
+        let metadata = self.metadata_cache.read().metadata.clone();
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let mut load_body = |id: PromptId| -> Result<String> {
+                let mut body: String = bodies.get(&txn, &id)?.context("prompt not found")?.into();
+                LineEnding::normalize(&mut body);
+                Ok(body)
+            };
+            let body = load_body(id)?;
+            let body = expand_includes(&body, &metadata, &mut load_body, &mut vec![id])?;
+            substitute_variables(&strip_snippet_markers(&body), &variables)
+        })
     }
 
-    pub fn default_prompt_metadata(&self) -> Vec<PromptMetadata> {
-        return self
-            .metadata_cache
-            .read()
+    /// Loads the given prompts into a [`PromptBundle`] using a single read transaction.
+    pub fn bundle(&self, ids: &[PromptId], cx: &App) -> Task<Result<PromptBundle>> {
+        let metadata = {
+            let cache = self.metadata_cache.read();
+            ids.iter()
+                .filter_map(|id| cache.metadata_by_id.get(id).cloned())
+                .collect::<Vec<_>>()
+        };
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let mut prompts = Vec::with_capacity(metadata.len());
+            for metadata in metadata {
+                let mut body: String = bodies
+                    .get(&txn, &metadata.id)?
+                    .context("prompt not found")?
+                    .into();
+                LineEnding::normalize(&mut body);
+                prompts.push(PromptBundleEntry {
+                    title: metadata.title,
+                    default: metadata.default,
+                    saved_at: None,
+                    tags: metadata.tags,
+                    body,
+                });
+            }
+            Ok(PromptBundle {
+                version: PromptBundle::VERSION,
+                prompts,
+            })
+        })
+    }
+
+    /// Creates a new prompt for every entry in the bundle. Each prompt gets a
+    /// fresh id, and titles that collide with existing prompts are suffixed.
+    pub fn insert_bundle(
+        &self,
+        bundle: PromptBundle,
+        cx: &Context<Self>,
+    ) -> Task<Result<Vec<PromptId>>> {
+        let mut cache = self.metadata_cache.write();
+        let mut existing_titles = cache
             .metadata
             .iter()
-            .filter(|metadata| metadata.default)
-            .cloned()
+            .filter_map(|metadata| Some(metadata.title.as_ref()?.to_string()))
+            .collect::<HashSet<_>>();
+
+        let saved_at = Utc::now();
+        let prompts = bundle
+            .prompts
+            .into_iter()
+            .map(|entry| {
+                let title = entry.title.map(|title| {
+                    let title = unique_title(&title, &existing_titles);
+                    existing_titles.insert(title.clone());
+                    SharedString::from(title)
+                });
+                let metadata = PromptMetadata {
+                    title,
+                    default: entry.default,
+                    saved_at: entry.saved_at.unwrap_or(saved_at),
+                    tags: entry.tags,
+                    ..PromptMetadata::new(PromptId::new())
+                };
+                cache.insert(metadata.clone());
+                (metadata, entry.body)
+            })
             .collect::<Vec<_>>();
-    }
-
-    pub fn delete(&self, id: PromptId, cx: &Context<Self>) -> Task<Result<()>> {
-        self.metadata_cache.write().remove(id);
+        drop(cache);
 
+        let ids = prompts
+            .iter()
+            .map(|(metadata, _)| metadata.id)
+            .collect::<Vec<_>>();
         let db_connection = self.env.clone();
         let bodies = self.bodies;
-        let metadata = self.metadata;
+        let metadata_db = self.metadata;
 
         let task = cx.background_spawn(async move {
             let mut txn = db_connection.write_txn()?;
-
-            metadata.delete(&mut txn, &id)?;
-            bodies.delete(&mut txn, &id)?;
-
+            for (metadata, body) in &prompts {
+                metadata_db.put(&mut txn, &metadata.id, metadata)?;
+                bodies.put(&mut txn, &metadata.id, body)?;
+            }
             txn.commit()?;
             anyhow::Ok(())
         });
@@ -314,67 +1093,826 @@ impl PromptStore {
         cx.spawn(async move |this, cx| {
             task.await?;
             this.update(cx, |_, cx| cx.emit(PromptsUpdatedEvent)).ok();
-            anyhow::Ok(())
+            anyhow::Ok(ids)
         })
     }
 
-    /// Returns the number of prompts in the store.
-    pub fn prompt_count(&self) -> usize {
-        self.metadata_cache.read().metadata.len()
+    /// Hashes the id, title, default flag and body of every prompt, so that two
+    /// libraries with the same contents produce the same checksum regardless of
+    /// the order in which prompts were written.
+    pub fn content_checksum(&self, cx: &App) -> Task<Result<String>> {
+        let env = self.env.clone();
+        let metadata_db = self.metadata;
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let mut prompts = Vec::new();
+            for result in metadata_db.iter(&txn)? {
+                let (id, metadata) = result?;
+                let mut body: String = bodies.get(&txn, &id)?.unwrap_or_default().into();
+                LineEnding::normalize(&mut body);
+                prompts.push((id.to_string(), metadata.title, metadata.default, body));
+            }
+            prompts.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+            let mut hasher = Sha256::new();
+            for (id, title, default, body) in prompts {
+                let title = title.as_deref().unwrap_or_default();
+                for field in [id.as_str(), title, body.as_str()] {
+                    hasher.update((field.len() as u64).to_le_bytes());
+                    hasher.update(field.as_bytes());
+                }
+                hasher.update([default as u8]);
+            }
+            Ok(format!("{:x}", hasher.finalize()))
+        })
     }
 
-    pub fn metadata(&self, id: PromptId) -> Option<PromptMetadata> {
-        self.metadata_cache.read().metadata_by_id.get(&id).cloned()
+    /// Moves the given prompt to the front of this session's recently opened prompts.
+    pub fn record_opened(&mut self, id: PromptId, cx: &mut Context<Self>) {
+        if self.recently_opened.front() == Some(&id) {
+            return;
+        }
+        self.recently_opened.retain(|recent_id| *recent_id != id);
+        self.recently_opened.push_front(id);
+        self.recently_opened.truncate(MAX_RECENTLY_OPENED);
+        cx.notify();
     }
 
-    pub fn first(&self) -> Option<PromptMetadata> {
-        self.metadata_cache.read().metadata.first().cloned()
+    /// The most recently opened prompts that still exist, most recent first.
+    pub fn recently_opened(&self) -> Vec<PromptMetadata> {
+        let cache = self.metadata_cache.read();
+        self.recently_opened
+            .iter()
+            .filter_map(|id| cache.metadata_by_id.get(id).cloned())
+            .collect()
     }
 
-    pub fn id_for_title(&self, title: &str) -> Option<PromptId> {
-        let metadata_cache = self.metadata_cache.read();
-        let metadata = metadata_cache
-            .metadata
-            .iter()
-            .find(|metadata| metadata.title.as_ref().map(|title| &***title) == Some(title))?;
-        Some(metadata.id)
+    /// The prompt's cached token count for the active model, if it was counted.
+    pub fn token_count(&self, id: PromptId) -> Option<u64> {
+        self.token_counts.get(&id).copied()
     }
 
-    pub fn search(
+    pub fn token_counts(&self) -> &HashMap<PromptId, u64> {
+        &self.token_counts
+    }
+
+    pub fn set_token_count(&mut self, id: PromptId, token_count: u64) {
+        self.token_counts.insert(id, token_count);
+    }
+
+    /// Drops the prompt's cached token count, e.g. because its body was
+    /// changed outside the rules library.
+    pub fn invalidate_token_count(&mut self, id: PromptId, cx: &mut Context<Self>) {
+        self.token_counts.remove(&id);
+        cx.emit(TokenCountsInvalidatedEvent::Prompt(id));
+    }
+
+    /// Drops every cached token count. Call this when the active model changes.
+    pub fn invalidate_all_token_counts(&mut self, cx: &mut Context<Self>) {
+        self.token_counts.clear();
+        cx.emit(TokenCountsInvalidatedEvent::All);
+    }
+
+    /// Creates a prompt for every `.txt` or `.md` file in `dir`, titled after the
+    /// file name. Files that can't be read or aren't valid UTF-8 are skipped
+    /// with a warning, and returned so they can be reported.
+    ///
+    /// Front matter at the start of a `.md` file, like [`Self::export_to_dir`]
+    /// writes, sets the prompt's default flag and save time. The prompts in
+    /// each directory are inserted as soon as that directory is read.
+    pub fn import_plain_text_dir(
         &self,
-        query: String,
-        cancellation_flag: Arc<AtomicBool>,
-        cx: &App,
+        dir: PathBuf,
+        subdirectories: SubdirectoryImport,
+        fs: Arc<dyn Fs>,
+        cx: &Context<Self>,
+    ) -> Task<Result<PlainTextImport>> {
+        cx.spawn(async move |this, cx| {
+            let mut import = PlainTextImport::default();
+            let mut dirs = vec![(dir, Vec::<SharedString>::new())];
+            while let Some((dir, tags)) = dirs.pop() {
+                let mut prompts = Vec::new();
+                let mut entries = fs.read_dir(&dir).await?;
+                let mut paths = Vec::new();
+                while let Some(path) = entries.next().await {
+                    paths.push(path?);
+                }
+                paths.sort();
+
+                for path in paths {
+                    if fs.is_dir(&path).await {
+                        let mut subdirectory_tags = tags.clone();
+                        match subdirectories {
+                            SubdirectoryImport::Skip => continue,
+                            SubdirectoryImport::Include => {}
+                            SubdirectoryImport::IncludeAsTags => {
+                                if let Some(name) = path.file_name() {
+                                    let tag =
+                                        SharedString::from(name.to_string_lossy().into_owned());
+                                    if !subdirectory_tags.contains(&tag) {
+                                        subdirectory_tags.push(tag);
+                                    }
+                                }
+                            }
+                        }
+                        dirs.push((path, subdirectory_tags));
+                        continue;
+                    }
+                    let extension = path.extension().and_then(|extension| extension.to_str());
+                    if !matches!(extension, Some("txt" | "md")) {
+                        continue;
+                    }
+                    let Some(title) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                        continue;
+                    };
+                    let bytes = match fs.load_bytes(&path).await {
+                        Ok(bytes) => bytes,
+                        Err(error) => {
+                            log::warn!("skipping {path:?}: {error}");
+                            import.skipped_paths.push(path);
+                            continue;
+                        }
+                    };
+                    let mut body = match String::from_utf8(bytes) {
+                        Ok(body) => body,
+                        Err(_) => {
+                            log::warn!("skipping {path:?}: not valid UTF-8");
+                            import.skipped_paths.push(path);
+                            continue;
+                        }
+                    };
+                    LineEnding::normalize(&mut body);
+                    let (front_matter, body) = match extension {
+                        Some("md") => {
+                            let (front_matter, body) = parse_front_matter(&body);
+                            (front_matter.unwrap_or_default(), body.to_string())
+                        }
+                        _ => (FrontMatter::default(), body),
+                    };
+                    prompts.push(PromptBundleEntry {
+                        title: Some(title.to_string().into()),
+                        default: front_matter.default,
+                        saved_at: front_matter.saved_at,
+                        tags: tags.clone(),
+                        body,
+                    });
+                }
+
+                if prompts.is_empty() {
+                    continue;
+                }
+                let bundle = PromptBundle {
+                    version: PromptBundle::VERSION,
+                    prompts,
+                };
+                import.prompt_ids.extend(
+                    this.update(cx, |this, cx| this.insert_bundle(bundle, cx))?
+                        .await?,
+                );
+            }
+            Ok(import)
+        })
+    }
+
+    /// Writes every user prompt to `dir` as a Markdown file named after its
+    /// title, with its metadata in a front matter block. Titles that collide,
+    /// or are empty, get the same numeric suffixes duplicated prompts do.
+    /// When `tags` isn't empty, only the prompts that have all of them (ignoring
+    /// case) are written. Returns the number of files written.
+    pub fn export_to_dir(
+        &self,
+        dir: PathBuf,
+        tags: Vec<SharedString>,
+        fs: Arc<dyn Fs>,
+        cx: &App,
+    ) -> Task<Result<usize>> {
+        let prompts = {
+            let cache = self.metadata_cache.read();
+            user_prompt_ids(&cache)
+                .into_iter()
+                .filter_map(|id| cache.metadata_by_id.get(&id).cloned())
+                .filter(|metadata| {
+                    tags.iter().all(|tag| {
+                        metadata
+                            .tags
+                            .iter()
+                            .any(|prompt_tag| prompt_tag.eq_ignore_ascii_case(tag))
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let prompts = {
+                let txn = env.read_txn()?;
+                let mut prompts_with_bodies = Vec::with_capacity(prompts.len());
+                for metadata in prompts {
+                    let Some(body) = bodies.get(&txn, &metadata.id)? else {
+                        log::warn!("skipping prompt {:?} without a body", metadata.id);
+                        continue;
+                    };
+                    let mut body: String = body.into();
+                    LineEnding::normalize(&mut body);
+                    prompts_with_bodies.push((metadata, body));
+                }
+                prompts_with_bodies
+            };
+
+            fs.create_dir(&dir).await?;
+            let mut file_stems = HashSet::default();
+            for (metadata, body) in &prompts {
+                let title = metadata
+                    .title
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|title| !title.is_empty())
+                    .map(file_stem_for_title)
+                    .unwrap_or_else(|| metadata.id.to_string());
+                let file_stem = unique_title(&title, &file_stems);
+                fs.atomic_write(
+                    dir.join(format!("{file_stem}.md")),
+                    markdown_with_front_matter(metadata, body),
+                )
+                .await?;
+                file_stems.insert(file_stem);
+            }
+            Ok(prompts.len())
+        })
+    }
+
+    /// Writes the metadata and body of every prompt to a single JSON file at
+    /// `path`, which [`Self::restore_archive`] can merge back in.
+    pub fn backup(&self, path: PathBuf, fs: Arc<dyn Fs>, cx: &App) -> Task<Result<()>> {
+        let env = self.env.clone();
+        let metadata_db = self.metadata;
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let archive = {
+                let txn = env.read_txn()?;
+                let mut prompts = Vec::new();
+                for result in metadata_db.iter(&txn)? {
+                    let (prompt_id, metadata) = result?;
+                    let body = bodies.get(&txn, &prompt_id)?.map(|body| {
+                        let mut body = body.to_string();
+                        LineEnding::normalize(&mut body);
+                        body
+                    });
+                    prompts.push(ArchivedPrompt { metadata, body });
+                }
+                PromptArchive {
+                    version: PromptArchive::VERSION,
+                    prompts,
+                }
+            };
+            fs.atomic_write(path, serde_json::to_string_pretty(&archive)?)
+                .await
+        })
+    }
+
+    /// Merges a file written by [`Self::backup`] into the store. A prompt that
+    /// already exists is only replaced if the archived one was saved later, the
+    /// same way prompts are merged when upgrading the database. Restored prompts
+    /// are taken out of the trash, so undoing an earlier deletion can't
+    /// overwrite them. Built-in prompts that no longer ship are skipped.
+    /// Returns the prompts that were added or replaced.
+    pub fn restore_archive(
+        &self,
+        path: PathBuf,
+        fs: Arc<dyn Fs>,
+        cx: &Context<Self>,
+    ) -> Task<Result<Vec<PromptId>>> {
+        let env = self.env.clone();
+        let metadata_db = self.metadata;
+        let bodies = self.bodies;
+        let trash = self.trash;
+        let task = cx.background_spawn(async move {
+            let text = fs.load(&path).await?;
+            let archive: PromptArchive =
+                serde_json::from_str(&text).context("not a prompt library backup")?;
+            anyhow::ensure!(
+                archive.version == PromptArchive::VERSION,
+                "unsupported prompt library backup version {}",
+                archive.version
+            );
+
+            let mut restored = Vec::new();
+            let mut txn = env.write_txn()?;
+            for ArchivedPrompt { metadata, body } in archive.prompts {
+                if metadata.id.is_built_in()
+                    && (RETIRED_BUILT_IN_PROMPTS.contains(&metadata.id)
+                        || !built_in_prompts()
+                            .iter()
+                            .any(|prompt| prompt.id == metadata.id))
+                {
+                    continue;
+                }
+                if metadata_db
+                    .get(&txn, &metadata.id)?
+                    .is_some_and(|existing| existing.saved_at >= metadata.saved_at)
+                {
+                    continue;
+                }
+                if let Some(body) = &body {
+                    bodies.put(&mut txn, &metadata.id, body)?;
+                } else if !metadata.id.is_built_in() {
+                    log::warn!("skipping backed up prompt {:?} without a body", metadata.id);
+                    continue;
+                }
+                metadata_db.put(&mut txn, &metadata.id, &metadata)?;
+                trash.delete(&mut txn, &metadata.id)?;
+                restored.push(metadata);
+            }
+            txn.commit()?;
+            anyhow::Ok(restored)
+        });
+
+        cx.spawn(async move |this, cx| {
+            let restored = task.await?;
+            let restored_ids = restored
+                .iter()
+                .map(|metadata| metadata.id)
+                .collect::<Vec<_>>();
+            this.update(cx, |this, cx| {
+                // Merge rather than reload, so prompts saved while the archive
+                // was being restored stay in the cache.
+                let mut cache = this.metadata_cache.write();
+                for metadata in restored {
+                    cache.insert(metadata);
+                }
+                drop(cache);
+                for id in &restored_ids {
+                    this.invalidate_token_count(*id, cx);
+                }
+                cx.emit(PromptsUpdatedEvent);
+            })?;
+            anyhow::Ok(restored_ids)
+        })
+    }
+
+    pub fn all_prompt_metadata(&self) -> Vec<PromptMetadata> {
+        self.metadata_cache.read().metadata.clone()
+    }
+
+    pub fn default_prompt_metadata(&self) -> Vec<PromptMetadata> {
+        return self
+            .metadata_cache
+            .read()
+            .metadata
+            .iter()
+            .filter(|metadata| metadata.default && !metadata.disabled && !metadata.archived)
+            .cloned()
+            .collect::<Vec<_>>();
+    }
+
+    /// Loads every default prompt along with its body, in the same order as
+    /// [`Self::default_prompt_metadata`]. Prompts whose body is missing are
+    /// skipped rather than failing the whole batch.
+    pub fn default_prompts_with_bodies(
+        &self,
+        cx: &App,
+    ) -> Task<Result<Vec<(PromptMetadata, String)>>> {
+        let default_prompts = self.default_prompt_metadata();
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let mut prompts = Vec::with_capacity(default_prompts.len());
+            for metadata in default_prompts {
+                let Some(body) = bodies.get(&txn, &metadata.id)? else {
+                    log::warn!("skipping default prompt {:?} without a body", metadata.id);
+                    continue;
+                };
+                let mut body: String = body.into();
+                LineEnding::normalize(&mut body);
+                prompts.push((metadata, body));
+            }
+            Ok(prompts)
+        })
+    }
+
+    /// Moves the prompt to the trash, from which it can be restored with
+    /// [`Self::restore`] for [`TRASH_RETENTION_DAYS`]. Resolves to whether the
+    /// prompt was put in the trash, which built-in prompts never are.
+    pub fn delete(&self, id: PromptId, cx: &Context<Self>) -> Task<Result<bool>> {
+        self.metadata_cache.write().remove(id);
+
+        let db_connection = self.env.clone();
+        let bodies = self.bodies;
+        let metadata = self.metadata;
+        let trash = self.trash;
+
+        let task = cx.background_spawn(async move {
+            let mut txn = db_connection.write_txn()?;
+            let trashed = Self::move_to_trash(&mut txn, metadata, bodies, trash, id, Utc::now())?;
+            txn.commit()?;
+            anyhow::Ok(trashed)
+        });
+
+        cx.spawn(async move |this, cx| {
+            let trashed = task.await?;
+            this.update(cx, |_, cx| cx.emit(PromptsUpdatedEvent)).ok();
+            anyhow::Ok(trashed)
+        })
+    }
+
+    /// Moves several prompts to the trash in a single write transaction, so
+    /// either all of them are deleted or none are. The cache is only updated,
+    /// and [`PromptsUpdatedEvent`] only emitted, once the transaction has been
+    /// committed. Resolves to the prompts that were put in the trash.
+    pub fn delete_many(
+        &self,
+        ids: Vec<PromptId>,
+        cx: &Context<Self>,
+    ) -> Task<Result<Vec<PromptId>>> {
+        let db_connection = self.env.clone();
+        let bodies = self.bodies;
+        let metadata = self.metadata;
+        let trash = self.trash;
+
+        let task = cx.background_spawn(async move {
+            let mut txn = db_connection.write_txn()?;
+            let deleted_at = Utc::now();
+            let mut trashed_ids = Vec::new();
+            for id in &ids {
+                if Self::move_to_trash(&mut txn, metadata, bodies, trash, *id, deleted_at)? {
+                    trashed_ids.push(*id);
+                }
+            }
+            txn.commit()?;
+            anyhow::Ok((ids, trashed_ids))
+        });
+
+        cx.spawn(async move |this, cx| {
+            let (ids, trashed_ids) = task.await?;
+            this.update(cx, |this, cx| {
+                this.metadata_cache
+                    .write()
+                    .remove_many(&ids.into_iter().collect());
+                cx.emit(PromptsUpdatedEvent);
+            })
+            .ok();
+            anyhow::Ok(trashed_ids)
+        })
+    }
+
+    /// Deletes the prompt, returning whether it was put in the trash.
+    fn move_to_trash(
+        txn: &mut heed::RwTxn,
+        metadata: Database<SerdeJson<PromptId>, SerdeJson<PromptMetadata>>,
+        bodies: Database<SerdeJson<PromptId>, Str>,
+        trash: Database<SerdeJson<PromptId>, SerdeJson<TrashedPrompt>>,
+        id: PromptId,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let mut trashed = false;
+        // Built-in prompts are seeded again on the next launch, so there's
+        // nothing worth restoring.
+        if !id.is_built_in()
+            && let Some(prompt_metadata) = metadata.get(txn, &id)?
+        {
+            let body = bodies.get(txn, &id)?.unwrap_or_default().to_string();
+            trash.put(
+                txn,
+                &id,
+                &TrashedPrompt {
+                    metadata: prompt_metadata,
+                    body,
+                    deleted_at,
+                },
+            )?;
+            trashed = true;
+        }
+        metadata.delete(txn, &id)?;
+        bodies.delete(txn, &id)?;
+        Ok(trashed)
+    }
+
+    /// Puts a prompt deleted with [`Self::delete`] or [`Self::delete_many`]
+    /// back, with the metadata and body it had when it was deleted.
+    pub fn restore(&self, id: PromptId, cx: &Context<Self>) -> Task<Result<()>> {
+        self.restore_many(vec![id], cx)
+    }
+
+    /// Like [`Self::restore`], but puts several prompts back in a single write
+    /// transaction, so either all of them are restored or none are.
+    pub fn restore_many(&self, ids: Vec<PromptId>, cx: &Context<Self>) -> Task<Result<()>> {
+        let db_connection = self.env.clone();
+        let bodies = self.bodies;
+        let metadata = self.metadata;
+        let trash = self.trash;
+
+        let task = cx.background_spawn(async move {
+            let mut txn = db_connection.write_txn()?;
+            let mut restored = Vec::with_capacity(ids.len());
+            for id in ids {
+                let trashed = trash
+                    .get(&txn, &id)?
+                    .context("prompt is not in the trash")?;
+                metadata.put(&mut txn, &id, &trashed.metadata)?;
+                bodies.put(&mut txn, &id, &trashed.body)?;
+                trash.delete(&mut txn, &id)?;
+                restored.push(trashed.metadata);
+            }
+            txn.commit()?;
+            anyhow::Ok(restored)
+        });
+
+        cx.spawn(async move |this, cx| {
+            let restored = task.await?;
+            this.update(cx, |this, cx| {
+                let mut cache = this.metadata_cache.write();
+                for prompt_metadata in restored {
+                    cache.insert(prompt_metadata);
+                }
+                drop(cache);
+                cx.emit(PromptsUpdatedEvent);
+            })
+            .ok();
+            anyhow::Ok(())
+        })
+    }
+
+    /// Changes a prompt's title without touching its body. Only the metadata
+    /// row is written, with a new `saved_at`.
+    pub fn rename(
+        &self,
+        id: PromptId,
+        title: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        let task = self.rename_updating_includes(id, title, None, cx);
+        cx.spawn(async move |_, _| {
+            task.await?;
+            Ok(())
+        })
+    }
+
+    /// Renames a prompt. With `previous_title`, `@include(previous_title)`
+    /// references in every user prompt, the renamed one included, are pointed
+    /// at the new title in the same write transaction. The previous title is
+    /// passed in because the rules library saves titles as they're typed, so
+    /// the stored title may already be the new one. References by id don't
+    /// depend on the title and are left alone. Returns the prompts whose bodies
+    /// were updated.
+    pub fn rename_updating_includes(
+        &self,
+        id: PromptId,
+        title: Option<SharedString>,
+        previous_title: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<Vec<PromptId>>> {
+        if id.is_built_in() {
+            return Task::ready(Err(anyhow!("built-in prompts cannot be renamed")));
+        }
+
+        let mut cache = self.metadata_cache.write();
+        let Some(previous_metadata) = cache.metadata_by_id.get(&id).cloned() else {
+            return Task::ready(Err(anyhow!("prompt not found")));
+        };
+        let rename_includes = previous_title
+            .zip(title.clone())
+            .filter(|(old_title, new_title)| old_title != new_title);
+        let prompt_metadata = PromptMetadata {
+            title,
+            saved_at: Utc::now(),
+            ..previous_metadata
+        };
+        cache.insert(prompt_metadata.clone());
+        let ids = user_prompt_ids(&cache);
+        drop(cache);
+
+        let db_connection = self.env.clone();
+        let bodies = self.bodies;
+        let metadata = self.metadata;
+        let task = cx.background_spawn(async move {
+            let mut txn = db_connection.write_txn()?;
+            metadata.put(&mut txn, &id, &prompt_metadata)?;
+            let updated_ids = match rename_includes {
+                Some((old_title, new_title)) => {
+                    rewrite_title_includes(&mut txn, bodies, ids, &old_title, &new_title)?
+                }
+                None => Vec::new(),
+            };
+            txn.commit()?;
+            anyhow::Ok(updated_ids)
+        });
+
+        cx.spawn(async move |this, cx| {
+            let updated_ids = task.await?;
+            this.update(cx, |this, cx| {
+                for id in &updated_ids {
+                    this.invalidate_token_count(*id, cx);
+                }
+                cx.emit(PromptsUpdatedEvent);
+            })
+            .ok();
+            Ok(updated_ids)
+        })
+    }
+
+    /// Finds the user prompts whose bodies include the prompt titled `title`
+    /// by its title.
+    pub fn prompts_including_title(
+        &self,
+        title: SharedString,
+        cx: &App,
+    ) -> Task<Result<Vec<PromptId>>> {
+        let ids = user_prompt_ids(&self.metadata_cache.read());
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let target = IncludeTarget::Title(title.to_string());
+            let mut including = Vec::new();
+            for id in ids {
+                if let Some(body) = bodies.get(&txn, &id)?
+                    && parse_includes(body)
+                        .iter()
+                        .any(|reference| reference.target == target)
+                {
+                    including.push(id);
+                }
+            }
+            Ok(including)
+        })
+    }
+
+    /// Finds user prompts that have no title and an empty body. Default,
+    /// locked and built-in prompts are never included.
+    pub fn empty_prompt_ids(&self, cx: &App) -> Task<Result<Vec<PromptId>>> {
+        let candidates = self
+            .metadata_cache
+            .read()
+            .metadata
+            .iter()
+            .filter(|metadata| {
+                !metadata.id.is_built_in()
+                    && !metadata.default
+                    && !metadata.locked
+                    && metadata
+                        .title
+                        .as_ref()
+                        .is_none_or(|title| title.trim().is_empty())
+            })
+            .map(|metadata| metadata.id)
+            .collect::<Vec<_>>();
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        cx.background_spawn(async move {
+            let txn = env.read_txn()?;
+            let mut empty = Vec::new();
+            for id in candidates {
+                if bodies
+                    .get(&txn, &id)?
+                    .is_none_or(|body| body.trim().is_empty())
+                {
+                    empty.push(id);
+                }
+            }
+            Ok(empty)
+        })
+    }
+
+    /// Returns the number of prompts in the store.
+    pub fn prompt_count(&self) -> usize {
+        self.metadata_cache.read().metadata.len()
+    }
+
+    pub fn metadata(&self, id: PromptId) -> Option<PromptMetadata> {
+        self.metadata_cache.read().metadata_by_id.get(&id).cloned()
+    }
+
+    pub fn first(&self) -> Option<PromptMetadata> {
+        self.metadata_cache.read().metadata.first().cloned()
+    }
+
+    /// Returns the first prompt without a title, if any.
+    pub fn first_untitled(&self) -> Option<PromptMetadata> {
+        self.metadata_cache
+            .read()
+            .metadata
+            .iter()
+            .find(|metadata| metadata.title.is_none())
+            .cloned()
+    }
+
+    pub fn id_for_title(&self, title: &str) -> Option<PromptId> {
+        let metadata_cache = self.metadata_cache.read();
+        let metadata = metadata_cache
+            .metadata
+            .iter()
+            .find(|metadata| metadata.title.as_ref().map(|title| &***title) == Some(title))?;
+        Some(metadata.id)
+    }
+
+    pub fn search(
+        &self,
+        query: String,
+        cancellation_flag: Arc<AtomicBool>,
+        cx: &App,
     ) -> Task<Vec<PromptMetadata>> {
+        let matches = self.search_with_positions(
+            query,
+            PromptSearchOptions::default(),
+            cancellation_flag,
+            cx,
+        );
+        cx.background_spawn(
+            async move { matches.await.into_iter().map(|mat| mat.metadata).collect() },
+        )
+    }
+
+    /// Like [`Self::search`], but matches the query as `options` say and also
+    /// reports which characters of each prompt's title, description and notes
+    /// matched it.
+    pub fn search_with_positions(
+        &self,
+        query: String,
+        options: PromptSearchOptions,
+        cancellation_flag: Arc<AtomicBool>,
+        cx: &App,
+    ) -> Task<Vec<PromptMatch>> {
         let cached_metadata = self.metadata_cache.read().metadata.clone();
         let executor = cx.background_executor().clone();
         cx.background_spawn(async move {
-            let mut matches = if query.is_empty() {
+            let mut matches: Vec<PromptMatch> = if query.is_empty() {
+                cached_metadata
+                    .into_iter()
+                    .map(|metadata| PromptMatch {
+                        metadata,
+                        title_positions: Vec::new(),
+                        description_positions: Vec::new(),
+                        notes_positions: Vec::new(),
+                    })
+                    .collect()
+            } else if options.exact {
                 cached_metadata
+                    .into_iter()
+                    .filter_map(|metadata| {
+                        let [title_positions, description_positions, notes_positions] =
+                            searchable_fields(&metadata).map(|field| {
+                                field
+                                    .and_then(|field| {
+                                        find_exact(field, &query, options.case_sensitive)
+                                    })
+                                    .unwrap_or_default()
+                            });
+                        let matched = !title_positions.is_empty()
+                            || !description_positions.is_empty()
+                            || !notes_positions.is_empty();
+                        matched.then_some(PromptMatch {
+                            metadata,
+                            title_positions,
+                            description_positions,
+                            notes_positions,
+                        })
+                    })
+                    .collect()
             } else {
                 let candidates = cached_metadata
                     .iter()
                     .enumerate()
                     .filter_map(|(ix, metadata)| {
-                        Some(StringMatchCandidate::new(ix, metadata.title.as_ref()?))
+                        let text = searchable_fields(metadata)
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        (!text.is_empty()).then(|| StringMatchCandidate::new(ix, &text))
                     })
                     .collect::<Vec<_>>();
-                let matches = fuzzy::match_strings(
+                let mut matches = fuzzy::match_strings(
                     &candidates,
                     &query,
-                    false,
+                    options.case_sensitive,
                     true,
                     100,
                     &cancellation_flag,
                     executor,
                 )
                 .await;
+                if options.case_sensitive {
+                    matches.retain(|mat| positions_match_case(&mat.string, &mat.positions, &query));
+                }
                 matches
                     .into_iter()
-                    .map(|mat| cached_metadata[mat.candidate_id].clone())
+                    .map(|mat| {
+                        let metadata = cached_metadata[mat.candidate_id].clone();
+                        let [title_positions, description_positions, notes_positions] =
+                            split_field_positions(searchable_fields(&metadata), &mat.positions);
+                        PromptMatch {
+                            metadata,
+                            title_positions,
+                            description_positions,
+                            notes_positions,
+                        }
+                    })
                     .collect()
             };
-            matches.sort_by_key(|metadata| Reverse(metadata.default));
+            matches
+                .sort_by_key(|mat| (Reverse(mat.metadata.pinned), Reverse(mat.metadata.default)));
             matches
         })
     }
@@ -391,25 +1929,41 @@ impl PromptStore {
             return Task::ready(Err(anyhow!("built-in prompts cannot be saved")));
         }
 
+        let mut cache = self.metadata_cache.write();
+        let previous_metadata = cache.get_or_new(id);
         let prompt_metadata = PromptMetadata {
-            id,
             title,
-            default,
+            // Archived prompts can't be defaults.
+            default: default && !previous_metadata.archived,
             saved_at: Utc::now(),
+            ..previous_metadata
         };
-        self.metadata_cache.write().insert(prompt_metadata.clone());
+        cache.insert(prompt_metadata.clone());
+        drop(cache);
 
         let db_connection = self.env.clone();
         let bodies = self.bodies;
         let metadata = self.metadata;
+        let trim_trailing_whitespace =
+            RulesLibrarySettings::get_global(cx).trim_trailing_whitespace_on_save;
+        let slow_commit_log = SlowCommitLog::enabled().then(|| self.slow_commit_log.clone());
 
         let task = cx.background_spawn(async move {
+            let mut body = body.to_string();
+            if trim_trailing_whitespace {
+                body = trim_trailing_whitespace_outside_code_blocks(&body);
+            }
+
+            let started_at = slow_commit_log.as_ref().map(|_| Instant::now());
             let mut txn = db_connection.write_txn()?;
 
             metadata.put(&mut txn, &id, &prompt_metadata)?;
-            bodies.put(&mut txn, &id, &body.to_string())?;
+            bodies.put(&mut txn, &id, &body)?;
 
             txn.commit()?;
+            if let Some((slow_commit_log, started_at)) = slow_commit_log.zip(started_at) {
+                slow_commit_log.record("save", started_at.elapsed());
+            }
 
             anyhow::Ok(())
         });
@@ -437,14 +1991,243 @@ impl PromptStore {
                 .and_then(|metadata| metadata.title.clone());
         }
 
+        let previous_metadata = cache.get_or_new(id);
         let prompt_metadata = PromptMetadata {
-            id,
             title,
-            default,
+            // Archived prompts can't be defaults.
+            default: default && !previous_metadata.archived,
             saved_at: Utc::now(),
+            ..previous_metadata
         };
 
         cache.insert(prompt_metadata.clone());
+        drop(cache);
+
+        let db_connection = self.env.clone();
+        let metadata = self.metadata;
+        let slow_commit_log = SlowCommitLog::enabled().then(|| self.slow_commit_log.clone());
+
+        let task = cx.background_spawn(async move {
+            let started_at = slow_commit_log.as_ref().map(|_| Instant::now());
+            let mut txn = db_connection.write_txn()?;
+            metadata.put(&mut txn, &id, &prompt_metadata)?;
+            txn.commit()?;
+            if let Some((slow_commit_log, started_at)) = slow_commit_log.zip(started_at) {
+                slow_commit_log.record("metadata save", started_at.elapsed());
+            }
+
+            anyhow::Ok(())
+        });
+
+        cx.spawn(async move |this, cx| {
+            task.await?;
+            this.update(cx, |_, cx| cx.emit(PromptsUpdatedEvent)).ok();
+            anyhow::Ok(())
+        })
+    }
+
+    pub fn set_locked(&self, id: PromptId, locked: bool, cx: &Context<Self>) -> Task<Result<()>> {
+        if id.is_built_in() {
+            return Task::ready(Err(anyhow!("built-in prompts are always read-only")));
+        }
+        self.update_metadata(id, |metadata| metadata.locked = locked, cx)
+    }
+
+    /// Disables or enables the prompt. A disabled default prompt keeps its
+    /// default flag, but isn't one of the [`Self::default_prompt_metadata`].
+    pub fn set_disabled(
+        &self,
+        id: PromptId,
+        disabled: bool,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        self.update_metadata(id, |metadata| metadata.disabled = disabled, cx)
+    }
+
+    /// Archives or unarchives the prompt. Archiving a default prompt removes it
+    /// from the defaults first.
+    pub fn set_archived(
+        &self,
+        id: PromptId,
+        archived: bool,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        if id.is_built_in() {
+            return Task::ready(Err(anyhow!("built-in prompts can't be archived")));
+        }
+        self.update_metadata(
+            id,
+            |metadata| {
+                metadata.archived = archived;
+                if archived {
+                    metadata.default = false;
+                }
+            },
+            cx,
+        )
+    }
+
+    pub fn set_notes(
+        &self,
+        id: PromptId,
+        notes: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        self.update_metadata(id, |metadata| metadata.notes = notes, cx)
+    }
+
+    /// Sets the prompt's description, or clears it if `description` is `None`.
+    pub fn set_description(
+        &self,
+        id: PromptId,
+        description: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        self.update_metadata(id, |metadata| metadata.description = description, cx)
+    }
+
+    /// Sets the language the prompt's body is highlighted as, or goes back to
+    /// Markdown if `language` is `None`.
+    pub fn set_language(
+        &self,
+        id: PromptId,
+        language: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        if id.is_built_in() {
+            return Task::ready(Err(anyhow!("built-in prompts are always markdown")));
+        }
+        self.update_metadata(id, |metadata| metadata.language = language, cx)
+    }
+
+    /// Sets the prompt's tags. Tags are trimmed, and empty ones or ones that
+    /// repeat an earlier tag (ignoring case) are dropped.
+    pub fn set_tags(
+        &self,
+        id: PromptId,
+        tags: Vec<SharedString>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        if id.is_built_in() {
+            return Task::ready(Err(anyhow!("built-in prompts cannot be tagged")));
+        }
+        let mut unique_tags = Vec::<SharedString>::with_capacity(tags.len());
+        for tag in &tags {
+            let tag = tag.trim();
+            if !tag.is_empty()
+                && !unique_tags
+                    .iter()
+                    .any(|unique_tag| unique_tag.eq_ignore_ascii_case(tag))
+            {
+                unique_tags.push(tag.to_string().into());
+            }
+        }
+        self.update_metadata(id, |metadata| metadata.tags = unique_tags, cx)
+    }
+
+    pub fn set_linked_path(
+        &self,
+        id: PromptId,
+        linked_path: Option<PathBuf>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        self.update_metadata(id, |metadata| metadata.linked_path = linked_path, cx)
+    }
+
+    pub fn toggle_pinned(&self, id: PromptId, cx: &Context<Self>) -> Task<Result<()>> {
+        self.update_metadata(id, |metadata| metadata.pinned = !metadata.pinned, cx)
+    }
+
+    /// Counts a use of the prompt, e.g. because it was sent in a thread.
+    /// Doesn't change when the prompt was last saved.
+    pub fn record_usage(&self, id: PromptId, cx: &Context<Self>) -> Task<Result<()>> {
+        self.update_metadata(
+            id,
+            |metadata| {
+                metadata.usage_count += 1;
+                metadata.last_used_at = Some(Utc::now());
+            },
+            cx,
+        )
+    }
+
+    pub fn sort_order(&self) -> RulesLibrarySortOrder {
+        self.metadata_cache.read().sort_order
+    }
+
+    /// Re-sorts the prompts, and saves the order as the rules library's
+    /// `sort_order` setting.
+    pub fn set_sort_order(
+        &self,
+        sort_order: RulesLibrarySortOrder,
+        fs: Arc<dyn Fs>,
+        cx: &mut Context<Self>,
+    ) {
+        self.apply_sort_order(sort_order, cx);
+        settings::update_settings_file(fs, cx, move |settings, _| {
+            settings.rules_library.get_or_insert_default().sort_order = Some(sort_order);
+        });
+    }
+
+    fn apply_sort_order(&self, sort_order: RulesLibrarySortOrder, cx: &mut Context<Self>) {
+        let mut cache = self.metadata_cache.write();
+        if cache.sort_order != sort_order {
+            cache.sort_order = sort_order;
+            cache.sort();
+            drop(cache);
+            cx.emit(PromptsUpdatedEvent);
+        }
+    }
+
+    /// Returns the value extensions stored under `key` for the prompt.
+    pub fn extra(&self, id: PromptId, key: &str) -> Option<serde_json::Value> {
+        self.metadata_cache
+            .read()
+            .metadata_by_id
+            .get(&id)?
+            .extra
+            .get(key)
+            .cloned()
+    }
+
+    /// Stores `value` under `key` in the prompt's extension data, or removes
+    /// `key` if `value` is `None`. Other keys are left untouched.
+    pub fn set_extra(
+        &self,
+        id: PromptId,
+        key: String,
+        value: Option<serde_json::Value>,
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        self.update_metadata(
+            id,
+            |metadata| match value {
+                Some(value) => {
+                    metadata.extra.insert(key, value);
+                }
+                None => {
+                    metadata.extra.remove(&key);
+                }
+            },
+            cx,
+        )
+    }
+
+    /// Applies `update` to the metadata of an existing prompt without changing when it
+    /// was last saved.
+    fn update_metadata(
+        &self,
+        id: PromptId,
+        update: impl FnOnce(&mut PromptMetadata),
+        cx: &Context<Self>,
+    ) -> Task<Result<()>> {
+        let mut cache = self.metadata_cache.write();
+        let Some(mut prompt_metadata) = cache.metadata_by_id.get(&id).cloned() else {
+            return Task::ready(Err(anyhow!("prompt not found")));
+        };
+        update(&mut prompt_metadata);
+        cache.insert(prompt_metadata.clone());
+        drop(cache);
 
         let db_connection = self.env.clone();
         let metadata = self.metadata;
@@ -466,6 +2249,803 @@ impl PromptStore {
 }
 
 /// Wraps a shared future to a prompt store so it can be assigned as a context global.
-pub struct GlobalPromptStore(Shared<Task<Result<Entity<PromptStore>, Arc<anyhow::Error>>>>);
+pub struct GlobalPromptStore(
+    Shared<Task<Result<Entity<PromptStore>, Arc<anyhow::Error>>>>,
+    Arc<MigrationProgress>,
+);
 
 impl Global for GlobalPromptStore {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use std::{cell::Cell, path::Path, rc::Rc};
+
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+        });
+    }
+
+    async fn open_store(db_path: &Path, cx: &mut TestAppContext) -> Entity<PromptStore> {
+        let store = cx
+            .update(|cx| PromptStore::new(db_path.to_path_buf(), Arc::default(), cx))
+            .await
+            .unwrap();
+        cx.new(|_| store)
+    }
+
+    async fn save_prompt(
+        store: &Entity<PromptStore>,
+        title: &str,
+        body: &str,
+        cx: &mut TestAppContext,
+    ) -> PromptMetadata {
+        let id = PromptId::new();
+        store
+            .update(cx, |store, cx| {
+                store.save(id, Some(title.to_string().into()), false, body.into(), cx)
+            })
+            .await
+            .unwrap();
+        store.read_with(cx, |store, _| store.metadata(id).unwrap())
+    }
+
+    async fn load_body(
+        store: &Entity<PromptStore>,
+        id: PromptId,
+        cx: &mut TestAppContext,
+    ) -> String {
+        store
+            .read_with(cx, |store, cx| store.load(id, cx))
+            .await
+            .unwrap()
+    }
+
+    fn saved_at(store: &PromptStore, id: PromptId) -> Option<DateTime<Utc>> {
+        store.metadata(id).map(|metadata| metadata.saved_at)
+    }
+
+    async fn write_archive(fs: &FakeFs, path: &Path, archive: &PromptArchive) {
+        fs.insert_file(path, serde_json::to_vec(archive).unwrap())
+            .await;
+    }
+
+    /// Writes `count` prompts to the databases older versions of Zed used,
+    /// each saved a minute after the previous one.
+    fn seed_v1_prompts(db_path: &Path, count: usize) -> Vec<PromptMetadataV1> {
+        std::fs::create_dir_all(db_path).unwrap();
+        let env = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(1024 * 1024 * 1024)
+                .max_dbs(MAX_DBS)
+                .open(db_path)
+                .unwrap()
+        };
+        let mut txn = env.write_txn().unwrap();
+        let metadata_db = env
+            .create_database::<SerdeBincode<PromptIdV1>, SerdeBincode<PromptMetadataV1>>(
+                &mut txn,
+                Some("metadata"),
+            )
+            .unwrap();
+        let bodies_db = env
+            .create_database::<SerdeBincode<PromptIdV1>, SerdeBincode<String>>(
+                &mut txn,
+                Some("bodies"),
+            )
+            .unwrap();
+        let first_saved_at = Utc::now() - chrono::Duration::days(1);
+        let prompts = (0..count)
+            .map(|ix| PromptMetadataV1 {
+                id: PromptIdV1(Uuid::new_v4()),
+                title: Some(format!("Prompt {ix}").into()),
+                default: ix == 0,
+                saved_at: first_saved_at + chrono::Duration::minutes(ix as i64),
+            })
+            .collect::<Vec<_>>();
+        for (ix, prompt) in prompts.iter().enumerate() {
+            metadata_db.put(&mut txn, &prompt.id, prompt).unwrap();
+            bodies_db
+                .put(&mut txn, &prompt.id, &format!("body {ix}"))
+                .unwrap();
+        }
+        txn.commit().unwrap();
+        prompts
+    }
+
+    fn v2_id(prompt: &PromptMetadataV1) -> PromptId {
+        UserPromptId(prompt.id.0).into()
+    }
+
+    #[gpui::test]
+    async fn test_migrate_v1_prompts(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let prompts = seed_v1_prompts(db_dir.path(), 2 * V1_MIGRATION_BATCH_SIZE + 1);
+
+        let progress = Arc::new(MigrationProgress::default());
+        let store = cx
+            .update(|cx| PromptStore::new(db_dir.path().to_path_buf(), progress.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(progress.total(), prompts.len());
+        assert_eq!(progress.migrated(), prompts.len());
+        assert!(!progress.is_running());
+
+        assert_eq!(store.prompt_count(), prompts.len());
+        for prompt in &prompts {
+            let metadata = store.metadata(v2_id(prompt)).unwrap();
+            assert_eq!(metadata.title, prompt.title);
+            assert_eq!(metadata.default, prompt.default);
+            assert_eq!(metadata.saved_at, prompt.saved_at);
+        }
+        let last = prompts.len() - 1;
+        let body = cx
+            .update(|cx| store.load(v2_id(&prompts[last]), cx))
+            .await
+            .unwrap();
+        assert_eq!(body, format!("body {last}"));
+    }
+
+    #[gpui::test]
+    async fn test_resume_cancelled_v1_migration(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let prompts = seed_v1_prompts(db_dir.path(), 2 * V1_MIGRATION_BATCH_SIZE + 1);
+
+        // Cancelling before the first batch leaves every prompt to the next
+        // time the store is opened.
+        let progress = Arc::new(MigrationProgress::default());
+        progress.cancel();
+        let store = cx
+            .update(|cx| PromptStore::new(db_dir.path().to_path_buf(), progress.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(progress.migrated(), 0);
+        assert_eq!(store.prompt_count(), 0);
+
+        // Prompts that were already migrated and edited since are kept when
+        // the migration runs again.
+        let edited = PromptMetadata {
+            title: Some("Edited".into()),
+            saved_at: Utc::now(),
+            ..PromptMetadata::new(v2_id(&prompts[0]))
+        };
+        let mut txn = store.env.write_txn().unwrap();
+        store.metadata.put(&mut txn, &edited.id, &edited).unwrap();
+        store
+            .bodies
+            .put(&mut txn, &edited.id, "edited body")
+            .unwrap();
+        txn.commit().unwrap();
+        drop(store);
+
+        let progress = Arc::new(MigrationProgress::default());
+        let store = cx
+            .update(|cx| PromptStore::new(db_dir.path().to_path_buf(), progress.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(progress.migrated(), prompts.len());
+        assert_eq!(store.prompt_count(), prompts.len());
+        assert_eq!(
+            store
+                .metadata(edited.id)
+                .and_then(|metadata| metadata.title),
+            edited.title
+        );
+        let body = cx.update(|cx| store.load(edited.id, cx)).await.unwrap();
+        assert_eq!(body, "edited body");
+        for prompt in &prompts[1..] {
+            let metadata = store.metadata(v2_id(prompt)).unwrap();
+            assert_eq!(metadata.saved_at, prompt.saved_at);
+        }
+    }
+
+    #[test]
+    fn test_metadata_cache_sort() {
+        let start = Utc::now();
+        let hours = chrono::Duration::hours;
+        let prompt = |title: &str, saved_at: DateTime<Utc>| PromptMetadata {
+            title: Some(title.to_string().into()),
+            saved_at,
+            ..PromptMetadata::new(PromptId::new())
+        };
+        let pinned = PromptMetadata {
+            pinned: true,
+            created_at: Some(start),
+            ..prompt("Zulu", start)
+        };
+        let alpha = PromptMetadata {
+            created_at: Some(start),
+            usage_count: 1,
+            last_used_at: Some(start + hours(1)),
+            ..prompt("Alpha", start + hours(1))
+        };
+        // Created before creation times were recorded, so it's sorted by
+        // when it was saved instead.
+        let beta = PromptMetadata {
+            usage_count: 5,
+            ..prompt("Beta", start + hours(3))
+        };
+        let gamma = PromptMetadata {
+            created_at: Some(start + hours(2)),
+            usage_count: 1,
+            last_used_at: Some(start + hours(2)),
+            ..prompt("Gamma", start + hours(2))
+        };
+        // Identical except for their ids.
+        let mut ties = [
+            prompt("Tie", start + hours(1) / 2),
+            prompt("Tie", start + hours(1) / 2),
+        ];
+        ties.sort_by_key(|metadata| metadata.id);
+        let [first_tie, second_tie] = ties;
+
+        let mut cache = MetadataCache::default();
+        for metadata in [&second_tie, &gamma, &first_tie, &beta, &pinned, &alpha] {
+            cache.insert(metadata.clone());
+        }
+        let mut assert_order = |sort_order, expected: [&PromptMetadata; 6]| {
+            cache.sort_order = sort_order;
+            cache.sort();
+            assert_eq!(
+                cache
+                    .metadata
+                    .iter()
+                    .map(|metadata| metadata.id)
+                    .collect::<Vec<_>>(),
+                expected.map(|metadata| metadata.id),
+                "{sort_order:?}"
+            );
+        };
+
+        assert_order(
+            RulesLibrarySortOrder::TitleAsc,
+            [&pinned, &alpha, &beta, &gamma, &first_tie, &second_tie],
+        );
+        assert_order(
+            RulesLibrarySortOrder::UpdatedDesc,
+            [&pinned, &beta, &gamma, &alpha, &first_tie, &second_tie],
+        );
+        assert_order(
+            RulesLibrarySortOrder::CreatedDesc,
+            [&pinned, &beta, &gamma, &first_tie, &second_tie, &alpha],
+        );
+        assert_order(
+            RulesLibrarySortOrder::MostUsed,
+            [&pinned, &beta, &gamma, &alpha, &first_tie, &second_tie],
+        );
+    }
+
+    #[test]
+    fn test_rename_db() {
+        let db_dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            heed::EnvOpenOptions::new()
+                .max_dbs(MAX_DBS)
+                .open(db_dir.path())
+                .unwrap()
+        };
+        let mut txn = env.write_txn().unwrap();
+        let previous_db = env
+            .create_database::<Str, Str>(&mut txn, Some("bodies.old"))
+            .unwrap();
+        previous_db.put(&mut txn, "a", "first").unwrap();
+        previous_db.put(&mut txn, "b", "second").unwrap();
+        txn.commit().unwrap();
+
+        for _ in 0..2 {
+            let mut txn = env.write_txn().unwrap();
+            PromptStore::rename_db(&env, &mut txn, "bodies.old", "bodies.new").unwrap();
+            txn.commit().unwrap();
+
+            let txn = env.read_txn().unwrap();
+            let db = env
+                .open_database::<Str, Str>(&txn, Some("bodies.new"))
+                .unwrap()
+                .unwrap();
+            assert_eq!(db.get(&txn, "a").unwrap(), Some("first"));
+            assert_eq!(db.get(&txn, "b").unwrap(), Some("second"));
+            let previous_db = env
+                .open_database::<Str, Str>(&txn, Some("bodies.old"))
+                .unwrap()
+                .unwrap();
+            assert!(previous_db.is_empty(&txn).unwrap());
+        }
+
+        // Renaming a database that doesn't exist does nothing.
+        let mut txn = env.write_txn().unwrap();
+        PromptStore::rename_db(&env, &mut txn, "missing", "bodies.new").unwrap();
+        txn.commit().unwrap();
+        let txn = env.read_txn().unwrap();
+        assert!(
+            env.open_database::<Str, Str>(&txn, Some("missing"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_exact() {
+        assert_eq!(
+            find_exact("Rust rules", "rules", false),
+            Some(vec![5, 6, 7, 8, 9])
+        );
+        assert_eq!(
+            find_exact("Rust rules", "RUST", false),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(find_exact("Rust rules", "RUST", true), None);
+        assert_eq!(
+            find_exact("Rust rules", "Rust", true),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(find_exact("Rust rules", "", false), None);
+        assert_eq!(find_exact("Rust", "Rust rules", false), None);
+        // Only the first occurrence is matched.
+        assert_eq!(find_exact("ab ab", "ab", false), Some(vec![0, 1]));
+        // Positions are byte offsets, and case folding handles non-ASCII letters.
+        assert_eq!(
+            find_exact("naïve Café", "CAFÉ", false),
+            Some(vec![7, 8, 9, 10])
+        );
+        assert_eq!(find_exact("naïve Café", "café", true), None);
+        assert_eq!(
+            find_exact("ÄRGER", "ärger", false),
+            Some(vec![0, 2, 3, 4, 5])
+        );
+    }
+
+    #[test]
+    fn test_positions_match_case() {
+        assert!(positions_match_case("Rust rules", &[0, 5], "Rr"));
+        assert!(!positions_match_case("rust rules", &[0, 5], "Rr"));
+        assert!(positions_match_case("Ärger", &[0, 2], "Är"));
+        assert!(!positions_match_case("ärger", &[0, 2], "Är"));
+        // A position that isn't on a character boundary never matches.
+        assert!(!positions_match_case("Ärger", &[1], "r"));
+    }
+
+    #[test]
+    fn test_split_field_positions() {
+        // "ab cd", with no description.
+        assert_eq!(
+            split_field_positions([Some("ab"), None, Some("cd")], &[1, 3]),
+            [vec![1], vec![], vec![0]]
+        );
+        // "ab  cd", where the empty description still has a separator.
+        assert_eq!(
+            split_field_positions([Some("ab"), Some(""), Some("cd")], &[0, 4]),
+            [vec![0], vec![], vec![0]]
+        );
+        // "ab cd", matched across the separator between the title and the
+        // description.
+        assert_eq!(
+            split_field_positions([Some("ab"), Some("cd"), None], &[1, 2, 3]),
+            [vec![1], vec![0], vec![]]
+        );
+        // "é x", where positions are byte offsets.
+        assert_eq!(
+            split_field_positions([Some("é"), None, Some("x")], &[0, 3]),
+            [vec![0], vec![], vec![0]]
+        );
+    }
+
+    #[test]
+    fn test_file_stem_for_title() {
+        assert_eq!(file_stem_for_title("Plain title"), "Plain title");
+        assert_eq!(
+            file_stem_for_title(r#"a/b\c:d*e?f"g<h>i|j"#),
+            "a-b-c-d-e-f-g-h-i-j"
+        );
+    }
+
+    #[test]
+    fn test_unique_title() {
+        let titles = |titles: &[&str]| titles.iter().map(|title| title.to_string()).collect();
+        assert_eq!(unique_title("Style", &titles(&[])), "Style");
+        assert_eq!(unique_title("Style", &titles(&["Style 1"])), "Style");
+        assert_eq!(unique_title("Style", &titles(&["Style"])), "Style 1");
+        assert_eq!(
+            unique_title("Style", &titles(&["Style", "Style 1", "Style 2"])),
+            "Style 3"
+        );
+        // The smallest free suffix is used, even if a larger one is taken.
+        assert_eq!(
+            unique_title("Style", &titles(&["Style", "Style 2"])),
+            "Style 1"
+        );
+        assert_eq!(unique_title("Style 1", &titles(&["Style 1"])), "Style 1 1");
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_outside_code_blocks() {
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks("one  \n\ttwo\t\nthree "),
+            "one\n\ttwo\nthree"
+        );
+
+        // Lines inside a fenced block are kept as they are, but the fences
+        // themselves are trimmed.
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks(
+                "before \n```rust  \nlet a = 1;  \n\n```  \nafter \n"
+            ),
+            "before\n```rust\nlet a = 1;  \n\n```\nafter\n"
+        );
+
+        // A fence only closes the block it was opened with.
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks("~~~\n```  \nx  \n~~~ \ny \n"),
+            "~~~\n```  \nx  \n~~~\ny\n"
+        );
+
+        // An unterminated block runs to the end of the text.
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks("a \n```\n  b  \nc\n"),
+            "a\n```\n  b  \nc\n"
+        );
+
+        // Trailing blank lines are dropped, keeping the final newline if
+        // there was one.
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks("text\n\n  \n\n"),
+            "text\n"
+        );
+        assert_eq!(
+            trim_trailing_whitespace_outside_code_blocks("text \n \t"),
+            "text"
+        );
+        assert_eq!(trim_trailing_whitespace_outside_code_blocks(" \n\n"), "\n");
+    }
+
+    #[gpui::test]
+    async fn test_record_usage(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let prompt = save_prompt(&store, "Rule", "body", cx).await;
+        let other = save_prompt(&store, "Another rule", "body", cx).await;
+        assert_eq!(prompt.usage_count, 0);
+        assert_eq!(prompt.last_used_at, None);
+        store.update(cx, |store, cx| {
+            store.apply_sort_order(RulesLibrarySortOrder::MostUsed, cx)
+        });
+
+        let updated_events = Rc::new(Cell::new(0));
+        let _subscription = cx.update(|cx| {
+            let updated_events = updated_events.clone();
+            cx.subscribe(&store, move |_, _: &PromptsUpdatedEvent, _| {
+                updated_events.set(updated_events.get() + 1)
+            })
+        });
+        for _ in 0..2 {
+            store
+                .update(cx, |store, cx| store.record_usage(prompt.id, cx))
+                .await
+                .unwrap();
+        }
+        cx.run_until_parked();
+        assert_eq!(updated_events.get(), 2);
+
+        // The most used rule moves ahead of the unused one.
+        let order = store.read_with(cx, |store, _| {
+            store
+                .all_prompt_metadata()
+                .into_iter()
+                .map(|metadata| metadata.id)
+                .filter(|id| *id == prompt.id || *id == other.id)
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(order, vec![prompt.id, other.id]);
+
+        let used = store.read_with(cx, |store, _| store.metadata(prompt.id).unwrap());
+        assert_eq!(used.usage_count, 2);
+        assert!(used.last_used_at.is_some());
+        assert_eq!(used.saved_at, prompt.saved_at);
+
+        // The count is stored, not just cached.
+        let stored = store.read_with(cx, |store, _| {
+            let txn = store.env.read_txn().unwrap();
+            store.metadata.get(&txn, &prompt.id).unwrap().unwrap()
+        });
+        assert_eq!(stored.usage_count, 2);
+        assert_eq!(stored.saved_at, prompt.saved_at);
+    }
+
+    #[gpui::test]
+    async fn test_rename(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let target = save_prompt(&store, "Target", "body", cx).await;
+        let including = save_prompt(&store, "Including", "@include(Target)\n", cx).await;
+
+        let unknown_id = PromptId::new();
+        let result = store
+            .update(cx, |store, cx| {
+                store.rename(unknown_id, Some("Unknown".into()), cx)
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(store.read_with(cx, |store, _| store.metadata(unknown_id).is_none()));
+        let result = store
+            .update(cx, |store, cx| {
+                store.rename(PromptId::EditWorkflow, Some("Built-in".into()), cx)
+            })
+            .await;
+        assert!(result.is_err());
+
+        // A plain rename only changes the title, not the body.
+        store
+            .update(cx, |store, cx| {
+                store.rename(including.id, Some("Plain".into()), cx)
+            })
+            .await
+            .unwrap();
+        let renamed = store.read_with(cx, |store, _| store.metadata(including.id).unwrap());
+        assert_eq!(renamed.title.as_deref(), Some("Plain"));
+        assert!(renamed.saved_at >= including.saved_at);
+        assert_eq!(
+            load_body(&store, including.id, cx).await,
+            "@include(Target)\n"
+        );
+
+        let updated_ids = store
+            .update(cx, |store, cx| {
+                store.rename_updating_includes(
+                    target.id,
+                    Some("Renamed".into()),
+                    Some("Target".into()),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated_ids, vec![including.id]);
+        assert_eq!(
+            load_body(&store, including.id, cx).await,
+            "@include(Renamed)\n"
+        );
+        let renamed = store.read_with(cx, |store, _| store.metadata(target.id).unwrap());
+        assert_eq!(renamed.title.as_deref(), Some("Renamed"));
+        assert_eq!(load_body(&store, target.id, cx).await, "body");
+    }
+
+    #[gpui::test]
+    async fn test_backup_round_trip(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let source_dir = tempfile::tempdir().unwrap();
+        let target_dir = tempfile::tempdir().unwrap();
+        let source = open_store(source_dir.path(), cx).await;
+        let target = open_store(target_dir.path(), cx).await;
+
+        let first = save_prompt(&source, "First", "first body", cx).await;
+        let second = save_prompt(&source, "Second", "second body", cx).await;
+        let backup_path = PathBuf::from("/backup.json");
+        source
+            .read_with(cx, |store, cx| {
+                store.backup(backup_path.clone(), fs.clone(), cx)
+            })
+            .await
+            .unwrap();
+
+        let restored_ids = target
+            .update(cx, |store, cx| {
+                store.restore_archive(backup_path.clone(), fs.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            restored_ids.into_iter().collect::<HashSet<_>>(),
+            HashSet::from_iter([first.id, second.id])
+        );
+        target.read_with(cx, |store, _| {
+            assert_eq!(saved_at(store, first.id), Some(first.saved_at));
+            assert_eq!(saved_at(store, second.id), Some(second.saved_at));
+        });
+        assert_eq!(load_body(&target, first.id, cx).await, "first body");
+        assert_eq!(load_body(&target, second.id, cx).await, "second body");
+
+        // Restoring the same backup again changes nothing.
+        let restored_ids = target
+            .update(cx, |store, cx| {
+                store.restore_archive(backup_path.clone(), fs.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert!(restored_ids.is_empty());
+    }
+
+    #[gpui::test]
+    async fn test_restore_archive_merges_prompts(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+
+        let outdated = save_prompt(&store, "Outdated", "outdated body", cx).await;
+        let current = save_prompt(&store, "Current", "current body", cx).await;
+        let trashed = save_prompt(&store, "Trashed", "trashed body", cx).await;
+        assert!(
+            store
+                .update(cx, |store, cx| store.delete(trashed.id, cx))
+                .await
+                .unwrap()
+        );
+        let missing_body = PromptMetadata::new(PromptId::new());
+        let retired_built_in = PromptMetadata {
+            title: Some("Built-in".into()),
+            ..PromptMetadata::new(PromptId::EditWorkflow)
+        };
+
+        let hour = chrono::Duration::hours(1);
+        let archive = PromptArchive {
+            version: PromptArchive::VERSION,
+            prompts: vec![
+                ArchivedPrompt {
+                    metadata: PromptMetadata {
+                        saved_at: outdated.saved_at + hour,
+                        ..outdated.clone()
+                    },
+                    body: Some("newer body".into()),
+                },
+                ArchivedPrompt {
+                    metadata: PromptMetadata {
+                        saved_at: current.saved_at - hour,
+                        ..current.clone()
+                    },
+                    body: Some("older body".into()),
+                },
+                ArchivedPrompt {
+                    metadata: trashed.clone(),
+                    body: Some("trashed body".into()),
+                },
+                ArchivedPrompt {
+                    metadata: missing_body.clone(),
+                    body: None,
+                },
+                ArchivedPrompt {
+                    metadata: retired_built_in.clone(),
+                    body: Some("retired body".into()),
+                },
+            ],
+        };
+        let backup_path = Path::new("/backup.json");
+        write_archive(&fs, backup_path, &archive).await;
+
+        let restored_ids = store
+            .update(cx, |store, cx| {
+                store.restore_archive(backup_path.to_path_buf(), fs.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            restored_ids.into_iter().collect::<HashSet<_>>(),
+            HashSet::from_iter([outdated.id, trashed.id])
+        );
+
+        assert_eq!(load_body(&store, outdated.id, cx).await, "newer body");
+        assert_eq!(load_body(&store, current.id, cx).await, "current body");
+        assert_eq!(load_body(&store, trashed.id, cx).await, "trashed body");
+        store.read_with(cx, |store, _| {
+            assert_eq!(
+                store
+                    .metadata(outdated.id)
+                    .map(|metadata| metadata.saved_at),
+                Some(outdated.saved_at + hour)
+            );
+            assert_eq!(saved_at(store, current.id), Some(current.saved_at));
+            assert_eq!(saved_at(store, trashed.id), Some(trashed.saved_at));
+            assert!(store.metadata(retired_built_in.id).is_none());
+            assert!(store.metadata(missing_body.id).is_none());
+        });
+
+        // The restored prompt was taken out of the trash, so an earlier undo
+        // can't bring back the version that was deleted.
+        assert!(
+            store
+                .update(cx, |store, cx| store.restore(trashed.id, cx))
+                .await
+                .is_err()
+        );
+    }
+
+    #[gpui::test]
+    async fn test_restore_archive_with_unsupported_version(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+
+        let prompt = PromptMetadata::new(PromptId::new());
+        let archive = PromptArchive {
+            version: PromptArchive::VERSION + 1,
+            prompts: vec![ArchivedPrompt {
+                metadata: prompt.clone(),
+                body: Some("body".into()),
+            }],
+        };
+        let backup_path = Path::new("/backup.json");
+        write_archive(&fs, backup_path, &archive).await;
+
+        let result = store
+            .update(cx, |store, cx| {
+                store.restore_archive(backup_path.to_path_buf(), fs.clone(), cx)
+            })
+            .await;
+        assert!(result.is_err());
+        store.read_with(cx, |store, _| assert!(store.metadata(prompt.id).is_none()));
+    }
+
+    #[gpui::test]
+    async fn test_bundle_round_trip(cx: &mut TestAppContext) {
+        init_test(cx);
+        let source_dir = tempfile::tempdir().unwrap();
+        let target_dir = tempfile::tempdir().unwrap();
+        let source = open_store(source_dir.path(), cx).await;
+        let target = open_store(target_dir.path(), cx).await;
+
+        let first = save_prompt(&source, "First", "first body", cx).await;
+        let second = save_prompt(&source, "Second", "second\nbody", cx).await;
+        save_prompt(&target, "First", "existing body", cx).await;
+        let initial_count = target.read_with(cx, |store, _| store.prompt_count());
+
+        let mut bundle = source
+            .read_with(cx, |store, cx| store.bundle(&[first.id, second.id], cx))
+            .await
+            .unwrap();
+        bundle.prompts[1].tags = vec!["style".into()];
+        let json = bundle.to_json().unwrap();
+
+        let insert = async |cx: &mut TestAppContext| {
+            let bundle = PromptBundle::from_json(&json).unwrap();
+            let ids = target
+                .update(cx, |store, cx| store.insert_bundle(bundle, cx))
+                .await
+                .unwrap();
+            let mut prompts = Vec::new();
+            for id in ids {
+                let metadata = target.read_with(cx, |store, _| store.metadata(id).unwrap());
+                let body = load_body(&target, id, cx).await;
+                prompts.push((metadata.title.unwrap(), metadata.tags, body));
+            }
+            prompts
+        };
+
+        // Inserted prompts get new ids, so titles that are already taken are
+        // suffixed, including by prompts inserted from an earlier paste.
+        let style = SharedString::from("style");
+        assert_eq!(
+            insert(cx).await,
+            [
+                ("First 1".into(), vec![], "first body".to_string()),
+                (
+                    "Second".into(),
+                    vec![style.clone()],
+                    "second\nbody".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            insert(cx).await,
+            [
+                ("First 2".into(), vec![], "first body".to_string()),
+                ("Second 1".into(), vec![style], "second\nbody".to_string()),
+            ]
+        );
+        target.read_with(cx, |store, _| {
+            assert_eq!(store.prompt_count(), initial_count + 4)
+        });
+
+        let mut unsupported = bundle;
+        unsupported.version = PromptBundle::VERSION + 1;
+        assert!(PromptBundle::from_json(&unsupported.to_json().unwrap()).is_err());
+        assert!(PromptBundle::from_json("plain text").is_err());
+    }
+}
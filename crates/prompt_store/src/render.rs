@@ -0,0 +1,356 @@
+use anyhow::{Result, anyhow};
+use collections::HashMap;
+use std::{fmt, ops::Range};
+use uuid::Uuid;
+
+use crate::{PromptId, PromptMetadata, UserPromptId};
+
+const INCLUDE_PREFIX: &str = "@include(";
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// The prompt an `@include(...)` reference points to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IncludeTarget {
+    /// `@include(Some Title)`
+    Title(String),
+    /// `@include(id:<uuid>)`, which keeps working when the included prompt is renamed.
+    Id(PromptId),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncludeReference {
+    /// Byte range of the whole reference, including `@include(` and `)`.
+    pub range: Range<usize>,
+    pub target: IncludeTarget,
+}
+
+impl IncludeTarget {
+    pub fn resolve(&self, metadata: &[PromptMetadata]) -> Option<PromptId> {
+        match self {
+            IncludeTarget::Title(title) => metadata
+                .iter()
+                .find(|metadata| metadata.title.as_deref() == Some(title.as_str()))
+                .map(|metadata| metadata.id),
+            IncludeTarget::Id(id) => metadata
+                .iter()
+                .any(|metadata| metadata.id == *id)
+                .then_some(*id),
+        }
+    }
+}
+
+/// Returned when rendering a prompt that references variables for which no
+/// value was provided.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnresolvedVariables(pub Vec<String>);
+
+impl fmt::Display for UnresolvedVariables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unresolved prompt variables: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for UnresolvedVariables {}
+
+/// Finds every `@include(...)` reference in `text`. References can't span lines.
+pub fn parse_includes(text: &str) -> Vec<IncludeReference> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find(INCLUDE_PREFIX).map(|ix| ix + offset) {
+        let target_start = start + INCLUDE_PREFIX.len();
+        let Some(target_len) = text[target_start..].find([')', '\n']) else {
+            break;
+        };
+        let target_end = target_start + target_len;
+        offset = target_end;
+        if text[target_end..].starts_with('\n') {
+            continue;
+        }
+
+        let target = text[target_start..target_end].trim();
+        if target.is_empty() {
+            continue;
+        }
+        let target = target
+            .strip_prefix("id:")
+            .and_then(|uuid| Uuid::parse_str(uuid.trim()).ok())
+            .map(|uuid| IncludeTarget::Id(UserPromptId(uuid).into()))
+            .unwrap_or_else(|| IncludeTarget::Title(target.to_string()));
+        references.push(IncludeReference {
+            range: start..target_end + 1,
+            target,
+        });
+    }
+    references
+}
+
+/// Points `@include(old_title)` references at `new_title` instead. Id-based
+/// references are left alone. Returns `None` if nothing was replaced.
+pub fn rename_title_includes(text: &str, old_title: &str, new_title: &str) -> Option<String> {
+    let mut renamed = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for reference in parse_includes(text) {
+        if reference.target != IncludeTarget::Title(old_title.to_string()) {
+            continue;
+        }
+        renamed.push_str(&text[last_end..reference.range.start]);
+        renamed.push_str(INCLUDE_PREFIX);
+        renamed.push_str(new_title);
+        renamed.push(')');
+        last_end = reference.range.end;
+    }
+    if last_end == 0 {
+        return None;
+    }
+    renamed.push_str(&text[last_end..]);
+    Some(renamed)
+}
+
+/// Recursively replaces `@include(...)` references with the bodies of the
+/// prompts they point to.
+pub(crate) fn expand_includes(
+    body: &str,
+    metadata: &[PromptMetadata],
+    load_body: &mut dyn FnMut(PromptId) -> Result<String>,
+    stack: &mut Vec<PromptId>,
+) -> Result<String> {
+    if stack.len() > MAX_INCLUDE_DEPTH {
+        return Err(anyhow!("prompt includes are nested too deeply"));
+    }
+
+    let mut expanded = String::with_capacity(body.len());
+    let mut last_end = 0;
+    for reference in parse_includes(body) {
+        let id = reference
+            .target
+            .resolve(metadata)
+            .ok_or_else(|| anyhow!("unresolved include {}", &body[reference.range.clone()]))?;
+        if stack.contains(&id) {
+            return Err(anyhow!(
+                "prompt include cycle at {}",
+                &body[reference.range.clone()]
+            ));
+        }
+
+        expanded.push_str(&body[last_end..reference.range.start]);
+        let included_body = load_body(id)?;
+        stack.push(id);
+        let included_body = expand_includes(&included_body, metadata, load_body, stack);
+        stack.pop();
+        expanded.push_str(&included_body?);
+        last_end = reference.range.end;
+    }
+    expanded.push_str(&body[last_end..]);
+    Ok(expanded)
+}
+
+/// Replaces `{{name}}` with the value of the variable `name`. Returns
+/// [`UnresolvedVariables`] listing every variable without a value.
+pub fn substitute_variables(text: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut unresolved = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let end = start + 2 + len + 2;
+        if !is_variable_name(name) {
+            result.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        result.push_str(&rest[..start]);
+        if let Some(value) = variables.get(name) {
+            result.push_str(value);
+        } else if !unresolved.iter().any(|unresolved| unresolved == name) {
+            unresolved.push(name.to_string());
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+
+    if unresolved.is_empty() {
+        Ok(result)
+    } else {
+        unresolved.sort();
+        Err(UnresolvedVariables(unresolved).into())
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Removes snippet tabstops (`$1`, `${1}`) and replaces placeholders
+/// (`${1:text}`) with their text. Bare tabstops look just like amounts of
+/// money, so they're only removed where an amount wouldn't be written: right
+/// after an opening bracket or quote, or alone on their line.
+pub fn strip_snippet_markers(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((ix, c)) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some((_, next)) if next.is_ascii_digit() => {
+                let digits_len = text[ix + 1..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(text.len() - ix - 1);
+                if is_bare_tabstop(text, ix..ix + 1 + digits_len) {
+                    while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+                } else {
+                    result.push(c);
+                }
+            }
+            Some((_, '{')) if text[ix + 2..].starts_with(|c: char| c.is_ascii_digit()) => {
+                let Some(len) = placeholder_len(&text[ix..]) else {
+                    result.push(c);
+                    continue;
+                };
+                let placeholder = &text[ix + 2..ix + len - 1];
+                if let Some((_, contents)) = placeholder.split_once(':') {
+                    result.push_str(&strip_snippet_markers(contents));
+                }
+                while chars.next_if(|(next_ix, _)| *next_ix < ix + len).is_some() {}
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn is_bare_tabstop(text: &str, range: Range<usize>) -> bool {
+    let before = &text[..range.start];
+    if before.ends_with(['(', '[', '{', '<', '"', '\'', '`']) {
+        return true;
+    }
+    let line_start = before.rfind('\n').map_or(0, |ix| ix + 1);
+    let line_end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |ix| range.end + ix);
+    text[line_start..range.start].trim().is_empty() && text[range.end..line_end].trim().is_empty()
+}
+
+/// Returns the length of the `${...}` placeholder at the start of `text`,
+/// accounting for nested placeholders.
+fn placeholder_len(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (ix, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(ix + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(title: &str) -> PromptMetadata {
+        PromptMetadata {
+            title: Some(title.to_string().into()),
+            ..PromptMetadata::new(PromptId::new())
+        }
+    }
+
+    #[test]
+    fn test_parse_includes() {
+        let id = PromptId::new();
+        let text = format!("a @include(Style Guide) b @include( id:{id} ) @include(\n)");
+        let references = parse_includes(&text);
+        assert_eq!(
+            references
+                .iter()
+                .map(|reference| reference.target.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                IncludeTarget::Title("Style Guide".into()),
+                IncludeTarget::Id(id)
+            ]
+        );
+        assert_eq!(&text[references[0].range.clone()], "@include(Style Guide)");
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let outer = metadata("Outer");
+        let inner = metadata("Inner");
+        let all = vec![outer.clone(), inner.clone()];
+        let mut load_body = |id: PromptId| -> Result<String> {
+            if id == inner.id {
+                Ok("inner".to_string())
+            } else {
+                Ok("before @include(Inner) after".to_string())
+            }
+        };
+
+        let expanded =
+            expand_includes("[@include(Outer)]", &all, &mut load_body, &mut Vec::new()).unwrap();
+        assert_eq!(expanded, "[before inner after]");
+
+        let mut load_cycle = |_: PromptId| -> Result<String> { Ok("@include(Outer)".to_string()) };
+        assert!(
+            expand_includes("@include(Outer)", &all, &mut load_cycle, &mut Vec::new()).is_err()
+        );
+        assert!(
+            expand_includes("@include(Missing)", &all, &mut load_body, &mut Vec::new()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_rename_title_includes() {
+        let id = PromptId::new();
+        let text = format!("@include(Old) @include( Old ) @include(Older) @include(id:{id})");
+        assert_eq!(
+            rename_title_includes(&text, "Old", "New").unwrap(),
+            format!("@include(New) @include(New) @include(Older) @include(id:{id})")
+        );
+        assert_eq!(rename_title_includes(&text, "Missing", "New"), None);
+    }
+
+    #[test]
+    fn test_substitute_variables() {
+        let mut variables = HashMap::default();
+        variables.insert("language".to_string(), "Rust".to_string());
+
+        assert_eq!(
+            substitute_variables("Write {{ language }}, not {{}} or {{a b}}", &variables).unwrap(),
+            "Write Rust, not {{}} or {{a b}}"
+        );
+
+        let error = substitute_variables("{{b}} {{a}} {{b}}", &variables).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<UnresolvedVariables>(),
+            Some(&UnresolvedVariables(vec!["a".into(), "b".into()]))
+        );
+    }
+
+    #[test]
+    fn test_strip_snippet_markers() {
+        assert_eq!(
+            strip_snippet_markers("fn ${1:name}(${2:${3:arg}}) {$0} costs $5 or ${cost}"),
+            "fn name(arg) {} costs $5 or ${cost}"
+        );
+        assert_eq!(
+            strip_snippet_markers("fn main() {\n    $0\n}\n$5 a month, US$10 or [$1]"),
+            "fn main() {\n    \n}\n$5 a month, US$10 or []"
+        );
+    }
+}
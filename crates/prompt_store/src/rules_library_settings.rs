@@ -0,0 +1,33 @@
+use settings::{RegisterSetting, RulesLibraryFocus, RulesLibrarySortOrder, Settings, SoftWrap};
+
+#[derive(Debug, Clone, Copy, PartialEq, RegisterSetting)]
+pub struct RulesLibrarySettings {
+    pub trim_trailing_whitespace_on_save: bool,
+    pub soft_wrap: SoftWrap,
+    pub fold_sections_on_load: bool,
+    pub fold_sections_min_lines: u32,
+    pub freeze_order_while_editing: bool,
+    pub edit_predictions: bool,
+    pub focus_on_open: RulesLibraryFocus,
+    pub show_state_labels: bool,
+    pub sort_order: RulesLibrarySortOrder,
+}
+
+impl Settings for RulesLibrarySettings {
+    fn from_settings(content: &settings::SettingsContent) -> Self {
+        let rules_library = content.rules_library.as_ref().unwrap();
+        Self {
+            trim_trailing_whitespace_on_save: rules_library
+                .trim_trailing_whitespace_on_save
+                .unwrap(),
+            soft_wrap: rules_library.soft_wrap.unwrap(),
+            fold_sections_on_load: rules_library.fold_sections_on_load.unwrap(),
+            fold_sections_min_lines: rules_library.fold_sections_min_lines.unwrap(),
+            freeze_order_while_editing: rules_library.freeze_order_while_editing.unwrap(),
+            edit_predictions: rules_library.edit_predictions.unwrap(),
+            focus_on_open: rules_library.focus_on_open.unwrap(),
+            show_state_labels: rules_library.show_state_labels.unwrap(),
+            sort_order: rules_library.sort_order.unwrap(),
+        }
+    }
+}
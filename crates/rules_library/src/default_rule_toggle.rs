@@ -0,0 +1,217 @@
+use anyhow::Result;
+use gpui::{DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity};
+use picker::{Picker, PickerDelegate};
+use prompt_store::{PromptId, PromptMetadata, PromptStore};
+use std::sync::Arc;
+use ui::{ListItem, ListItemSpacing, prelude::*};
+use util::ResultExt;
+use workspace::{ModalView, Toast, Workspace, notifications::NotificationId};
+
+use crate::{RulesLibrary, ToggleDefaultRuleByTitle};
+
+/// A modal for adding a rule to the default rules, or removing it, by typing
+/// its title, without opening the rules library.
+pub struct DefaultRuleToggle {
+    picker: Entity<Picker<DefaultRuleToggleDelegate>>,
+}
+
+impl DefaultRuleToggle {
+    pub(crate) fn register(
+        workspace: &mut Workspace,
+        _window: Option<&mut Window>,
+        _cx: &mut Context<Workspace>,
+    ) {
+        workspace.register_action(|_, _: &ToggleDefaultRuleByTitle, window, cx| {
+            let store = PromptStore::global(cx);
+            cx.spawn_in(window, async move |workspace, cx| {
+                let store = store.await?;
+                workspace.update_in(cx, |workspace, window, cx| {
+                    let workspace_handle = cx.weak_entity();
+                    workspace.toggle_modal(window, cx, move |window, cx| {
+                        Self::new(store, workspace_handle, window, cx)
+                    });
+                })
+            })
+            .detach_and_log_err(cx);
+        });
+    }
+
+    fn new(
+        store: Entity<PromptStore>,
+        workspace: WeakEntity<Workspace>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let delegate = DefaultRuleToggleDelegate {
+            default_rule_toggle: cx.entity().downgrade(),
+            workspace,
+            store,
+            query: String::new(),
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+        Self { picker }
+    }
+}
+
+impl Render for DefaultRuleToggle {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl Focusable for DefaultRuleToggle {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for DefaultRuleToggle {}
+impl ModalView for DefaultRuleToggle {}
+
+struct DefaultRuleToggleDelegate {
+    default_rule_toggle: WeakEntity<DefaultRuleToggle>,
+    workspace: WeakEntity<Workspace>,
+    store: Entity<PromptStore>,
+    query: String,
+    matches: Vec<PromptMetadata>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for DefaultRuleToggleDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Add or remove a default rule…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> Option<SharedString> {
+        Some("No rules match".into())
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn confirm(&mut self, _: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        // Toggle the row that's shown as selected. An exact title is only a
+        // fallback for when nothing matched, e.g. before the first search ends.
+        let prompt_id = match self.matches.get(self.selected_index) {
+            Some(rule) => Some(rule.id),
+            None if self.matches.is_empty() => self.store.read(cx).id_for_title(self.query.trim()),
+            None => None,
+        };
+        match prompt_id {
+            Some(prompt_id) => toggle_default(&self.store, prompt_id, cx).detach_and_log_err(cx),
+            None => {
+                let message = format!("No rule matches \"{}\"", self.query.trim());
+                self.workspace
+                    .update(cx, |workspace, cx| {
+                        workspace.show_toast(
+                            Toast::new(NotificationId::unique::<DefaultRuleToggle>(), message),
+                            cx,
+                        )
+                    })
+                    .log_err();
+            }
+        }
+        self.dismissed(window, cx);
+    }
+
+    fn dismissed(&mut self, _: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.default_rule_toggle
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let search = self
+            .store
+            .read(cx)
+            .search(query.clone(), Arc::default(), cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let matches = search.await;
+            this.update(cx, |this, cx| {
+                this.delegate.query = query;
+                this.delegate.matches = matches;
+                this.delegate.selected_index = 0;
+                cx.notify();
+            })
+            .ok();
+        })
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let rule = self.matches.get(ix)?;
+        let title = rule.title.clone().unwrap_or_else(|| "Untitled".into());
+
+        let mut list_item = ListItem::new(ix)
+            .inset(true)
+            .spacing(ListItemSpacing::Sparse)
+            .toggle_state(selected)
+            .child(Label::new(title).truncate());
+
+        if rule.default {
+            list_item = list_item.end_slot(Icon::new(IconName::Check).color(Color::Muted));
+        }
+
+        Some(list_item)
+    }
+}
+
+/// Flips whether the rule is a default rule, then refreshes any open rules
+/// library so it reflects the change.
+fn toggle_default(
+    store: &Entity<PromptStore>,
+    prompt_id: PromptId,
+    cx: &mut App,
+) -> Task<Result<()>> {
+    let save = store.update(cx, |store, cx| {
+        let metadata = store.metadata(prompt_id)?;
+        Some(store.save_metadata(prompt_id, metadata.title, !metadata.default, cx))
+    });
+    cx.spawn(async move |cx| {
+        if let Some(save) = save {
+            save.await?;
+        }
+        cx.update(|cx| {
+            for window in cx.windows() {
+                if let Some(window) = window.downcast::<RulesLibrary>() {
+                    window
+                        .update(cx, |rules_library, window, cx| {
+                            rules_library
+                                .picker
+                                .update(cx, |picker, cx| picker.refresh(window, cx));
+                            cx.notify();
+                        })
+                        .ok();
+                }
+            }
+        })
+    })
+}
@@ -0,0 +1,277 @@
+use collections::HashSet;
+use gpui::Task;
+use language_model::{ConfiguredModel, LanguageModelRegistry};
+use prompt_store::PromptId;
+use std::cmp::Reverse;
+use std::time::Duration;
+use ui::{Tooltip, prelude::*};
+use util::{ResultExt as _, TryFutureExt as _};
+
+use crate::{RulesLibrary, ShowLibrarySummary, token_count_request};
+
+/// How many of the largest rules the summary lists.
+const LARGEST_RULE_COUNT: usize = 5;
+
+/// Counts across the whole rules library, shown in place of the active rule.
+/// Everything except token counts comes from the store's metadata when the
+/// summary renders. Token counts are the ones cached in the store, the same
+/// ones the rule list filters by, and rules that weren't counted yet are
+/// counted in the background while the summary is open. Archived rules are
+/// only included in the archived count.
+pub(crate) struct LibrarySummary {
+    default_token_count: Option<u64>,
+    /// Rules whose body couldn't be loaded or counted since the last refresh.
+    failed_ids: HashSet<PromptId>,
+    pending_counts: Task<Option<()>>,
+}
+
+impl RulesLibrary {
+    pub(crate) fn toggle_library_summary(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.library_summary.take().is_none() {
+            self.default_rules_preview = None;
+            self.library_summary = Some(LibrarySummary {
+                default_token_count: None,
+                failed_ids: HashSet::default(),
+                pending_counts: Task::ready(None),
+            });
+            self.refresh_library_summary(false, window, cx);
+        }
+        cx.notify();
+    }
+
+    /// Recounts the tokens of the default rules together, and counts every
+    /// unarchived rule that has no cached token count yet. A rule that can't
+    /// be counted is logged and skipped. With `debounce`, the previous
+    /// counts stay shown until rules stop changing for a moment, so saving
+    /// every keystroke doesn't recount the whole library each time.
+    pub(crate) fn refresh_library_summary(
+        &mut self,
+        debounce: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+        let Some(summary) = self.library_summary.as_mut() else {
+            return;
+        };
+        if !debounce {
+            summary.default_token_count = None;
+        }
+        let Some(ConfiguredModel { model, .. }) =
+            LanguageModelRegistry::read_global(cx).default_model()
+        else {
+            summary.pending_counts = Task::ready(None);
+            return;
+        };
+
+        let store = self.store.clone();
+        summary.pending_counts = cx.spawn_in(window, async move |this, cx| {
+            async move {
+                if debounce {
+                    cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+                }
+                let (default_rules, uncounted_ids) = store.read_with(cx, |store, cx| {
+                    let uncounted_ids = store
+                        .all_prompt_metadata()
+                        .into_iter()
+                        .filter(|metadata| !metadata.archived)
+                        .map(|metadata| metadata.id)
+                        .filter(|id| store.token_count(*id).is_none())
+                        .collect::<Vec<_>>();
+                    (store.default_prompts_with_bodies(cx), uncounted_ids)
+                })?;
+                // Counted the same way as the default rules preview, so the totals match.
+                let default_token_count = match default_rules.await {
+                    Ok(default_rules) => {
+                        let body = default_rules
+                            .iter()
+                            .map(|(_, body)| body.as_str())
+                            .collect::<Vec<_>>()
+                            .join("\n\n");
+                        cx.update(|_, cx| model.count_tokens(token_count_request(body), cx))?
+                            .await
+                    }
+                    Err(error) => Err(error),
+                };
+                this.update(cx, |this, cx| {
+                    if let Some(summary) = &mut this.library_summary {
+                        summary.default_token_count = default_token_count.log_err();
+                        summary.failed_ids.clear();
+                        cx.notify();
+                    }
+                })?;
+
+                for prompt_id in uncounted_ids {
+                    let token_count = match store
+                        .read_with(cx, |store, cx| store.load(prompt_id, cx))?
+                        .await
+                    {
+                        Ok(body) => {
+                            cx.update(|_, cx| model.count_tokens(token_count_request(body), cx))?
+                                .await
+                        }
+                        Err(error) => Err(error),
+                    };
+                    match token_count.log_err() {
+                        Some(token_count) => store
+                            .update(cx, |store, _| store.set_token_count(prompt_id, token_count))?,
+                        None => this.update(cx, |this, _| {
+                            if let Some(summary) = &mut this.library_summary {
+                                summary.failed_ids.insert(prompt_id);
+                            }
+                        })?,
+                    }
+                    this.update(cx, |_, cx| cx.notify())?;
+                }
+                anyhow::Ok(())
+            }
+            .log_err()
+            .await
+        });
+    }
+
+    pub(crate) fn render_library_summary(
+        &self,
+        cx: &mut Context<Self>,
+    ) -> Option<impl IntoElement> {
+        let summary = self.library_summary.as_ref()?;
+        let store = self.store.read(cx);
+        let (archived, metadata): (Vec<_>, Vec<_>) = store
+            .all_prompt_metadata()
+            .into_iter()
+            .partition(|metadata| metadata.archived);
+        let archived_count = archived.len();
+        let rule_count = metadata.len();
+        let default_count = metadata
+            .iter()
+            .filter(|metadata| metadata.default && !metadata.disabled)
+            .count();
+        let mut counted = metadata
+            .iter()
+            .filter_map(|metadata| Some((metadata, store.token_count(metadata.id)?)))
+            .collect::<Vec<_>>();
+        let counted_count = counted.len();
+        let failed_count = metadata
+            .iter()
+            .filter(|metadata| {
+                summary.failed_ids.contains(&metadata.id)
+                    && store.token_count(metadata.id).is_none()
+            })
+            .count();
+        counted.sort_by_key(|(_, token_count)| Reverse(*token_count));
+        counted.truncate(LARGEST_RULE_COUNT);
+        let has_model = LanguageModelRegistry::read_global(cx)
+            .default_model()
+            .is_some();
+
+        let token_status = if !has_model {
+            Some("Select a model to count tokens.".to_string())
+        } else if counted_count + failed_count < rule_count {
+            Some(format!(
+                "Counting tokens… {counted_count} of {rule_count} rules counted."
+            ))
+        } else if failed_count > 0 {
+            Some(format!(
+                "{failed_count} of {rule_count} rules couldn't be counted."
+            ))
+        } else {
+            None
+        };
+
+        Some(
+            v_flex()
+                .id("library-summary")
+                .h_full()
+                .flex_1()
+                .border_l_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().editor_background)
+                .child(
+                    h_flex()
+                        .py_2()
+                        .px_2p5()
+                        .justify_between()
+                        .child(Headline::new("Library Summary").size(HeadlineSize::Small))
+                        .child(
+                            IconButton::new("close-library-summary", IconName::Close)
+                                .tooltip(move |_window, cx| {
+                                    Tooltip::for_action("Close Summary", &ShowLibrarySummary, cx)
+                                })
+                                .on_click(|_, window, cx| {
+                                    window.dispatch_action(Box::new(ShowLibrarySummary), cx);
+                                }),
+                        ),
+                )
+                .child(
+                    v_flex()
+                        .px_2p5()
+                        .py_2()
+                        .gap_1()
+                        .child(summary_row("Rules", rule_count.to_string()))
+                        .child(summary_row("Default rules", default_count.to_string()))
+                        .child(summary_row("Archived rules", archived_count.to_string()))
+                        .child(summary_row(
+                            "Default rule tokens",
+                            summary
+                                .default_token_count
+                                .map_or_else(|| "–".to_string(), |count| count.to_string()),
+                        ))
+                        .children(token_status.map(|status| {
+                            Label::new(status)
+                                .size(LabelSize::Small)
+                                .color(Color::Muted)
+                        })),
+                )
+                .when(!counted.is_empty(), |this| {
+                    this.child(
+                        v_flex()
+                            .px_2p5()
+                            .py_2()
+                            .gap_1()
+                            .child(
+                                Label::new("Largest Rules")
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                            .children(counted.into_iter().enumerate().map(
+                                |(ix, (metadata, token_count))| {
+                                    let prompt_id = metadata.id;
+                                    h_flex()
+                                        .id(("largest-rule", ix))
+                                        .px_1()
+                                        .rounded_sm()
+                                        .justify_between()
+                                        .cursor_pointer()
+                                        .hover(|style| style.bg(cx.theme().colors().element_hover))
+                                        .child(
+                                            Label::new(
+                                                metadata
+                                                    .title
+                                                    .clone()
+                                                    .unwrap_or_else(|| "Untitled".into()),
+                                            )
+                                            .truncate(),
+                                        )
+                                        .child(
+                                            Label::new(format!("{token_count} tokens"))
+                                                .color(Color::Muted),
+                                        )
+                                        .on_click(cx.listener(move |this, _, window, cx| {
+                                            this.library_summary = None;
+                                            this.load_rule(prompt_id, true, window, cx);
+                                        }))
+                                },
+                            )),
+                    )
+                }),
+        )
+    }
+}
+
+fn summary_row(label: &'static str, value: String) -> impl IntoElement {
+    h_flex()
+        .justify_between()
+        .child(Label::new(label))
+        .child(Label::new(value).color(Color::Muted))
+}
@@ -0,0 +1,77 @@
+use gpui::Task;
+use prompt_store::MigrationProgress;
+use std::sync::Arc;
+use std::time::Duration;
+use ui::{ProgressBar, Tooltip, prelude::*};
+
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shown while rules saved by older versions of Zed are migrated, before the
+/// rules library can open.
+pub(crate) struct MigrationProgressView {
+    progress: Arc<MigrationProgress>,
+    _poll: Task<()>,
+}
+
+impl MigrationProgressView {
+    pub(crate) fn new(progress: Arc<MigrationProgress>, cx: &mut Context<Self>) -> Self {
+        let poll = cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(POLL_INTERVAL).await;
+                if this.update(cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            progress,
+            _poll: poll,
+        }
+    }
+}
+
+impl Render for MigrationProgressView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let migrated = self.progress.migrated();
+        let total = self.progress.total();
+        let cancelled = self.progress.is_cancelled();
+        let status = if cancelled {
+            "Finishing the current batch…".to_string()
+        } else {
+            format!("{migrated} of {total} rules")
+        };
+
+        v_flex()
+            .size_full()
+            .p_4()
+            .gap_3()
+            .bg(cx.theme().colors().background)
+            .child(Label::new("Upgrading the rules library"))
+            .child(ProgressBar::new(
+                "rules-migration-progress",
+                migrated as f32,
+                total.max(1) as f32,
+                cx,
+            ))
+            .child(
+                h_flex()
+                    .justify_between()
+                    .child(
+                        Label::new(status)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        Button::new("cancel-rules-migration", "Cancel")
+                            .disabled(cancelled)
+                            .tooltip(Tooltip::text(
+                                "Open the library now. The rest is upgraded next time.",
+                            ))
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.progress.cancel();
+                                cx.notify();
+                            })),
+                    ),
+            )
+    }
+}
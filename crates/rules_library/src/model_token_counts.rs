@@ -0,0 +1,180 @@
+use collections::HashMap;
+use gpui::{Corner, Task, anchored, deferred};
+use language_model::{LanguageModelId, LanguageModelProviderId, LanguageModelRegistry};
+use prompt_store::PromptId;
+use std::time::Duration;
+use ui::prelude::*;
+
+use crate::{RulesLibrary, token_count_request};
+
+/// A rule body's token count for every available model, shown while the token
+/// count in the rule's header is hovered. Counts are dropped whenever the body
+/// changes, and only the models without a count are counted again.
+pub(crate) struct ModelTokenCounts {
+    /// `None` when the model failed to count the body.
+    counts: HashMap<(LanguageModelProviderId, LanguageModelId), Option<u64>>,
+    pending_counts: Task<()>,
+}
+
+impl ModelTokenCounts {
+    pub(crate) fn new() -> Self {
+        Self {
+            counts: HashMap::default(),
+            pending_counts: Task::ready(()),
+        }
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        self.counts.clear();
+        self.pending_counts = Task::ready(());
+    }
+}
+
+impl RulesLibrary {
+    /// Counts the rule's body with every available model that has no
+    /// count since the body was last edited, one model at a time. With
+    /// `debounce`, counting waits until the body stops changing for a moment.
+    pub(crate) fn count_tokens_for_all_models(
+        &mut self,
+        prompt_id: PromptId,
+        debounce: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let uncounted_models = LanguageModelRegistry::read_global(cx)
+            .available_models(cx)
+            .filter(|model| {
+                !rule_editor
+                    .model_token_counts
+                    .counts
+                    .contains_key(&(model.provider_id(), model.id()))
+            })
+            .collect::<Vec<_>>();
+        if uncounted_models.is_empty() {
+            return;
+        }
+
+        let body = rule_editor.body_editor.read(cx).text(cx);
+        rule_editor.model_token_counts.pending_counts =
+            cx.spawn_in(window, async move |this, cx| {
+                if debounce {
+                    cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+                }
+                for model in uncounted_models {
+                    let Ok(count) = cx
+                        .update(|_, cx| model.count_tokens(token_count_request(body.clone()), cx))
+                    else {
+                        return;
+                    };
+                    let token_count = count.await;
+                    if let Err(error) = &token_count {
+                        log::debug!("failed to count tokens for {}: {error}", model.id().0);
+                    }
+                    let updated = this.update(cx, |this, cx| {
+                        if let Some(rule_editor) = this.rule_editors.get_mut(&prompt_id) {
+                            rule_editor
+                                .model_token_counts
+                                .counts
+                                .insert((model.provider_id(), model.id()), token_count.ok());
+                            cx.notify();
+                        }
+                    });
+                    if updated.is_err() {
+                        return;
+                    }
+                }
+            });
+    }
+
+    pub(crate) fn set_show_model_token_counts(
+        &mut self,
+        prompt_id: PromptId,
+        show: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.show_model_token_counts = show;
+        if show {
+            self.count_tokens_for_all_models(prompt_id, false, window, cx);
+        }
+        cx.notify();
+    }
+
+    pub(crate) fn render_model_token_counts(
+        &self,
+        prompt_id: PromptId,
+        cx: &App,
+    ) -> Option<impl IntoElement> {
+        if !self.show_model_token_counts {
+            return None;
+        }
+        let rule_editor = self.rule_editors.get(&prompt_id)?;
+        let registry = LanguageModelRegistry::read_global(cx);
+        let default_model = registry.default_model().map(|default| default.model);
+        let rows = registry
+            .available_models(cx)
+            .map(|model| {
+                let key = (model.provider_id(), model.id());
+                let is_default = default_model.as_ref().is_some_and(|default_model| {
+                    default_model.provider_id() == key.0 && default_model.id() == key.1
+                });
+                let count = match rule_editor.model_token_counts.counts.get(&key) {
+                    Some(Some(token_count)) => format!("{token_count} tokens"),
+                    Some(None) => "–".to_string(),
+                    None => "…".to_string(),
+                };
+                h_flex()
+                    .gap_4()
+                    .justify_between()
+                    .child(
+                        Label::new(format!("{} {}", model.provider_name().0, model.name().0))
+                            .size(LabelSize::Small)
+                            .color(if is_default {
+                                Color::Accent
+                            } else {
+                                Color::Default
+                            })
+                            .truncate(),
+                    )
+                    .child(Label::new(count).size(LabelSize::Small).color(Color::Muted))
+            })
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return None;
+        }
+
+        Some(deferred(
+            anchored().anchor(Corner::TopRight).child(
+                v_flex()
+                    .id("model-token-counts")
+                    .occlude()
+                    .mt_6()
+                    .p_2()
+                    .gap_1()
+                    .min_w_64()
+                    .max_w_96()
+                    .max_h_80()
+                    .overflow_y_scroll()
+                    .elevation_2(cx)
+                    .child(
+                        Label::new("Token Estimation by Model")
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .children(rows)
+                    .when(self.show_cursor_token_count, |this| {
+                        this.child(
+                            Label::new("The header counts tokens before the cursor / in total.")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                    }),
+            ),
+        ))
+    }
+}
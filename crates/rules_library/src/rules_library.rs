@@ -1,37 +1,63 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use collections::{HashMap, HashSet};
-use editor::{CompletionProvider, SelectionEffects};
-use editor::{CurrentLineHighlight, Editor, EditorElement, EditorEvent, EditorStyle, actions::Tab};
+use editor::display_map::{DisplayRow, DisplaySnapshot};
+use editor::scroll::ScrollAnchor;
+use editor::{CompletionProvider, SelectionEffects, hover_markdown_style};
+use editor::{
+    CurrentLineHighlight, Editor, EditorElement, EditorEvent, EditorStyle,
+    actions::{Redo, Tab, Undo},
+};
+use fs::Fs;
+use futures::future::{FutureExt as _, Shared};
 use gpui::{
-    Action, App, Bounds, DEFAULT_ADDITIONAL_WINDOW_SIZE, Entity, EventEmitter, Focusable,
+    Action, App, Bounds, ClickEvent, ClipboardItem, Corner, DEFAULT_ADDITIONAL_WINDOW_SIZE, Entity,
+    EventEmitter, Focusable, Global, MouseButton, MouseDownEvent, MouseUpEvent, PathPromptOptions,
     PromptLevel, Subscription, Task, TextStyle, TitlebarOptions, WindowBounds, WindowHandle,
     WindowOptions, actions, point, size, transparent_black,
 };
-use language::{Buffer, LanguageRegistry, language_settings::SoftWrap};
+use language::{
+    Buffer, BufferSnapshot, Language, LanguageRegistry, Point, ToOffset as _, ToPoint as _,
+    TransactionId, language_settings::SoftWrap,
+};
 use language_model::{
     ConfiguredModel, LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage, Role,
 };
+use markdown::{Markdown, MarkdownElement};
 use picker::{Picker, PickerDelegate};
 use release_channel::ReleaseChannel;
 use rope::Rope;
-use settings::Settings;
+use settings::{RulesLibraryFocus, RulesLibrarySortOrder, Settings, SettingsStore};
+use std::future::Future;
+use std::ops::{Range, RangeInclusive};
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use theme::ThemeSettings;
 use title_bar::platform_title_bar::PlatformTitleBar;
 use ui::{
-    Divider, KeyBinding, ListItem, ListItemSpacing, ListSubHeader, Render, Tooltip, prelude::*,
+    Banner, Chip, CommonAnimationExt as _, ContextMenu, Disclosure, Divider, HighlightedLabel,
+    KeyBinding, ListItem, ListItemSpacing, ListSubHeader, PopoverMenu, Render, Tooltip, prelude::*,
 };
 use util::{ResultExt, TryFutureExt};
 use workspace::{Workspace, WorkspaceSettings, client_side_decorations};
-use zed_actions::assistant::InlineAssist;
+use zed_actions::{agent::ToggleModelSelector, assistant::InlineAssist};
 
 use prompt_store::*;
 
+mod default_rule_toggle;
+mod library_summary;
+mod migration_progress;
+mod model_token_counts;
+
+pub use default_rule_toggle::DefaultRuleToggle;
+use library_summary::LibrarySummary;
+use migration_progress::MigrationProgressView;
+use model_token_counts::ModelTokenCounts;
+
 pub fn init(cx: &mut App) {
     prompt_store::init(cx);
+    cx.observe_new(DefaultRuleToggle::register).detach();
 }
 
 actions!(
@@ -39,15 +65,89 @@ actions!(
     [
         /// Creates a new rule in the rules library.
         NewRule,
-        /// Deletes the selected rule.
+        /// Deletes the rules checked in the list, or the selected rule if none are checked.
         DeleteRule,
         /// Duplicates the selected rule.
         DuplicateRule,
         /// Toggles whether the selected rule is a default rule.
-        ToggleDefaultRule
+        ToggleDefaultRule,
+        /// Picks a rule by title and toggles whether it's a default rule, without opening the library.
+        ToggleDefaultRuleByTitle,
+        /// Toggles whether the rules checked in the list are default rules, or the
+        /// active rule if none are checked.
+        ToggleDefaultForSelectedRules,
+        /// Leaves the active default rule out of threads without removing it from the
+        /// default rules, or includes it again.
+        ToggleRuleDisabled,
+        /// Copies the selected rules to the clipboard so they can be pasted into a rules library.
+        CopyRules,
+        /// Creates new rules from rules previously copied to the clipboard.
+        PasteRules,
+        /// Copies the active rule's body to the clipboard.
+        CopyRule,
+        /// Copies the active rule's body as it's displayed, turning soft wraps into line breaks.
+        CopyRuleWrapped,
+        /// Toggles the outline of markdown headings next to the active rule's body.
+        ToggleRuleOutline,
+        /// Shows or hides the active rule's markdown rendered next to its body.
+        ToggleRulePreview,
+        /// Locks or unlocks the selected rule against edits.
+        ToggleRuleLock,
+        /// Archives the selected rule, hiding it from the list without deleting it.
+        ArchiveRule,
+        /// Restores the selected archived rule to the list.
+        UnarchiveRule,
+        /// Shows or hides archived rules in the list.
+        ToggleArchivedRules,
+        /// Creates a rule for every text or markdown file in a chosen directory, reading
+        /// the front matter of markdown files exported from a rules library.
+        ImportRulesFromDirectory,
+        /// Writes every rule to a chosen directory as a markdown file with front matter.
+        ExportRulesToDirectory,
+        /// Writes every rule, with all of its metadata, to a single backup file.
+        BackupRules,
+        /// Merges a backup file into the library, keeping whichever copy of a rule
+        /// was saved last.
+        RestoreRules,
+        /// Deletes every rule that has no title and an empty body.
+        DeleteEmptyRules,
+        /// Shows or hides the active rule's token count.
+        ToggleTokenCount,
+        /// Counts the active rule's tokens right away.
+        RecountTokens,
+        /// Shows or hides how many of the active rule's tokens come before the cursor.
+        ToggleCursorTokenCount,
+        /// Shows every default rule joined together, as the assistant sees them.
+        PreviewDefaultRules,
+        /// Starts or stops collecting timings of rule saves.
+        ToggleSaveMetrics,
+        /// Shows the rule save timings collected since they were enabled.
+        ShowSaveMetrics,
+        /// Writes the active rule's body to a file, optionally removing it from the library.
+        SaveRuleToFile,
+        /// Keeps the rules library window above other windows, or stops doing so.
+        ToggleAlwaysOnTop,
+        /// Shows or hides counts and token totals across the whole rules library.
+        ShowLibrarySummary,
+        /// Focuses the search field above the rule list and selects its query.
+        FocusSearch
     ]
 );
 
+/// How many of the rules opened most recently are listed above the rule list.
+const RECENT_RULE_COUNT: usize = 5;
+
+/// Below this window width, the library shows either the rule list or the
+/// active rule instead of both side by side.
+const COMPACT_LAYOUT_MAX_WIDTH: Pixels = px(640.);
+
+/// Whether rules library windows stay above other windows. Toggled with
+/// [`ToggleAlwaysOnTop`] and kept for the rest of the session.
+#[derive(Default)]
+struct AlwaysOnTop(bool);
+
+impl Global for AlwaysOnTop {}
+
 const BUILT_IN_TOOLTIP_TEXT: &str = concat!(
     "This rule supports special functionality.\n",
     "It's read-only, but you can remove it from your default rules."
@@ -82,6 +182,7 @@ pub fn open_rules_library(
     inline_assist_delegate: Box<dyn InlineAssistDelegate>,
     make_completion_provider: Rc<dyn Fn() -> Rc<dyn CompletionProvider>>,
     prompt_to_select: Option<PromptId>,
+    read_only: bool,
     cx: &mut App,
 ) -> Task<Result<WindowHandle<RulesLibrary>>> {
     let store = PromptStore::global(cx);
@@ -96,6 +197,7 @@ pub fn open_rules_library(
                 if let Some(existing_window) = existing_window {
                     existing_window
                         .update(cx, |rules_library, window, cx| {
+                            rules_library.set_read_only(read_only, window, cx);
                             if let Some(prompt_to_select) = prompt_to_select {
                                 rules_library.load_rule(prompt_to_select, true, window, cx);
                             }
@@ -115,7 +217,44 @@ pub fn open_rules_library(
             return Ok(existing_window);
         }
 
-        let store = store.await?;
+        // Rules saved by older versions of Zed may still be migrating, in which
+        // case the store isn't ready yet. The migration may only start after
+        // the library was opened, so keep checking on it until the store has
+        // loaded, and show how far along it is once it's running.
+        let progress = cx
+            .update(|cx| PromptStore::migration_progress(cx))
+            .ok()
+            .flatten();
+        let store = store.fuse();
+        futures::pin_mut!(store);
+        let mut progress_window = None;
+        let store = loop {
+            let mut poll = cx
+                .background_executor()
+                .timer(migration_progress::POLL_INTERVAL)
+                .fuse();
+            futures::select_biased! {
+                store = store => break store,
+                _ = poll => {
+                    if progress_window.is_none()
+                        && let Some(progress) = progress
+                            .as_ref()
+                            .filter(|progress| progress.is_running())
+                    {
+                        progress_window = cx
+                            .update(|cx| open_migration_progress_window(progress.clone(), cx))
+                            .ok()
+                            .flatten();
+                    }
+                }
+            }
+        };
+        if let Some(progress_window) = progress_window {
+            progress_window
+                .update(cx, |_, window, _| window.remove_window())
+                .ok();
+        }
+        let store = store?;
         cx.update(|cx| {
             let app_id = ReleaseChannel::global(cx).app_id();
             let bounds = Bounds::centered(None, size(px(1024.0), px(768.0)), cx);
@@ -150,6 +289,7 @@ pub fn open_rules_library(
                             inline_assist_delegate,
                             make_completion_provider,
                             prompt_to_select,
+                            read_only,
                             window,
                             cx,
                         )
@@ -160,39 +300,288 @@ pub fn open_rules_library(
     })
 }
 
+fn open_migration_progress_window(
+    progress: Arc<MigrationProgress>,
+    cx: &mut App,
+) -> Option<WindowHandle<MigrationProgressView>> {
+    let bounds = Bounds::centered(None, size(px(360.0), px(120.0)), cx);
+    cx.open_window(
+        WindowOptions {
+            titlebar: Some(TitlebarOptions {
+                title: Some("Upgrading Rules Library".into()),
+                ..Default::default()
+            }),
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            is_resizable: false,
+            kind: gpui::WindowKind::Floating,
+            ..Default::default()
+        },
+        |_, cx| cx.new(|cx| MigrationProgressView::new(progress, cx)),
+    )
+    .log_err()
+}
+
 pub struct RulesLibrary {
     title_bar: Option<Entity<PlatformTitleBar>>,
     store: Entity<PromptStore>,
     language_registry: Arc<LanguageRegistry>,
+    /// Loaded when the library opens so that opening a rule doesn't wait on it.
+    /// Resolves to `None` if Markdown fails to load, in which case rules are
+    /// edited as plain text.
+    markdown: Shared<Task<Option<Arc<Language>>>>,
     rule_editors: HashMap<PromptId, RuleEditor>,
     active_rule_id: Option<PromptId>,
     picker: Entity<Picker<RulePickerDelegate>>,
     pending_load: Task<()>,
+    show_outline: bool,
+    show_notes: bool,
+    show_token_count: bool,
+    /// Whether to show the number of tokens before the cursor next to the token count.
+    show_cursor_token_count: bool,
+    /// Whether the token count is hovered, which shows the count for every model.
+    show_model_token_counts: bool,
+    /// Whether the library was opened for browsing only, in which case rules
+    /// can't be edited, created or deleted.
+    read_only: bool,
+    default_rules_preview: Option<DefaultRulesPreview>,
+    library_summary: Option<LibrarySummary>,
+    /// How many rules are being deleted at once, if any.
+    deleting_rule_count: Option<usize>,
+    /// The rules deleted last, while their deletion can still be undone from
+    /// the rule list.
+    deleted_rules: Option<DeletedRules>,
+    /// Whether the last mouse down in a rule or the default rules preview was a
+    /// cmd-click (ctrl-click on Linux and Windows), which opens the rule an
+    /// `@include(...)` under it refers to.
+    include_click_pending: bool,
+    /// Explains why the last cmd-clicked `@include(...)` didn't open anything.
+    unresolved_include: Option<SharedString>,
+    /// Whether rules are being imported from a directory, in which case the
+    /// rule list is refreshed as each batch of them is inserted.
+    importing_rules: bool,
+    /// Whether the rule list skipped re-sorting after a save because the rule
+    /// was being edited. See `freeze_order_while_editing`.
+    picker_refresh_deferred: bool,
+    /// Whether to focus the body of the first rule the picker selects, per the
+    /// `focus_on_open` setting.
+    focus_body_on_load: bool,
+    /// Only collected while enabled with [`ToggleSaveMetrics`].
+    save_metrics: Option<SaveMetrics>,
+    /// In the compact layout, whether the active rule is shown instead of the list.
+    show_rule_in_compact_layout: bool,
     inline_assist_delegate: Box<dyn InlineAssistDelegate>,
     make_completion_provider: Rc<dyn Fn() -> Rc<dyn CompletionProvider>>,
     _subscriptions: Vec<Subscription>,
 }
 
+/// How long the rule list offers to undo a deletion.
+const UNDO_DELETE_TIMEOUT: Duration = Duration::from_secs(8);
+
+struct DeletedRules {
+    prompt_ids: Vec<PromptId>,
+    _dismiss: Task<()>,
+}
+
 struct RuleEditor {
     title_editor: Entity<Editor>,
     body_editor: Entity<Editor>,
     token_count: Option<u64>,
     pending_token_count: Task<Option<()>>,
+    /// The number of tokens in the body before the cursor.
+    cursor_token_count: Option<u64>,
+    word_count: usize,
+    char_count: usize,
+    pending_text_count: Task<()>,
+    pending_cursor_token_count: Task<Option<()>>,
+    model_token_counts: ModelTokenCounts,
     next_title_and_body_to_save: Option<(String, Rope)>,
+    /// When the next title and body were queued, if save metrics are enabled.
+    next_save_queued_at: Option<Instant>,
     pending_save: Option<Task<Option<()>>>,
+    outline: Vec<RuleOutlineEntry>,
+    pending_outline: Task<()>,
+    /// The body rendered as markdown, while the preview is shown.
+    preview: Option<Entity<Markdown>>,
+    notes_editor: Entity<Editor>,
+    pending_notes_save: Task<()>,
+    /// A short summary shown under the rule's title in the list.
+    description_editor: Entity<Editor>,
+    pending_description_save: Task<()>,
+    /// The rule's tags, separated by commas.
+    tags_editor: Entity<Editor>,
+    pending_tags_save: Task<()>,
+    /// When each of the title's and body's transactions was last edited, so
+    /// that a change spanning both editors can be undone as a unit.
+    title_edit_times: HashMap<TransactionId, Instant>,
+    body_edit_times: HashMap<TransactionId, Instant>,
+    /// Whether to fold the body's sections once it has been parsed for the first time.
+    fold_sections_on_parse: bool,
+    /// The title when the title editor was focused, used to offer updating
+    /// `@include(...)` references to it once the rule has been renamed.
+    title_before_edit: Option<SharedString>,
     _subscriptions: Vec<Subscription>,
 }
 
+/// Title and body edits made within this long of each other are undone and
+/// redone together.
+const LINKED_EDIT_WINDOW: Duration = Duration::from_secs(2);
+
+/// The transaction the editor would undo next, or redo next if `redo` is set.
+fn next_transaction(editor: &Entity<Editor>, redo: bool, cx: &App) -> Option<TransactionId> {
+    let buffer = editor.read(cx).buffer().read(cx).as_singleton()?;
+    let buffer = buffer.read(cx);
+    let entry = if redo {
+        buffer.peek_redo_stack()
+    } else {
+        buffer.peek_undo_stack()
+    }?;
+    Some(entry.transaction_id())
+}
+
+/// How many transactions' edit times are kept per editor. Older transactions
+/// are undone in each editor on its own.
+const MAX_RECORDED_EDIT_TIMES: usize = 100;
+
+/// Records when the editor's latest transaction was edited. Undoing leaves
+/// something on the redo stack, and isn't recorded as a new edit.
+fn record_edit_time(
+    editor: &Entity<Editor>,
+    edit_times: &mut HashMap<TransactionId, Instant>,
+    cx: &App,
+) {
+    let Some(buffer) = editor.read(cx).buffer().read(cx).as_singleton() else {
+        return;
+    };
+    let buffer = buffer.read(cx);
+    if buffer.peek_redo_stack().is_none()
+        && let Some(entry) = buffer.peek_undo_stack()
+    {
+        edit_times.insert(entry.transaction_id(), Instant::now());
+        if edit_times.len() > MAX_RECORDED_EDIT_TIMES
+            && let Some(oldest_transaction_id) = edit_times
+                .iter()
+                .min_by_key(|(_, edited_at)| **edited_at)
+                .map(|(transaction_id, _)| *transaction_id)
+        {
+            edit_times.remove(&oldest_transaction_id);
+        }
+    }
+}
+
+/// Timings of rule saves, used to diagnose saves that fall behind typing.
+#[derive(Default)]
+struct SaveMetrics {
+    saves: u32,
+    /// Edits that replaced a queued edit before it could be saved.
+    coalesced_edits: u32,
+    total_wait: Duration,
+    max_wait: Duration,
+    total_save_duration: Duration,
+    max_save_duration: Duration,
+}
+
+impl SaveMetrics {
+    fn record_save(&mut self, wait: Duration, save_duration: Duration) {
+        self.saves += 1;
+        self.total_wait += wait;
+        self.max_wait = self.max_wait.max(wait);
+        self.total_save_duration += save_duration;
+        self.max_save_duration = self.max_save_duration.max(save_duration);
+    }
+
+    fn summary(&self) -> String {
+        let average = |total: Duration| total.checked_div(self.saves).unwrap_or_default();
+        format!(
+            "Saves: {}\nCoalesced edits: {}\nWait before saving: {:?} average, {:?} max\nSave duration: {:?} average, {:?} max",
+            self.saves,
+            self.coalesced_edits,
+            average(self.total_wait),
+            self.max_wait,
+            average(self.total_save_duration),
+            self.max_save_duration,
+        )
+    }
+}
+
+/// A read-only view of every default rule joined together.
+struct DefaultRulesPreview {
+    editor: Entity<Editor>,
+    token_count: Option<u64>,
+    pending_refresh: Task<Option<()>>,
+    _editor_subscription: Subscription,
+}
+
+struct RuleOutlineEntry {
+    row: u32,
+    depth: usize,
+    title: SharedString,
+}
+
 enum RulePickerEntry {
-    Header(SharedString),
-    Rule(PromptMetadata),
+    Header(RuleSection),
+    Rule(PromptMatch),
     Separator,
 }
 
+/// A group of rules listed under a header before the rest.
+#[derive(Clone, Copy)]
+enum RuleSection {
+    Pinned,
+    Default,
+}
+
+impl RuleSection {
+    fn title(self) -> &'static str {
+        match self {
+            RuleSection::Pinned => "Pinned Rules",
+            RuleSection::Default => "Default Rules",
+        }
+    }
+
+    fn tooltip(self) -> &'static str {
+        match self {
+            RuleSection::Pinned => "Pinned Rules are listed first, whatever the search.",
+            RuleSection::Default => "Default Rules are attached by default with every new thread.",
+        }
+    }
+}
+
 struct RulePickerDelegate {
     store: Entity<PromptStore>,
     selected_index: usize,
     filtered_entries: Vec<RulePickerEntry>,
+    default_only: bool,
+    include_archived: bool,
+    read_only: bool,
+    /// The rule to select the first time matches are updated, instead of the
+    /// first rule.
+    rule_to_select: Option<PromptId>,
+    /// The token range the current query filters by, if any. Rules are
+    /// filtered by the token counts cached in the store.
+    token_range: Option<RangeInclusive<u64>>,
+    /// Rules checked with a secondary click, which batch actions apply to
+    /// instead of the active rule.
+    checked_rule_ids: HashSet<PromptId>,
+    search_options: PromptSearchOptions,
+}
+
+impl RulePickerDelegate {
+    /// Replaces a rule's metadata without re-sorting or re-filtering the list.
+    fn update_rule_in_place(&mut self, metadata: PromptMetadata) {
+        for entry in &mut self.filtered_entries {
+            if let RulePickerEntry::Rule(rule) = entry
+                && rule.metadata.id == metadata.id
+            {
+                *rule = PromptMatch {
+                    metadata,
+                    title_positions: Vec::new(),
+                    description_positions: Vec::new(),
+                    notes_positions: Vec::new(),
+                };
+                return;
+            }
+        }
+    }
 }
 
 enum RulePickerEvent {
@@ -200,6 +589,7 @@ enum RulePickerEvent {
     Confirmed { prompt_id: PromptId },
     Deleted { prompt_id: PromptId },
     ToggledDefault { prompt_id: PromptId },
+    ToggledPinned { prompt_id: PromptId },
 }
 
 impl EventEmitter<RulePickerEvent> for Picker<RulePickerDelegate> {}
@@ -214,6 +604,8 @@ impl PickerDelegate for RulePickerDelegate {
     fn no_matches_text(&self, _window: &mut Window, cx: &mut App) -> Option<SharedString> {
         let text = if self.store.read(cx).prompt_count() == 0 {
             "No rules.".into()
+        } else if self.token_range.is_some() {
+            "No rules found. Only rules whose tokens were counted match token ranges.".into()
         } else {
             "No rules found matching your search.".into()
         };
@@ -228,7 +620,9 @@ impl PickerDelegate for RulePickerDelegate {
         self.selected_index = ix.min(self.filtered_entries.len().saturating_sub(1));
 
         if let Some(RulePickerEntry::Rule(rule)) = self.filtered_entries.get(self.selected_index) {
-            cx.emit(RulePickerEvent::Selected { prompt_id: rule.id });
+            cx.emit(RulePickerEvent::Selected {
+                prompt_id: rule.metadata.id,
+            });
         }
 
         cx.notify();
@@ -251,32 +645,86 @@ impl PickerDelegate for RulePickerDelegate {
         window: &mut Window,
         cx: &mut Context<Picker<Self>>,
     ) -> Task<()> {
+        let (query, tag_filters) = parse_tag_filters(&query);
+        let (query, token_range) = parse_token_range(&query);
+        // Without a model to count tokens with, there's nothing to filter by.
+        self.token_range = token_range.filter(|_| {
+            LanguageModelRegistry::read_global(cx)
+                .default_model()
+                .is_some()
+        });
+        let token_filter = self
+            .token_range
+            .clone()
+            .map(|range| (range, self.store.read(cx).token_counts().clone()));
         let cancellation_flag = Arc::new(AtomicBool::default());
-        let search = self.store.read(cx).search(query, cancellation_flag, cx);
+        let search = self.store.read(cx).search_with_positions(
+            query,
+            self.search_options,
+            cancellation_flag,
+            cx,
+        );
+        let default_only = self.default_only;
+        let include_archived = self.include_archived;
 
         let prev_prompt_id = self
             .filtered_entries
             .get(self.selected_index)
             .and_then(|entry| {
                 if let RulePickerEntry::Rule(rule) = entry {
-                    Some(rule.id)
+                    Some(rule.metadata.id)
                 } else {
                     None
                 }
-            });
+            })
+            .or_else(|| self.rule_to_select.take());
 
         cx.spawn_in(window, async move |this, cx| {
             let (filtered_entries, selected_index) = cx
                 .background_spawn(async move {
-                    let matches = search.await;
+                    let mut matches = search.await;
+                    if default_only {
+                        matches.retain(|rule| rule.metadata.default);
+                    }
+                    if !include_archived {
+                        matches.retain(|rule| !rule.metadata.archived);
+                    }
+                    matches.retain(|rule| {
+                        tag_filters.iter().all(|tag_filter| {
+                            rule.metadata
+                                .tags
+                                .iter()
+                                .any(|tag| tag.eq_ignore_ascii_case(tag_filter))
+                        })
+                    });
+                    if let Some((range, token_counts)) = token_filter {
+                        matches.retain(|rule| {
+                            token_counts
+                                .get(&rule.metadata.id)
+                                .is_some_and(|count| range.contains(count))
+                        });
+                    }
 
-                    let (default_rules, non_default_rules): (Vec<_>, Vec<_>) =
-                        matches.iter().partition(|rule| rule.default);
+                    let (pinned_rules, unpinned_rules): (Vec<_>, Vec<_>) =
+                        matches.iter().partition(|rule| rule.metadata.pinned);
+                    let (default_rules, non_default_rules): (Vec<_>, Vec<_>) = unpinned_rules
+                        .into_iter()
+                        .partition(|rule| rule.metadata.default);
 
                     let mut filtered_entries = Vec::new();
 
+                    if !pinned_rules.is_empty() {
+                        filtered_entries.push(RulePickerEntry::Header(RuleSection::Pinned));
+
+                        for rule in pinned_rules {
+                            filtered_entries.push(RulePickerEntry::Rule(rule.clone()));
+                        }
+
+                        filtered_entries.push(RulePickerEntry::Separator);
+                    }
+
                     if !default_rules.is_empty() {
-                        filtered_entries.push(RulePickerEntry::Header("Default Rules".into()));
+                        filtered_entries.push(RulePickerEntry::Header(RuleSection::Default));
 
                         for rule in default_rules {
                             filtered_entries.push(RulePickerEntry::Rule(rule.clone()));
@@ -293,7 +741,7 @@ impl PickerDelegate for RulePickerDelegate {
                         .and_then(|prev_prompt_id| {
                             filtered_entries.iter().position(|entry| {
                                 if let RulePickerEntry::Rule(rule) = entry {
-                                    rule.id == prev_prompt_id
+                                    rule.metadata.id == prev_prompt_id
                                 } else {
                                     false
                                 }
@@ -327,7 +775,9 @@ impl PickerDelegate for RulePickerDelegate {
 
     fn confirm(&mut self, _secondary: bool, _: &mut Window, cx: &mut Context<Picker<Self>>) {
         if let Some(RulePickerEntry::Rule(rule)) = self.filtered_entries.get(self.selected_index) {
-            cx.emit(RulePickerEvent::Confirmed { prompt_id: rule.id });
+            cx.emit(RulePickerEvent::Confirmed {
+                prompt_id: rule.metadata.id,
+            });
         }
     }
 
@@ -341,16 +791,14 @@ impl PickerDelegate for RulePickerDelegate {
         cx: &mut Context<Picker<Self>>,
     ) -> Option<Self::ListItem> {
         match self.filtered_entries.get(ix)? {
-            RulePickerEntry::Header(title) => Some(
-                ListSubHeader::new(title.clone())
+            RulePickerEntry::Header(section) => Some(
+                ListSubHeader::new(section.title())
                     .end_slot(
                         IconButton::new("info", IconName::Info)
                             .style(ButtonStyle::Transparent)
                             .icon_size(IconSize::Small)
                             .icon_color(Color::Muted)
-                            .tooltip(Tooltip::text(
-                                "Default Rules are attached by default with every new thread.",
-                            ))
+                            .tooltip(Tooltip::text(section.tooltip()))
                             .into_any_element(),
                     )
                     .inset(true)
@@ -363,20 +811,104 @@ impl PickerDelegate for RulePickerDelegate {
                     .into_any_element(),
             ),
             RulePickerEntry::Rule(rule) => {
-                let default = rule.default;
-                let prompt_id = rule.id;
+                let default = rule.metadata.default;
+                let archived = rule.metadata.archived;
+                let pinned = rule.metadata.pinned;
+                let disabled = default && rule.metadata.disabled;
+                let prompt_id = rule.metadata.id;
+                let checked = self.checked_rule_ids.contains(&prompt_id);
+                let show_usage_count =
+                    self.store.read(cx).sort_order() == RulesLibrarySortOrder::MostUsed;
 
                 Some(
                     ListItem::new(ix)
                         .inset(true)
                         .spacing(ListItemSpacing::Sparse)
                         .toggle_state(selected)
+                        .start_slot::<Icon>(checked.then(|| {
+                            Icon::new(IconName::Check)
+                                .size(IconSize::Small)
+                                .color(Color::Accent)
+                        }))
+                        .on_click(cx.listener(move |picker, event: &ClickEvent, _, cx| {
+                            if event.modifiers().secondary() {
+                                let checked_rule_ids = &mut picker.delegate.checked_rule_ids;
+                                if !checked_rule_ids.remove(&prompt_id) {
+                                    checked_rule_ids.insert(prompt_id);
+                                }
+                                cx.stop_propagation();
+                                cx.notify();
+                            }
+                        }))
                         .child(
-                            Label::new(rule.title.clone().unwrap_or("Untitled".into()))
-                                .truncate()
-                                .mr_10(),
+                            v_flex()
+                                .mr_10()
+                                .when(disabled, |this| this.opacity(0.5))
+                                .child(
+                                    h_flex()
+                                        .gap_1()
+                                        .child(
+                                            HighlightedLabel::new(
+                                                rule.metadata
+                                                    .title
+                                                    .clone()
+                                                    .unwrap_or("Untitled".into()),
+                                                rule.title_positions.clone(),
+                                            )
+                                            .when(archived, |label| label.color(Color::Muted))
+                                            .truncate(),
+                                        )
+                                        .children(render_rule_state_labels(&rule.metadata, cx))
+                                        .children(rule.metadata.tags.iter().map(|tag| {
+                                            Chip::new(tag.clone()).label_color(Color::Muted)
+                                        }))
+                                        .when(show_usage_count, |this| {
+                                            this.child(
+                                                Label::new(match rule.metadata.usage_count {
+                                                    1 => "1 use".to_string(),
+                                                    count => format!("{count} uses"),
+                                                })
+                                                .size(LabelSize::Small)
+                                                .color(Color::Muted),
+                                            )
+                                        }),
+                                )
+                                .when_some(
+                                    rule.metadata.description.as_ref(),
+                                    |this, description| {
+                                        this.child(
+                                            HighlightedLabel::new(
+                                                description.clone(),
+                                                rule.description_positions.clone(),
+                                            )
+                                            .size(LabelSize::Small)
+                                            .color(Color::Muted)
+                                            .truncate(),
+                                        )
+                                    },
+                                )
+                                // Show the notes when they matched, so it's clear why the rule
+                                // is in the results.
+                                .when_some(
+                                    rule.metadata
+                                        .notes
+                                        .as_ref()
+                                        .filter(|_| !rule.notes_positions.is_empty()),
+                                    |this, notes| {
+                                        this.child(
+                                            HighlightedLabel::new(
+                                                // Replacing newlines keeps the match positions valid.
+                                                notes.replace('\n', " "),
+                                                rule.notes_positions.clone(),
+                                            )
+                                            .size(LabelSize::Small)
+                                            .color(Color::Muted)
+                                            .truncate(),
+                                        )
+                                    },
+                                ),
                         )
-                        .end_slot::<IconButton>(default.then(|| {
+                        .end_slot::<IconButton>((default && !self.read_only).then(|| {
                             IconButton::new("toggle-default-rule", IconName::Paperclip)
                                 .toggle_state(true)
                                 .icon_color(Color::Accent)
@@ -386,7 +918,7 @@ impl PickerDelegate for RulePickerDelegate {
                                     cx.emit(RulePickerEvent::ToggledDefault { prompt_id })
                                 }))
                         }))
-                        .end_hover_slot(
+                        .end_hover_slot((!self.read_only).then(|| {
                             h_flex()
                                 .child(if prompt_id.is_built_in() {
                                     div()
@@ -411,10 +943,32 @@ impl PickerDelegate for RulePickerDelegate {
                                         }))
                                         .into_any_element()
                                 })
+                                .child(
+                                    IconButton::new("toggle-pinned-rule", IconName::Pin)
+                                        .selected_icon(IconName::Unpin)
+                                        .toggle_state(pinned)
+                                        .icon_size(IconSize::Small)
+                                        .icon_color(if pinned {
+                                            Color::Accent
+                                        } else {
+                                            Color::Muted
+                                        })
+                                        .tooltip(Tooltip::text(if pinned {
+                                            "Unpin Rule"
+                                        } else {
+                                            "Pin Rule"
+                                        }))
+                                        .on_click(cx.listener(move |_, _, _, cx| {
+                                            cx.emit(RulePickerEvent::ToggledPinned { prompt_id })
+                                        })),
+                                )
                                 .child(
                                     IconButton::new("toggle-default-rule", IconName::Plus)
                                         .selected_icon(IconName::Dash)
                                         .toggle_state(default)
+                                        // Archived rules have to be unarchived before
+                                        // they can be defaults.
+                                        .disabled(archived)
                                         .icon_size(IconSize::Small)
                                         .icon_color(if default {
                                             Color::Accent
@@ -440,8 +994,8 @@ impl PickerDelegate for RulePickerDelegate {
                                         .on_click(cx.listener(move |_, _, _, cx| {
                                             cx.emit(RulePickerEvent::ToggledDefault { prompt_id })
                                         })),
-                                ),
-                        )
+                                )
+                        }))
                         .into_any_element(),
                 )
             }
@@ -465,6 +1019,35 @@ impl PickerDelegate for RulePickerDelegate {
             .border_color(cx.theme().colors().border)
             .child(Icon::new(IconName::MagnifyingGlass).color(Color::Muted))
             .child(editor.clone())
+            .child(
+                IconButton::new("toggle-case-sensitive-search", IconName::CaseSensitive)
+                    .icon_size(IconSize::Small)
+                    .toggle_state(self.search_options.case_sensitive)
+                    .tooltip(Tooltip::text("Match Case"))
+                    .on_click(cx.listener(|picker, _, window, cx| {
+                        let search_options = &mut picker.delegate.search_options;
+                        search_options.case_sensitive = !search_options.case_sensitive;
+                        picker.refresh(window, cx);
+                    })),
+            )
+            .child(
+                IconButton::new("toggle-exact-search", IconName::Quote)
+                    .icon_size(IconSize::Small)
+                    .toggle_state(self.search_options.exact)
+                    .tooltip(|_window, cx| {
+                        Tooltip::with_meta(
+                            "Match Exactly",
+                            None,
+                            "Find the query as typed, instead of fuzzy matching it.",
+                            cx,
+                        )
+                    })
+                    .on_click(cx.listener(|picker, _, window, cx| {
+                        let search_options = &mut picker.delegate.search_options;
+                        search_options.exact = !search_options.exact;
+                        picker.refresh(window, cx);
+                    })),
+            )
     }
 }
 
@@ -475,25 +1058,63 @@ impl RulesLibrary {
         inline_assist_delegate: Box<dyn InlineAssistDelegate>,
         make_completion_provider: Rc<dyn Fn() -> Rc<dyn CompletionProvider>>,
         rule_to_select: Option<PromptId>,
+        read_only: bool,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) -> Self {
-        let (_selected_index, _matches) = if let Some(rule_to_select) = rule_to_select {
-            let matches = store.read(cx).all_prompt_metadata();
-            let selected_index = matches
-                .iter()
-                .enumerate()
-                .find(|(_, metadata)| metadata.id == rule_to_select)
-                .map_or(0, |(ix, _)| ix);
-            (selected_index, matches)
-        } else {
-            (0, vec![])
-        };
+        // Focusing the body only applies when there's a rule to open; otherwise
+        // focus stays in the picker.
+        let focus_body_on_load = RulesLibrarySettings::get_global(cx).focus_on_open
+            == RulesLibraryFocus::Body
+            && store.read(cx).prompt_count() > 0;
+        let rule_to_select = rule_to_select.or_else(|| {
+            if focus_body_on_load {
+                store.read(cx).recently_opened().first().map(|rule| rule.id)
+            } else {
+                None
+            }
+        });
+
+        if cx.default_global::<AlwaysOnTop>().0 {
+            window.set_always_on_top(true);
+        }
+
+        let store_subscription = cx.subscribe_in(
+            &store,
+            window,
+            |this, _, _: &PromptsUpdatedEvent, window, cx| {
+                if this.importing_rules {
+                    this.picker
+                        .update(cx, |picker, cx| picker.refresh(window, cx));
+                }
+                this.refresh_default_rules_preview(window, cx);
+                this.refresh_library_summary(true, window, cx);
+            },
+        );
+
+        let markdown = cx
+            .background_spawn({
+                let language_registry = language_registry.clone();
+                async move {
+                    language_registry
+                        .language_for_name("Markdown")
+                        .await
+                        .log_err()
+                }
+            })
+            .shared();
 
         let picker_delegate = RulePickerDelegate {
             store: store.clone(),
             selected_index: 0,
             filtered_entries: Vec::new(),
+            default_only: false,
+            include_archived: false,
+            read_only,
+            rule_to_select,
+            token_range: None,
+            checked_rule_ids: HashSet::default(),
+            search_options: PromptSearchOptions::default(),
         };
 
         let picker = cx.new(|cx| {
@@ -512,16 +1133,158 @@ impl RulesLibrary {
             },
             store,
             language_registry,
+            markdown,
             rule_editors: HashMap::default(),
             active_rule_id: None,
             pending_load: Task::ready(()),
+            show_outline: false,
+            show_notes: false,
+            show_token_count: true,
+            show_cursor_token_count: false,
+            show_model_token_counts: false,
+            read_only,
+            default_rules_preview: None,
+            library_summary: None,
+            deleting_rule_count: None,
+            deleted_rules: None,
+            importing_rules: false,
+            include_click_pending: false,
+            unresolved_include: None,
+            picker_refresh_deferred: false,
+            focus_body_on_load,
+            save_metrics: None,
+            show_rule_in_compact_layout: false,
             inline_assist_delegate,
             make_completion_provider,
-            _subscriptions: vec![cx.subscribe_in(&picker, window, Self::handle_picker_event)],
+            _subscriptions: vec![
+                cx.subscribe_in(&picker, window, Self::handle_picker_event),
+                store_subscription,
+                cx.subscribe_in(&store, window, Self::handle_token_counts_invalidated),
+                cx.subscribe_in(
+                    &LanguageModelRegistry::global(cx),
+                    window,
+                    Self::handle_language_model_registry_event,
+                ),
+                cx.observe_global_in::<SettingsStore>(window, {
+                    let mut soft_wrap = RulesLibrarySettings::get_global(cx).soft_wrap;
+                    let mut edit_predictions =
+                        RulesLibrarySettings::get_global(cx).edit_predictions;
+                    let mut sort_order = RulesLibrarySettings::get_global(cx).sort_order;
+                    move |this, window, cx| {
+                        // The store re-sorts itself when the setting changes.
+                        let new_sort_order = RulesLibrarySettings::get_global(cx).sort_order;
+                        if sort_order != new_sort_order {
+                            sort_order = new_sort_order;
+                            this.picker
+                                .update(cx, |picker, cx| picker.refresh(window, cx));
+                        }
+
+                        let new_soft_wrap = RulesLibrarySettings::get_global(cx).soft_wrap;
+                        if soft_wrap != new_soft_wrap {
+                            soft_wrap = new_soft_wrap;
+                            for rule_editor in this.rule_editors.values() {
+                                rule_editor.body_editor.update(cx, |editor, cx| {
+                                    editor.set_soft_wrap_mode(soft_wrap, cx)
+                                });
+                            }
+                        }
+
+                        let new_edit_predictions =
+                            RulesLibrarySettings::get_global(cx).edit_predictions;
+                        if edit_predictions != new_edit_predictions {
+                            edit_predictions = new_edit_predictions;
+                            for (prompt_id, rule_editor) in &this.rule_editors {
+                                let show = body_edit_predictions(
+                                    this.is_rule_read_only(*prompt_id, cx),
+                                    cx,
+                                );
+                                rule_editor.body_editor.update(cx, |editor, cx| {
+                                    editor.set_show_edit_predictions(show, window, cx)
+                                });
+                            }
+                        }
+                    }
+                }),
+            ],
             picker,
         }
     }
 
+    fn handle_language_model_registry_event(
+        &mut self,
+        _: &Entity<LanguageModelRegistry>,
+        event: &language_model::Event,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let language_model::Event::DefaultModelChanged = event {
+            self.store
+                .update(cx, |store, cx| store.invalidate_all_token_counts(cx));
+        }
+    }
+
+    /// Drops the token counts shown for the invalidated rules, and recounts
+    /// the active rule if it was among them. Other rules are recounted when
+    /// they're next opened.
+    fn handle_token_counts_invalidated(
+        &mut self,
+        _: &Entity<PromptStore>,
+        event: &TokenCountsInvalidatedEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let active_rule_invalidated = match event {
+            TokenCountsInvalidatedEvent::Prompt(prompt_id) => {
+                if let Some(rule_editor) = self.rule_editors.get_mut(prompt_id) {
+                    rule_editor.token_count = None;
+                    rule_editor.cursor_token_count = None;
+                }
+                self.active_rule_id == Some(*prompt_id)
+            }
+            TokenCountsInvalidatedEvent::All => {
+                for rule_editor in self.rule_editors.values_mut() {
+                    rule_editor.token_count = None;
+                    rule_editor.cursor_token_count = None;
+                }
+                self.refresh_library_summary(false, window, cx);
+                self.active_rule_id.is_some()
+            }
+        };
+        self.picker.update(cx, |picker, cx| {
+            if picker.delegate.token_range.is_some() {
+                picker.refresh(window, cx);
+            }
+        });
+        if active_rule_invalidated && let Some(prompt_id) = self.active_rule_id {
+            self.count_tokens(prompt_id, false, window, cx);
+            self.count_tokens_before_cursor(prompt_id, false, window, cx);
+        }
+        cx.notify();
+    }
+
+    /// Focuses the Agent panel in a workspace window and opens its model selector.
+    fn select_model(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        for window in cx.windows() {
+            if let Some(workspace) = window.downcast::<Workspace>() {
+                let focused = workspace
+                    .update(cx, |workspace, window, cx| {
+                        window.activate_window();
+                        let focused = self
+                            .inline_assist_delegate
+                            .focus_agent_panel(workspace, window, cx);
+                        if focused {
+                            window.dispatch_action(Box::new(ToggleModelSelector), cx);
+                        }
+                        focused
+                    })
+                    .unwrap_or(false);
+                if focused {
+                    return;
+                }
+            }
+        }
+    }
+
     fn handle_picker_event(
         &mut self,
         _: &Entity<Picker<RulePickerDelegate>>,
@@ -531,7 +1294,8 @@ impl RulesLibrary {
     ) {
         match event {
             RulePickerEvent::Selected { prompt_id } => {
-                self.load_rule(*prompt_id, false, window, cx);
+                let focus = std::mem::take(&mut self.focus_body_on_load);
+                self.load_rule(*prompt_id, focus, window, cx);
             }
             RulePickerEvent::Confirmed { prompt_id } => {
                 self.load_rule(*prompt_id, true, window, cx);
@@ -539,6 +1303,9 @@ impl RulesLibrary {
             RulePickerEvent::ToggledDefault { prompt_id } => {
                 self.toggle_default_for_rule(*prompt_id, window, cx);
             }
+            RulePickerEvent::ToggledPinned { prompt_id } => {
+                self.toggle_pinned_for_rule(*prompt_id, window, cx);
+            }
             RulePickerEvent::Deleted { prompt_id } => {
                 self.delete_rule(*prompt_id, window, cx);
             }
@@ -546,13 +1313,21 @@ impl RulesLibrary {
     }
 
     pub fn new_rule(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.create_rule(window, cx).detach_and_log_err(cx);
+    }
+
+    /// Like [`Self::new_rule`], but resolves once the new rule has been
+    /// persisted and loaded.
+    pub fn create_rule(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<PromptId>> {
         // If we already have an untitled rule, use that instead
         // of creating a new one.
-        if let Some(metadata) = self.store.read(cx).first()
-            && metadata.title.is_none()
-        {
+        if let Some(metadata) = self.store.read(cx).first_untitled() {
             self.load_rule(metadata.id, true, window, cx);
-            return;
+            return Task::ready(Ok(metadata.id));
         }
 
         let prompt_id = PromptId::new();
@@ -565,9 +1340,9 @@ impl RulesLibrary {
             save.await?;
             this.update_in(cx, |this, window, cx| {
                 this.load_rule(prompt_id, true, window, cx)
-            })
+            })?;
+            Ok(prompt_id)
         })
-        .detach_and_log_err(cx);
     }
 
     pub fn save_rule(&mut self, prompt_id: PromptId, window: &mut Window, cx: &mut Context<Self>) {
@@ -594,19 +1369,27 @@ impl RulesLibrary {
         let store = self.store.clone();
         let executor = cx.background_executor().clone();
 
+        if let Some(save_metrics) = self.save_metrics.as_mut() {
+            if rule_editor.next_title_and_body_to_save.is_some() {
+                save_metrics.coalesced_edits += 1;
+            } else {
+                rule_editor.next_save_queued_at = Some(Instant::now());
+            }
+        }
         rule_editor.next_title_and_body_to_save = Some((title, body));
         if rule_editor.pending_save.is_none() {
             rule_editor.pending_save = Some(cx.spawn_in(window, async move |this, cx| {
                 async move {
                     loop {
                         let title_and_body = this.update(cx, |this, _| {
-                            this.rule_editors
-                                .get_mut(&prompt_id)?
-                                .next_title_and_body_to_save
-                                .take()
+                            let rule_editor = this.rule_editors.get_mut(&prompt_id)?;
+                            let queued_at = rule_editor.next_save_queued_at.take();
+                            let title_and_body = rule_editor.next_title_and_body_to_save.take()?;
+                            Some((title_and_body, queued_at))
                         })?;
 
-                        if let Some((title, body)) = title_and_body {
+                        if let Some(((title, body), queued_at)) = title_and_body {
+                            let save_started_at = queued_at.map(|_| Instant::now());
                             let title = if title.trim().is_empty() {
                                 None
                             } else {
@@ -620,8 +1403,19 @@ impl RulesLibrary {
                             .await
                             .log_err();
                             this.update_in(cx, |this, window, cx| {
-                                this.picker
-                                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                                if let (
+                                    Some(save_metrics),
+                                    Some(queued_at),
+                                    Some(save_started_at),
+                                ) = (this.save_metrics.as_mut(), queued_at, save_started_at)
+                                {
+                                    save_metrics.record_save(
+                                        save_started_at - queued_at,
+                                        save_started_at.elapsed(),
+                                    );
+                                }
+                                this.refresh_picker_after_save(prompt_id, window, cx);
+                                this.update_window_title(window, cx);
                                 cx.notify();
                             })?;
 
@@ -643,12 +1437,65 @@ impl RulesLibrary {
         }
     }
 
+    /// Refreshes the rule list after a rule was saved. With
+    /// `freeze_order_while_editing`, a rule whose title or body is focused only
+    /// has its row updated in place, and the list is re-sorted once the editor
+    /// loses focus.
+    fn refresh_picker_after_save(
+        &mut self,
+        prompt_id: PromptId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let editing = RulesLibrarySettings::get_global(cx).freeze_order_while_editing
+            && self
+                .rule_editors
+                .get(&prompt_id)
+                .is_some_and(|rule_editor| {
+                    rule_editor.title_editor.focus_handle(cx).is_focused(window)
+                        || rule_editor.body_editor.focus_handle(cx).is_focused(window)
+                });
+        let metadata = self.store.read(cx).metadata(prompt_id);
+        match metadata {
+            Some(metadata) if editing => {
+                self.picker.update(cx, |picker, cx| {
+                    picker.delegate.update_rule_in_place(metadata);
+                    cx.notify();
+                });
+                self.picker_refresh_deferred = true;
+            }
+            _ => {
+                self.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+            }
+        }
+    }
+
+    fn flush_deferred_picker_refresh(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if std::mem::take(&mut self.picker_refresh_deferred) {
+            self.picker
+                .update(cx, |picker, cx| picker.refresh(window, cx));
+        }
+    }
+
     pub fn delete_active_rule(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(active_rule_id) = self.active_rule_id {
             self.delete_rule(active_rule_id, window, cx);
         }
     }
 
+    /// Deletes the rules checked in the list, or the active rule, asking for a
+    /// single confirmation that lists the count when there's more than one.
+    pub fn delete_selected_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let mut prompt_ids = self.selected_rule_ids(cx);
+        prompt_ids.retain(|prompt_id| !prompt_id.is_built_in());
+        match prompt_ids.as_slice() {
+            [] => {}
+            [prompt_id] => self.delete_rule(*prompt_id, window, cx),
+            _ => self.delete_rules(prompt_ids, window, cx),
+        }
+    }
+
     pub fn duplicate_active_rule(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(active_rule_id) = self.active_rule_id {
             self.duplicate_rule(active_rule_id, window, cx);
@@ -679,53 +1526,418 @@ impl RulesLibrary {
         cx.notify();
     }
 
-    pub fn load_rule(
+    pub fn toggle_disabled_for_active_rule(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(active_rule_id) = self.active_rule_id else {
+            return;
+        };
+        self.store.update(cx, |store, cx| {
+            if let Some(rule_metadata) = store.metadata(active_rule_id) {
+                store
+                    .set_disabled(active_rule_id, !rule_metadata.disabled, cx)
+                    .detach_and_log_err(cx);
+            }
+        });
+        self.picker
+            .update(cx, |picker, cx| picker.refresh(window, cx));
+        cx.notify();
+    }
+
+    pub fn toggle_pinned_for_rule(
         &mut self,
         prompt_id: PromptId,
-        focus: bool,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        if let Some(rule_editor) = self.rule_editors.get(&prompt_id) {
-            if focus {
-                rule_editor
-                    .body_editor
-                    .update(cx, |editor, cx| window.focus(&editor.focus_handle(cx)));
-            }
-            self.set_active_rule(Some(prompt_id), window, cx);
-        } else if let Some(rule_metadata) = self.store.read(cx).metadata(prompt_id) {
-            let language_registry = self.language_registry.clone();
-            let rule = self.store.read(cx).load(prompt_id, cx);
-            let make_completion_provider = self.make_completion_provider.clone();
-            self.pending_load = cx.spawn_in(window, async move |this, cx| {
-                let rule = rule.await;
-                let markdown = language_registry.language_for_name("Markdown").await;
-                this.update_in(cx, |this, window, cx| match rule {
-                    Ok(rule) => {
-                        let title_editor = cx.new(|cx| {
-                            let mut editor = Editor::single_line(window, cx);
-                            editor.set_placeholder_text("Untitled", window, cx);
-                            editor.set_text(rule_metadata.title.unwrap_or_default(), window, cx);
-                            if prompt_id.is_built_in() {
-                                editor.set_read_only(true);
-                                editor.set_show_edit_predictions(Some(false), window, cx);
-                            }
-                            editor
-                        });
-                        let body_editor = cx.new(|cx| {
-                            let buffer = cx.new(|cx| {
-                                let mut buffer = Buffer::local(rule, cx);
-                                buffer.set_language(markdown.log_err(), cx);
-                                buffer.set_language_registry(language_registry);
-                                buffer
-                            });
-
-                            let mut editor = Editor::for_buffer(buffer, None, window, cx);
-                            if prompt_id.is_built_in() {
+        self.store
+            .update(cx, |store, cx| store.toggle_pinned(prompt_id, cx))
+            .detach_and_log_err(cx);
+        self.picker
+            .update(cx, |picker, cx| picker.refresh(window, cx));
+        cx.notify();
+    }
+
+    fn save_rule_notes(&mut self, prompt_id: PromptId, cx: &mut Context<Self>) {
+        const NOTES_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let notes_editor = rule_editor.notes_editor.clone();
+        rule_editor.pending_notes_save = cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(NOTES_SAVE_DEBOUNCE).await;
+            this.update(cx, |this, cx| {
+                let notes = notes_editor.read(cx).text(cx);
+                let notes = (!notes.trim().is_empty()).then(|| SharedString::from(notes));
+                this.store
+                    .update(cx, |store, cx| store.set_notes(prompt_id, notes, cx))
+                    .detach_and_log_err(cx);
+            })
+            .ok();
+        });
+    }
+
+    /// Saves the description once it stops changing, and refreshes the rule
+    /// list so that it shows the description under the title and matches it
+    /// when searching.
+    fn save_rule_description(
+        &mut self,
+        prompt_id: PromptId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        const DESCRIPTION_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let description_editor = rule_editor.description_editor.clone();
+        rule_editor.pending_description_save = cx.spawn_in(window, async move |this, cx| {
+            cx.background_executor()
+                .timer(DESCRIPTION_SAVE_DEBOUNCE)
+                .await;
+            this.update_in(cx, |this, window, cx| {
+                let description = description_editor.read(cx).text(cx);
+                let description = Some(description.trim())
+                    .filter(|description| !description.is_empty())
+                    .map(|description| SharedString::from(description.to_string()));
+                this.store
+                    .update(cx, |store, cx| {
+                        store.set_description(prompt_id, description, cx)
+                    })
+                    .detach_and_log_err(cx);
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+            })
+            .ok();
+        });
+    }
+
+    /// Saves the tags once they stop changing, and refreshes the rule list so
+    /// that it shows them and a `tag:` search picks them up.
+    fn save_rule_tags(&mut self, prompt_id: PromptId, window: &mut Window, cx: &mut Context<Self>) {
+        const TAGS_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let tags_editor = rule_editor.tags_editor.clone();
+        rule_editor.pending_tags_save = cx.spawn_in(window, async move |this, cx| {
+            cx.background_executor().timer(TAGS_SAVE_DEBOUNCE).await;
+            this.update_in(cx, |this, window, cx| {
+                let tags = tags_editor
+                    .read(cx)
+                    .text(cx)
+                    .split(',')
+                    .map(|tag| SharedString::from(tag.to_string()))
+                    .collect();
+                this.store
+                    .update(cx, |store, cx| store.set_tags(prompt_id, tags, cx))
+                    .detach_and_log_err(cx);
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+            })
+            .ok();
+        });
+    }
+
+    /// Switches between browsing and editing. Locked and built-in rules stay
+    /// read-only either way.
+    pub fn set_read_only(&mut self, read_only: bool, window: &mut Window, cx: &mut Context<Self>) {
+        if self.read_only == read_only {
+            return;
+        }
+
+        self.read_only = read_only;
+        self.picker.update(cx, |picker, cx| {
+            picker.delegate.read_only = read_only;
+            cx.notify();
+        });
+        for (prompt_id, rule_editor) in &self.rule_editors {
+            let editor_read_only = self.is_rule_read_only(*prompt_id, cx);
+            rule_editor.title_editor.update(cx, |editor, cx| {
+                editor.set_read_only(editor_read_only);
+                editor.set_show_edit_predictions(editor_read_only.then_some(false), window, cx);
+            });
+            rule_editor.body_editor.update(cx, |editor, cx| {
+                editor.set_read_only(editor_read_only);
+                editor.set_show_edit_predictions(
+                    body_edit_predictions(editor_read_only, cx),
+                    window,
+                    cx,
+                );
+            });
+            rule_editor
+                .notes_editor
+                .update(cx, |editor, _| editor.set_read_only(read_only));
+            rule_editor
+                .description_editor
+                .update(cx, |editor, _| editor.set_read_only(read_only));
+            rule_editor
+                .tags_editor
+                .update(cx, |editor, _| editor.set_read_only(editor_read_only));
+        }
+        cx.notify();
+    }
+
+    /// Whether a rule can't be edited, because it's built in, locked, or the
+    /// library is read-only.
+    fn is_rule_read_only(&self, prompt_id: PromptId, cx: &App) -> bool {
+        self.read_only
+            || prompt_id.is_built_in()
+            || self
+                .store
+                .read(cx)
+                .metadata(prompt_id)
+                .is_some_and(|metadata| metadata.locked)
+    }
+
+    pub fn toggle_lock_for_active_rule(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(active_rule_id) = self.active_rule_id else {
+            return;
+        };
+        let Some(rule_metadata) = self.store.read(cx).metadata(active_rule_id) else {
+            return;
+        };
+        if active_rule_id.is_built_in() || self.read_only {
+            return;
+        }
+
+        let locked = !rule_metadata.locked;
+        self.store
+            .update(cx, |store, cx| store.set_locked(active_rule_id, locked, cx))
+            .detach_and_log_err(cx);
+        if let Some(rule_editor) = self.rule_editors.get(&active_rule_id) {
+            rule_editor.title_editor.update(cx, |editor, cx| {
+                editor.set_read_only(locked);
+                editor.set_show_edit_predictions(locked.then_some(false), window, cx);
+            });
+            rule_editor.body_editor.update(cx, |editor, cx| {
+                editor.set_read_only(locked);
+                editor.set_show_edit_predictions(body_edit_predictions(locked, cx), window, cx);
+            });
+            rule_editor
+                .tags_editor
+                .update(cx, |editor, _| editor.set_read_only(locked));
+        }
+        cx.notify();
+    }
+
+    /// Archives or unarchives the active rule. Archiving a default rule also
+    /// removes it from the default rules.
+    pub fn set_active_rule_archived(
+        &mut self,
+        archived: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(active_rule_id) = self.active_rule_id else {
+            return;
+        };
+        if active_rule_id.is_built_in() || self.read_only {
+            return;
+        }
+
+        let archive = self.store.update(cx, |store, cx| {
+            store.set_archived(active_rule_id, archived, cx)
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            archive.await?;
+            this.update_in(cx, |this, window, cx| {
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Loads the language a rule's body is highlighted as: Markdown, unless
+    /// the rule names another language. Unknown languages fall back to plain
+    /// text.
+    fn body_language(
+        &self,
+        language: Option<SharedString>,
+    ) -> impl Future<Output = Option<Arc<Language>>> + use<> {
+        let markdown = self.markdown.clone();
+        let language_registry = self.language_registry.clone();
+        async move {
+            let Some(name) = language else {
+                return markdown.await;
+            };
+            match language_registry.language_for_name(&name).await {
+                Ok(language) => Some(language),
+                Err(error) => {
+                    log::warn!("rule language {name:?} not found: {error}");
+                    Some(language::PLAIN_TEXT.clone())
+                }
+            }
+        }
+    }
+
+    /// Changes the language a rule's body is highlighted as, re-highlighting it
+    /// in place if it's open. `None` goes back to Markdown.
+    fn set_rule_language(
+        &mut self,
+        prompt_id: PromptId,
+        language: Option<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.is_rule_read_only(prompt_id, cx) {
+            return;
+        }
+
+        let save = self.store.update(cx, |store, cx| {
+            store.set_language(prompt_id, language.clone(), cx)
+        });
+        let language = self.body_language(language);
+        cx.spawn_in(window, async move |this, cx| {
+            save.await?;
+            let language = language.await;
+            this.update(cx, |this, cx| {
+                let Some(rule_editor) = this.rule_editors.get(&prompt_id) else {
+                    return;
+                };
+                let buffer = rule_editor
+                    .body_editor
+                    .read(cx)
+                    .buffer()
+                    .read(cx)
+                    .as_singleton();
+                if let Some(buffer) = buffer {
+                    buffer.update(cx, |buffer, cx| buffer.set_language(language, cx));
+                }
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn render_rule_language_menu(
+        &self,
+        prompt_id: PromptId,
+        language: Option<SharedString>,
+        cx: &Context<Self>,
+    ) -> impl IntoElement {
+        let this = cx.entity().downgrade();
+        let language_registry = self.language_registry.clone();
+        let label = language.clone().unwrap_or_else(|| "Markdown".into());
+        PopoverMenu::new("rule-language-menu")
+            .trigger_with_tooltip(
+                Button::new("rule-language", label)
+                    .label_size(LabelSize::Small)
+                    .color(Color::Muted)
+                    .icon(IconName::ChevronDown)
+                    .icon_size(IconSize::XSmall)
+                    .icon_position(IconPosition::End)
+                    .icon_color(Color::Muted),
+                Tooltip::text("Highlight Rule As…"),
+            )
+            .menu(move |window, cx| {
+                let names = language_registry.language_names();
+                let this = this.clone();
+                let language = language.clone();
+                Some(ContextMenu::build(window, cx, move |mut menu, _, _| {
+                    for name in names {
+                        let name = name.0;
+                        let selected = match &language {
+                            Some(language) => *language == name,
+                            None => name == "Markdown",
+                        };
+                        // Markdown is the default, so choosing it clears the override.
+                        let new_language = (name != "Markdown").then(|| name.clone());
+                        let this = this.clone();
+                        menu = menu.toggleable_entry(
+                            name,
+                            selected,
+                            IconPosition::Start,
+                            None,
+                            move |window, cx| {
+                                this.update(cx, |this, cx| {
+                                    this.set_rule_language(
+                                        prompt_id,
+                                        new_language.clone(),
+                                        window,
+                                        cx,
+                                    )
+                                })
+                                .ok();
+                            },
+                        );
+                    }
+                    menu
+                }))
+            })
+            .anchor(Corner::TopRight)
+    }
+
+    pub fn load_rule(
+        &mut self,
+        prompt_id: PromptId,
+        focus: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.store
+            .update(cx, |store, cx| store.record_opened(prompt_id, cx));
+        if focus {
+            self.show_rule_in_compact_layout = true;
+        }
+        if let Some(rule_editor) = self.rule_editors.get(&prompt_id) {
+            if focus {
+                rule_editor
+                    .body_editor
+                    .update(cx, |editor, cx| window.focus(&editor.focus_handle(cx)));
+            }
+            self.set_active_rule(Some(prompt_id), window, cx);
+        } else if let Some(rule_metadata) = self.store.read(cx).metadata(prompt_id) {
+            let language_registry = self.language_registry.clone();
+            let language = self.body_language(rule_metadata.language.clone());
+            let rule = self.store.read(cx).load(prompt_id, cx);
+            let make_completion_provider = self.make_completion_provider.clone();
+            self.pending_load = cx.spawn_in(window, async move |this, cx| {
+                let rule = rule.await;
+                let language = language.await;
+                this.update_in(cx, |this, window, cx| match rule {
+                    Ok(rule) => {
+                        let read_only =
+                            this.read_only || prompt_id.is_built_in() || rule_metadata.locked;
+                        let title_editor = cx.new(|cx| {
+                            let mut editor = Editor::single_line(window, cx);
+                            editor.set_placeholder_text("Untitled", window, cx);
+                            editor.set_text(rule_metadata.title.unwrap_or_default(), window, cx);
+                            if read_only {
                                 editor.set_read_only(true);
                                 editor.set_show_edit_predictions(Some(false), window, cx);
                             }
-                            editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
+                            editor
+                        });
+                        let settings = RulesLibrarySettings::get_global(cx);
+                        let fold_sections_on_parse = settings.fold_sections_on_load
+                            && rule.lines().count() >= settings.fold_sections_min_lines as usize;
+                        // Built-in rules can't be edited, so they're previewed by default.
+                        let preview = prompt_id
+                            .is_built_in()
+                            .then(|| this.new_rule_preview(rule.clone(), cx));
+                        let body_editor = cx.new(|cx| {
+                            let buffer = cx.new(|cx| {
+                                let mut buffer = Buffer::local(rule, cx);
+                                buffer.set_language(language, cx);
+                                buffer.set_language_registry(language_registry);
+                                buffer
+                            });
+
+                            let mut editor = Editor::for_buffer(buffer, None, window, cx);
+                            editor.set_read_only(read_only);
+                            editor.set_show_edit_predictions(
+                                body_edit_predictions(read_only, cx),
+                                window,
+                                cx,
+                            );
+                            editor.set_soft_wrap_mode(
+                                RulesLibrarySettings::get_global(cx).soft_wrap,
+                                cx,
+                            );
                             editor.set_show_gutter(false, cx);
                             editor.set_show_wrap_guides(false, cx);
                             editor.set_show_indent_guides(false, cx);
@@ -737,6 +1949,41 @@ impl RulesLibrary {
                             }
                             editor
                         });
+                        let notes_editor = cx.new(|cx| {
+                            let mut editor = Editor::auto_height(2, 8, window, cx);
+                            editor.set_placeholder_text(
+                                "Notes about this rule, never sent to the model",
+                                window,
+                                cx,
+                            );
+                            editor.set_text(rule_metadata.notes.unwrap_or_default(), window, cx);
+                            editor.set_read_only(this.read_only);
+                            editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
+                            editor.set_show_edit_predictions(Some(false), window, cx);
+                            editor
+                        });
+                        let description_editor = cx.new(|cx| {
+                            let mut editor = Editor::single_line(window, cx);
+                            editor.set_placeholder_text("Add a description", window, cx);
+                            editor.set_text(
+                                rule_metadata.description.clone().unwrap_or_default(),
+                                window,
+                                cx,
+                            );
+                            editor.set_read_only(this.read_only);
+                            editor.set_show_edit_predictions(Some(false), window, cx);
+                            editor
+                        });
+                        let tags_editor = cx.new(|cx| {
+                            let mut editor = Editor::single_line(window, cx);
+                            editor.set_placeholder_text("Tags, separated by commas", window, cx);
+                            editor.set_text(rule_metadata.tags.join(", "), window, cx);
+                            // Tags are part of the rule, unlike notes and descriptions.
+                            editor.set_read_only(read_only);
+                            editor.set_show_edit_predictions(Some(false), window, cx);
+                            editor
+                        });
+                        let cached_token_count = this.store.read(cx).token_count(prompt_id);
                         let _subscriptions = vec![
                             cx.subscribe_in(
                                 &title_editor,
@@ -756,6 +2003,29 @@ impl RulesLibrary {
                                     )
                                 },
                             ),
+                            cx.subscribe(&notes_editor, move |this, _, event: &EditorEvent, cx| {
+                                if let EditorEvent::BufferEdited = event {
+                                    this.save_rule_notes(prompt_id, cx);
+                                }
+                            }),
+                            cx.subscribe_in(
+                                &description_editor,
+                                window,
+                                move |this, _, event: &EditorEvent, window, cx| {
+                                    if let EditorEvent::BufferEdited = event {
+                                        this.save_rule_description(prompt_id, window, cx);
+                                    }
+                                },
+                            ),
+                            cx.subscribe_in(
+                                &tags_editor,
+                                window,
+                                move |this, _, event: &EditorEvent, window, cx| {
+                                    if let EditorEvent::BufferEdited = event {
+                                        this.save_rule_tags(prompt_id, window, cx);
+                                    }
+                                },
+                            ),
                         ];
                         this.rule_editors.insert(
                             prompt_id,
@@ -763,14 +2033,38 @@ impl RulesLibrary {
                                 title_editor,
                                 body_editor,
                                 next_title_and_body_to_save: None,
+                                next_save_queued_at: None,
                                 pending_save: None,
-                                token_count: None,
+                                token_count: cached_token_count,
                                 pending_token_count: Task::ready(None),
+                                cursor_token_count: None,
+                                pending_cursor_token_count: Task::ready(None),
+                                model_token_counts: ModelTokenCounts::new(),
+                                word_count: 0,
+                                char_count: 0,
+                                pending_text_count: Task::ready(()),
+                                outline: Vec::new(),
+                                pending_outline: Task::ready(()),
+                                preview,
+                                notes_editor,
+                                pending_notes_save: Task::ready(()),
+                                description_editor,
+                                pending_description_save: Task::ready(()),
+                                tags_editor,
+                                pending_tags_save: Task::ready(()),
+                                fold_sections_on_parse,
+                                title_edit_times: HashMap::default(),
+                                body_edit_times: HashMap::default(),
+                                title_before_edit: None,
                                 _subscriptions,
                             },
                         );
                         this.set_active_rule(Some(prompt_id), window, cx);
-                        this.count_tokens(prompt_id, window, cx);
+                        if cached_token_count.is_none() {
+                            this.count_tokens(prompt_id, true, window, cx);
+                        }
+                        this.count_tokens_before_cursor(prompt_id, true, window, cx);
+                        this.count_words_and_chars(prompt_id, false, cx);
                     }
                     Err(error) => {
                         // TODO: we should show the error in the UI.
@@ -789,6 +2083,12 @@ impl RulesLibrary {
         cx: &mut Context<Self>,
     ) {
         self.active_rule_id = prompt_id;
+        if let Some(prompt_id) = prompt_id {
+            self.update_outline(prompt_id, false, cx);
+        } else {
+            self.show_rule_in_compact_layout = false;
+        }
+        self.update_window_title(window, cx);
         self.picker.update(cx, |picker, cx| {
             if let Some(prompt_id) = prompt_id {
                 if picker
@@ -797,14 +2097,14 @@ impl RulesLibrary {
                     .get(picker.delegate.selected_index())
                     .is_none_or(|old_selected_prompt| {
                         if let RulePickerEntry::Rule(rule) = old_selected_prompt {
-                            rule.id != prompt_id
+                            rule.metadata.id != prompt_id
                         } else {
                             true
                         }
                     })
                     && let Some(ix) = picker.delegate.filtered_entries.iter().position(|mat| {
                         if let RulePickerEntry::Rule(rule) = mat {
-                            rule.id == prompt_id
+                            rule.metadata.id == prompt_id
                         } else {
                             false
                         }
@@ -819,6 +2119,19 @@ impl RulesLibrary {
         cx.notify();
     }
 
+    /// Includes the active rule's title in the window title, so the library can
+    /// be told apart in the window switcher.
+    fn update_window_title(&self, window: &mut Window, cx: &App) {
+        let rule_title = self
+            .active_rule_id
+            .and_then(|prompt_id| self.store.read(cx).metadata(prompt_id)?.title)
+            .filter(|title| !title.trim().is_empty());
+        match rule_title {
+            Some(rule_title) => window.set_window_title(&format!("Rules Library — {rule_title}")),
+            None => window.set_window_title("Rules Library"),
+        }
+    }
+
     pub fn delete_rule(
         &mut self,
         prompt_id: PromptId,
@@ -840,17 +2153,9 @@ impl RulesLibrary {
             cx.spawn_in(window, async move |this, cx| {
                 if confirmation.await.ok() == Some(0) {
                     this.update_in(cx, |this, window, cx| {
-                        if this.active_rule_id == Some(prompt_id) {
-                            this.set_active_rule(None, window, cx);
-                        }
-                        this.rule_editors.remove(&prompt_id);
-                        this.store
-                            .update(cx, |store, cx| store.delete(prompt_id, cx))
-                            .detach_and_log_err(cx);
-                        this.picker
-                            .update(cx, |picker, cx| picker.refresh(window, cx));
-                        cx.notify();
-                    })?;
+                        this.delete_rule_without_confirmation(prompt_id, window, cx)
+                    })?
+                    .await?;
                 }
                 anyhow::Ok(())
             })
@@ -858,252 +2163,1698 @@ impl RulesLibrary {
         }
     }
 
-    pub fn duplicate_rule(
+    /// Deletes the rule right away, resolving once the deletion has been persisted.
+    pub fn delete_rule_without_confirmation(
         &mut self,
         prompt_id: PromptId,
         window: &mut Window,
         cx: &mut Context<Self>,
-    ) {
-        if let Some(rule) = self.rule_editors.get(&prompt_id) {
-            const DUPLICATE_SUFFIX: &str = " copy";
-            let title_to_duplicate = rule.title_editor.read(cx).text(cx);
-            let existing_titles = self
-                .rule_editors
-                .iter()
-                .filter(|&(&id, _)| id != prompt_id)
-                .map(|(_, rule_editor)| rule_editor.title_editor.read(cx).text(cx))
-                .filter(|title| title.starts_with(&title_to_duplicate))
-                .collect::<HashSet<_>>();
-
-            let title = if existing_titles.is_empty() {
-                title_to_duplicate + DUPLICATE_SUFFIX
-            } else {
-                let mut i = 1;
-                loop {
-                    let new_title = format!("{title_to_duplicate}{DUPLICATE_SUFFIX} {i}");
-                    if !existing_titles.contains(&new_title) {
-                        break new_title;
-                    }
-                    i += 1;
-                }
-            };
-
-            let new_id = PromptId::new();
-            let body = rule.body_editor.read(cx).text(cx);
-            let save = self.store.update(cx, |store, cx| {
-                store.save(new_id, Some(title.into()), false, body.into(), cx)
-            });
-            self.picker
-                .update(cx, |picker, cx| picker.refresh(window, cx));
-            cx.spawn_in(window, async move |this, cx| {
-                save.await?;
-                this.update_in(cx, |rules_library, window, cx| {
-                    rules_library.load_rule(new_id, true, window, cx)
-                })
-            })
-            .detach_and_log_err(cx);
+    ) -> Task<Result<()>> {
+        if self.active_rule_id == Some(prompt_id) {
+            self.set_active_rule(None, window, cx);
         }
+        self.rule_editors.remove(&prompt_id);
+        let delete = self
+            .store
+            .update(cx, |store, cx| store.delete(prompt_id, cx));
+        self.picker
+            .update(cx, |picker, cx| picker.refresh(window, cx));
+        cx.notify();
+        cx.spawn(async move |this, cx| {
+            if delete.await? {
+                this.update(cx, |this, cx| this.offer_undo_delete(vec![prompt_id], cx))?;
+            }
+            Ok(())
+        })
     }
 
-    fn focus_active_rule(&mut self, _: &Tab, window: &mut Window, cx: &mut Context<Self>) {
-        if let Some(active_rule) = self.active_rule_id {
-            self.rule_editors[&active_rule]
-                .body_editor
-                .update(cx, |editor, cx| window.focus(&editor.focus_handle(cx)));
+    /// Offers to undo the deletion of the rules. Rules deleted while an
+    /// earlier deletion can still be undone are added to it, so that undoing
+    /// restores both.
+    fn offer_undo_delete(&mut self, mut prompt_ids: Vec<PromptId>, cx: &mut Context<Self>) {
+        if prompt_ids.is_empty() {
+            return;
+        }
+        if let Some(deleted_rules) = self.deleted_rules.take() {
+            prompt_ids.extend(deleted_rules.prompt_ids);
+        }
+        self.deleted_rules = Some(DeletedRules {
+            prompt_ids,
+            _dismiss: cx.spawn(async move |this, cx| {
+                cx.background_executor().timer(UNDO_DELETE_TIMEOUT).await;
+                this.update(cx, |this, cx| {
+                    this.deleted_rules = None;
+                    cx.notify();
+                })
+                .ok();
+            }),
+        });
+        cx.notify();
+    }
+
+    /// Restores the rules deleted last, if their deletion can still be undone.
+    pub fn undo_delete(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(deleted_rules) = self.deleted_rules.take() else {
+            return;
+        };
+        let restore = self.store.update(cx, |store, cx| {
+            store.restore_many(deleted_rules.prompt_ids.clone(), cx)
+        });
+        cx.notify();
+        cx.spawn_in(window, async move |this, cx| {
+            let result = restore.await;
+            this.update_in(cx, |this, window, cx| {
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                if let Some(prompt_id) = deleted_rules.prompt_ids.first() {
+                    this.load_rule(*prompt_id, true, window, cx);
+                }
+            })?;
+            result
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn delete_empty_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let empty_rules = self.store.read(cx).empty_prompt_ids(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let mut prompt_ids = empty_rules.await?;
+            let confirmation = this.update_in(cx, |this, window, cx| {
+                // Rules that are open may have edits that haven't been saved yet.
+                prompt_ids.retain(|prompt_id| {
+                    this.rule_editors.get(prompt_id).is_none_or(|rule_editor| {
+                        rule_editor.title_editor.read(cx).text(cx).trim().is_empty()
+                            && rule_editor.body_editor.read(cx).text(cx).trim().is_empty()
+                    })
+                });
+                if prompt_ids.is_empty() {
+                    return None;
+                }
+                Some(window.prompt(
+                    PromptLevel::Warning,
+                    &format!(
+                        "Are you sure you want to delete {} empty {}?",
+                        prompt_ids.len(),
+                        if prompt_ids.len() == 1 {
+                            "rule"
+                        } else {
+                            "rules"
+                        }
+                    ),
+                    None,
+                    &["Delete", "Cancel"],
+                    cx,
+                ))
+            })?;
+            let Some(confirmation) = confirmation else {
+                return Ok(());
+            };
+            if confirmation.await.ok() != Some(0) {
+                return Ok(());
+            }
+
+            this.update_in(cx, |this, window, cx| {
+                this.delete_rules_without_confirmation(prompt_ids, window, cx)
+            })?
+            .await
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Deletes several rules after a single confirmation.
+    pub fn delete_rules(
+        &mut self,
+        prompt_ids: Vec<PromptId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let confirmation = window.prompt(
+            PromptLevel::Warning,
+            &format!(
+                "Are you sure you want to delete {} rules?",
+                prompt_ids.len()
+            ),
+            None,
+            &["Delete", "Cancel"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            if confirmation.await.ok() == Some(0) {
+                this.update_in(cx, |this, window, cx| {
+                    this.delete_rules_without_confirmation(prompt_ids, window, cx)
+                })?
+                .await?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Deletes the rules in a single transaction, so that either all of them
+    /// are deleted or none are. The rule list is refreshed once they're gone.
+    pub fn delete_rules_without_confirmation(
+        &mut self,
+        prompt_ids: Vec<PromptId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        if self
+            .active_rule_id
+            .is_some_and(|active_rule_id| prompt_ids.contains(&active_rule_id))
+        {
+            self.set_active_rule(None, window, cx);
+        }
+        for prompt_id in &prompt_ids {
+            self.rule_editors.remove(prompt_id);
+        }
+        self.deleting_rule_count = Some(prompt_ids.len());
+        cx.notify();
+
+        let delete = self
+            .store
+            .update(cx, |store, cx| store.delete_many(prompt_ids, cx));
+        cx.spawn_in(window, async move |this, cx| {
+            let trashed_ids = delete.await;
+            this.update_in(cx, |this, window, cx| {
+                this.deleting_rule_count = None;
+                this.clear_checked_rules(window, cx);
+                cx.notify();
+            })?;
+            let trashed_ids = trashed_ids?;
+            this.update(cx, |this, cx| this.offer_undo_delete(trashed_ids, cx))
+        })
+    }
+
+    pub fn duplicate_rule(
+        &mut self,
+        prompt_id: PromptId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.rule_editors.contains_key(&prompt_id) {
+            self.create_duplicate_rule(prompt_id, window, cx)
+                .detach_and_log_err(cx);
+        }
+    }
+
+    /// Like [`Self::duplicate_rule`], but resolves to the duplicate's id once
+    /// it has been persisted and loaded. Fails if the rule isn't open.
+    pub fn create_duplicate_rule(
+        &mut self,
+        prompt_id: PromptId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<PromptId>> {
+        if let Some(rule) = self.rule_editors.get(&prompt_id) {
+            const DUPLICATE_SUFFIX: &str = " copy";
+            let title_to_duplicate = rule.title_editor.read(cx).text(cx);
+            let existing_titles = self
+                .rule_editors
+                .iter()
+                .filter(|&(&id, _)| id != prompt_id)
+                .map(|(_, rule_editor)| rule_editor.title_editor.read(cx).text(cx))
+                .filter(|title| title.starts_with(&title_to_duplicate))
+                .collect::<HashSet<_>>();
+
+            let title = if existing_titles.is_empty() {
+                title_to_duplicate + DUPLICATE_SUFFIX
+            } else {
+                let mut i = 1;
+                loop {
+                    let new_title = format!("{title_to_duplicate}{DUPLICATE_SUFFIX} {i}");
+                    if !existing_titles.contains(&new_title) {
+                        break new_title;
+                    }
+                    i += 1;
+                }
+            };
+
+            let new_id = PromptId::new();
+            let body = rule.body_editor.read(cx).text(cx);
+            let save = self.store.update(cx, |store, cx| {
+                store.save(new_id, Some(title.into()), false, body.into(), cx)
+            });
+            self.picker
+                .update(cx, |picker, cx| picker.refresh(window, cx));
+            cx.spawn_in(window, async move |this, cx| {
+                save.await?;
+                this.update_in(cx, |rules_library, window, cx| {
+                    rules_library.load_rule(new_id, true, window, cx)
+                })?;
+                Ok(new_id)
+            })
+        } else {
+            Task::ready(Err(anyhow!("rule {prompt_id:?} isn't open")))
+        }
+    }
+
+    /// The checked rules that the list shows, in the list's order, or the
+    /// active rule if none are checked.
+    fn selected_rule_ids(&self, cx: &App) -> Vec<PromptId> {
+        let delegate = &self.picker.read(cx).delegate;
+        if delegate.checked_rule_ids.is_empty() {
+            return self.active_rule_id.into_iter().collect();
+        }
+        // Rules that were checked and then filtered out of the list stay
+        // checked, but batch actions only apply to the ones that are shown.
+        delegate
+            .filtered_entries
+            .iter()
+            .filter_map(|entry| match entry {
+                RulePickerEntry::Rule(rule) => Some(rule.metadata.id),
+                _ => None,
+            })
+            .filter(|id| delegate.checked_rule_ids.contains(id))
+            .collect()
+    }
+
+    /// Unchecks every rule and refreshes the list, once a batch action is done.
+    fn clear_checked_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.picker.update(cx, |picker, cx| {
+            picker.delegate.checked_rule_ids.clear();
+            picker.refresh(window, cx);
+        });
+    }
+
+    /// Makes every selected rule a default rule, or removes them all from the
+    /// default rules if they already are. Archived rules are skipped, since
+    /// they can't be default rules.
+    pub fn toggle_default_for_selected_rules(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let rules = self
+            .selected_rule_ids(cx)
+            .into_iter()
+            .filter_map(|id| self.store.read(cx).metadata(id))
+            .filter(|metadata| !metadata.archived)
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            return;
+        }
+        let default = !rules.iter().all(|metadata| metadata.default);
+        let saves = self.store.update(cx, |store, cx| {
+            rules
+                .into_iter()
+                .filter(|metadata| metadata.default != default)
+                .map(|metadata| store.save_metadata(metadata.id, metadata.title, default, cx))
+                .collect::<Vec<_>>()
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let result = futures::future::try_join_all(saves).await;
+            this.update_in(cx, |this, window, cx| this.clear_checked_rules(window, cx))?;
+            result.map(|_| ())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn copy_selected_rules(&mut self, cx: &mut Context<Self>) {
+        let rule_ids = self.selected_rule_ids(cx);
+        if rule_ids.is_empty() {
+            return;
+        }
+
+        let bundle = self.store.read(cx).bundle(&rule_ids, cx);
+        cx.spawn(async move |_, cx| {
+            let text = bundle.await?.to_json()?;
+            cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new_string(text)))
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Copies the active rule's body as it is. Works for built-in and locked
+    /// rules too, since it doesn't change them.
+    pub fn copy_active_rule(&mut self, cx: &mut Context<Self>) {
+        let Some(rule_editor) = self
+            .active_rule_id
+            .and_then(|rule_id| self.rule_editors.get(&rule_id))
+        else {
+            return;
+        };
+
+        let text = rule_editor.body_editor.read(cx).text(cx);
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+    }
+
+    pub fn copy_active_rule_wrapped(&mut self, cx: &mut Context<Self>) {
+        let Some(rule_editor) = self
+            .active_rule_id
+            .and_then(|rule_id| self.rule_editors.get(&rule_id))
+        else {
+            return;
+        };
+
+        let text = rule_editor.body_editor.update(cx, |editor, cx| {
+            wrapped_text(&editor.text(cx), &editor.display_snapshot(cx))
+        });
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+    }
+
+    pub fn paste_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+            return;
+        };
+        let Some(bundle) = PromptBundle::from_json(&text).log_err() else {
+            return;
+        };
+
+        let insert = self
+            .store
+            .update(cx, |store, cx| store.insert_bundle(bundle, cx));
+        self.picker
+            .update(cx, |picker, cx| picker.refresh(window, cx));
+        cx.spawn_in(window, async move |this, cx| {
+            let rule_ids = insert.await?;
+            this.update_in(cx, |this, window, cx| {
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                if let Some(rule_id) = rule_ids.first() {
+                    this.load_rule(*rule_id, true, window, cx);
+                }
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn import_rules_from_directory(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Import".into()),
+        });
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let Some(dir) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            this.update(cx, |this, _| this.importing_rules = true)?;
+            let import = store
+                .update(cx, |store, cx| {
+                    store.import_plain_text_dir(dir, SubdirectoryImport::IncludeAsTags, fs, cx)
+                })?
+                .await;
+            this.update(cx, |this, _| this.importing_rules = false)?;
+            let import = import?;
+            this.update_in(cx, |this, window, cx| {
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                if let Some(rule_id) = import.prompt_ids.first() {
+                    this.load_rule(*rule_id, true, window, cx);
+                }
+                if !import.skipped_paths.is_empty() {
+                    let skipped_paths = import
+                        .skipped_paths
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    drop(window.prompt(
+                        PromptLevel::Warning,
+                        &format!(
+                            "Imported {} rules. {} files couldn't be read as text and were skipped.",
+                            import.prompt_ids.len(),
+                            import.skipped_paths.len()
+                        ),
+                        Some(&skipped_paths),
+                        &["Ok"],
+                        cx,
+                    ));
+                }
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Exports every rule, or only the rules with the search query's `tag:`
+    /// filters when it has any.
+    pub fn export_rules_to_directory(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let (_, tags) = parse_tag_filters(&self.picker.read(cx).query(cx));
+        let exported_rules = if tags.is_empty() {
+            "rules".to_string()
+        } else {
+            format!("rules tagged {}", tags.join(", "))
+        };
+        let tags = tags.into_iter().map(SharedString::from).collect::<Vec<_>>();
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Export".into()),
+        });
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let Some(dir) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            let exported_count = store
+                .read_with(cx, |store, cx| {
+                    store.export_to_dir(dir.clone(), tags, fs, cx)
+                })?
+                .await?;
+            this.update_in(cx, |_, window, cx| {
+                drop(window.prompt(
+                    PromptLevel::Info,
+                    &format!(
+                        "Exported {exported_count} {exported_rules} to {}",
+                        dir.display()
+                    ),
+                    None,
+                    &["Ok"],
+                    cx,
+                ));
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn backup_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let directory = util::paths::home_dir().to_path_buf();
+        let path = cx.prompt_for_new_path(&directory, Some("rules-backup.json"));
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let Some(path) = path.await?? else {
+                return Ok(());
+            };
+            let result = store
+                .read_with(cx, |store, cx| store.backup(path.clone(), fs, cx))?
+                .await;
+            this.update_in(cx, |_, window, cx| match &result {
+                Ok(()) => drop(window.prompt(
+                    PromptLevel::Info,
+                    &format!("Backed up rules to {}", path.display()),
+                    None,
+                    &["Ok"],
+                    cx,
+                )),
+                Err(error) => drop(window.prompt(
+                    PromptLevel::Critical,
+                    &format!("Failed to back up rules to {}", path.display()),
+                    Some(&format!("{error:#}")),
+                    &["Ok"],
+                    cx,
+                )),
+            })?;
+            result
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn restore_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Restore".into()),
+        });
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let Some(path) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            let rule_ids = store
+                .update(cx, |store, cx| store.restore_archive(path, fs, cx))?
+                .await?;
+            this.update_in(cx, |this, window, cx| {
+                // Open editors would otherwise keep showing, and saving, the bodies
+                // that were just replaced.
+                for rule_id in &rule_ids {
+                    this.rule_editors.remove(rule_id);
+                }
+                if let Some(active_rule_id) = this.active_rule_id
+                    && rule_ids.contains(&active_rule_id)
+                {
+                    this.active_rule_id = None;
+                    this.load_rule(active_rule_id, false, window, cx);
+                }
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                drop(window.prompt(
+                    PromptLevel::Info,
+                    &format!("Restored {} rules", rule_ids.len()),
+                    None,
+                    &["Ok"],
+                    cx,
+                ));
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn save_active_rule_to_file(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(prompt_id) = self.active_rule_id else {
+            return;
+        };
+        let Some(rule_editor) = self.rule_editors.get(&prompt_id) else {
+            return;
+        };
+        let Some(rule_metadata) = self.store.read(cx).metadata(prompt_id) else {
+            return;
+        };
+        let body = rule_editor.body_editor.read(cx).text(cx);
+        let title = rule_metadata.title.unwrap_or_else(|| "Untitled".into());
+        let file_name = format!("{}.md", file_stem_for_title(&title));
+        let directory = rule_metadata
+            .linked_path
+            .as_deref()
+            .and_then(|path| path.parent())
+            .unwrap_or(util::paths::home_dir())
+            .to_path_buf();
+        let path = cx.prompt_for_new_path(&directory, Some(&file_name));
+        // The library copy can only be linked or deleted if the library can be edited.
+        let can_modify = !self.read_only && !prompt_id.is_built_in();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, async move |this, cx| {
+            let Some(path) = path.await?? else {
+                return Ok(());
+            };
+            fs.atomic_write(path.clone(), body).await?;
+
+            let answers: &[&str] = if can_modify {
+                &["Keep and Link", "Keep", "Delete from Library"]
+            } else {
+                &["Ok"]
+            };
+            let answer = this.update_in(cx, |_, window, cx| {
+                window.prompt(
+                    PromptLevel::Info,
+                    &format!("Saved “{title}” to {}", path.display()),
+                    can_modify.then_some(
+                        "Keep the rule in the library, optionally linked to the file, or delete it.",
+                    ),
+                    answers,
+                    cx,
+                )
+            })?;
+            match answer.await {
+                Ok(0) if can_modify => {
+                    this.update(cx, |this, cx| {
+                        this.store
+                            .update(cx, |store, cx| store.set_linked_path(prompt_id, Some(path), cx))
+                    })?
+                    .await
+                }
+                Ok(2) if can_modify => {
+                    this.update_in(cx, |this, window, cx| {
+                        this.delete_rule_without_confirmation(prompt_id, window, cx)
+                    })?
+                    .await
+                }
+                _ => Ok(()),
+            }
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn focus_active_rule(&mut self, _: &Tab, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(active_rule) = self.active_rule_id {
+            self.rule_editors[&active_rule]
+                .body_editor
+                .update(cx, |editor, cx| window.focus(&editor.focus_handle(cx)));
             cx.stop_propagation();
         }
     }
 
-    fn focus_picker(&mut self, _: &menu::Cancel, window: &mut Window, cx: &mut Context<Self>) {
-        self.picker
-            .update(cx, |picker, cx| picker.focus(window, cx));
+    /// Switches the compact layout back to the rule list, keeping the active rule selected.
+    fn show_rule_list(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_rule_in_compact_layout = false;
+        self.picker
+            .update(cx, |picker, cx| picker.focus(window, cx));
+        cx.notify();
+    }
+
+    fn render_compact_rule_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        h_flex()
+            .h_9()
+            .px_1p5()
+            .flex_none()
+            .border_l_1()
+            .border_b_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().editor_background)
+            .child(
+                Button::new("back-to-rule-list", "Rules")
+                    .icon(IconName::ArrowLeft)
+                    .icon_position(IconPosition::Start)
+                    .icon_size(IconSize::Small)
+                    .icon_color(Color::Muted)
+                    .tooltip(Tooltip::text("Back to Rule List"))
+                    .on_click(cx.listener(|this, _, window, cx| this.show_rule_list(window, cx))),
+            )
+    }
+
+    fn focus_picker(&mut self, _: &menu::Cancel, window: &mut Window, cx: &mut Context<Self>) {
+        self.picker
+            .update(cx, |picker, cx| picker.focus(window, cx));
+    }
+
+    pub fn focus_search(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_rule_in_compact_layout = false;
+        self.picker
+            .update(cx, |picker, cx| picker.focus_and_select_query(window, cx));
+        cx.notify();
+    }
+
+    pub fn inline_assist(
+        &mut self,
+        action: &InlineAssist,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(active_rule_id) = self.active_rule_id else {
+            cx.propagate();
+            return;
+        };
+        if self.read_only {
+            return;
+        }
+
+        let rule_editor = &self.rule_editors[&active_rule_id].body_editor;
+        let Some(ConfiguredModel { provider, .. }) =
+            LanguageModelRegistry::read_global(cx).inline_assistant_model()
+        else {
+            return;
+        };
+
+        let initial_prompt = action.prompt.clone();
+        if provider.is_authenticated(cx) {
+            self.inline_assist_delegate
+                .assist(rule_editor, initial_prompt, window, cx);
+        } else {
+            for window in cx.windows() {
+                if let Some(workspace) = window.downcast::<Workspace>() {
+                    let panel = workspace
+                        .update(cx, |workspace, window, cx| {
+                            window.activate_window();
+                            self.inline_assist_delegate
+                                .focus_agent_panel(workspace, window, cx)
+                        })
+                        .ok();
+                    if panel == Some(true) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn move_down_from_title(
+        &mut self,
+        _: &editor::actions::MoveDown,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(rule_id) = self.active_rule_id
+            && let Some(rule_editor) = self.rule_editors.get(&rule_id)
+        {
+            window.focus(&rule_editor.body_editor.focus_handle(cx));
+        }
+    }
+
+    fn move_up_from_body(
+        &mut self,
+        _: &editor::actions::MoveUp,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(rule_id) = self.active_rule_id
+            && let Some(rule_editor) = self.rule_editors.get(&rule_id)
+        {
+            window.focus(&rule_editor.title_editor.focus_handle(cx));
+        }
+    }
+
+    fn handle_rule_title_editor_event(
+        &mut self,
+        prompt_id: PromptId,
+        title_editor: &Entity<Editor>,
+        event: &EditorEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            EditorEvent::BufferEdited => {
+                if let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) {
+                    record_edit_time(title_editor, &mut rule_editor.title_edit_times, cx);
+                }
+                self.save_rule(prompt_id, window, cx);
+                self.count_tokens(prompt_id, true, window, cx);
+            }
+            EditorEvent::Focused => {
+                let title = self
+                    .store
+                    .read(cx)
+                    .metadata(prompt_id)
+                    .and_then(|metadata| metadata.title);
+                if let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) {
+                    rule_editor.title_before_edit = title;
+                }
+            }
+            EditorEvent::Blurred => {
+                self.flush_deferred_picker_refresh(window, cx);
+                self.offer_to_update_includes(prompt_id, window, cx);
+                title_editor.update(cx, |title_editor, cx| {
+                    title_editor.change_selections(
+                        SelectionEffects::no_scroll(),
+                        window,
+                        cx,
+                        |selections| {
+                            let cursor = selections.oldest_anchor().head();
+                            selections.select_anchor_ranges([cursor..cursor]);
+                        },
+                    );
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// After a rule was renamed, offers to point `@include(...)` references to
+    /// its previous title at the new one. References by id keep working on
+    /// their own.
+    fn offer_to_update_includes(
+        &mut self,
+        prompt_id: PromptId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let Some(old_title) = rule_editor.title_before_edit.take() else {
+            return;
+        };
+        let new_title = rule_editor.title_editor.read(cx).text(cx);
+        let new_title = new_title.trim();
+        if new_title.is_empty() || new_title == old_title.trim() {
+            return;
+        }
+        let new_title = SharedString::from(new_title.to_string());
+
+        let including = self
+            .store
+            .read(cx)
+            .prompts_including_title(old_title.clone(), cx);
+        let store = self.store.clone();
+        cx.spawn_in(window, async move |this, cx| {
+            let including = including.await?;
+            if including.is_empty() {
+                return Ok(());
+            }
+            let answer = this.update_in(cx, |_, window, cx| {
+                let message = match including.len() {
+                    1 => format!("Update the rule that includes “{old_title}”?"),
+                    count => format!("Update the {count} rules that include “{old_title}”?"),
+                };
+                window.prompt(
+                    PromptLevel::Info,
+                    &message,
+                    Some(&format!(
+                        "Their @include({old_title}) will become @include({new_title})."
+                    )),
+                    &["Update References", "Leave Unchanged"],
+                    cx,
+                )
+            })?;
+            if answer.await != Ok(0) {
+                return Ok(());
+            }
+
+            let updated_ids = store
+                .update(cx, |store, cx| {
+                    store.rename_updating_includes(prompt_id, Some(new_title), Some(old_title), cx)
+                })?
+                .await?;
+            this.update_in(cx, |this, window, cx| {
+                // Drop the editors of updated rules so they're reloaded with the
+                // new bodies rather than saving over them.
+                for id in &updated_ids {
+                    this.rule_editors.remove(id);
+                }
+                if let Some(active_rule_id) = this.active_rule_id
+                    && updated_ids.contains(&active_rule_id)
+                {
+                    this.active_rule_id = None;
+                    this.load_rule(active_rule_id, false, window, cx);
+                }
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn handle_rule_body_editor_event(
+        &mut self,
+        prompt_id: PromptId,
+        body_editor: &Entity<Editor>,
+        event: &EditorEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            EditorEvent::BufferEdited => {
+                if let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) {
+                    record_edit_time(body_editor, &mut rule_editor.body_edit_times, cx);
+                    rule_editor.model_token_counts.invalidate();
+                    if let Some(preview) = &rule_editor.preview {
+                        let body = body_editor.read(cx).text(cx);
+                        preview.update(cx, |preview, cx| preview.replace(body, cx));
+                    }
+                }
+                self.save_rule(prompt_id, window, cx);
+                self.count_tokens(prompt_id, true, window, cx);
+                self.count_tokens_before_cursor(prompt_id, true, window, cx);
+                self.count_words_and_chars(prompt_id, true, cx);
+                // Keep the counts that are being shown up to date.
+                if self.show_model_token_counts {
+                    self.count_tokens_for_all_models(prompt_id, true, window, cx);
+                }
+            }
+            EditorEvent::SelectionsChanged { local: true } => {
+                self.count_tokens_before_cursor(prompt_id, true, window, cx);
+                self.open_clicked_include(body_editor, window, cx);
+            }
+            EditorEvent::Reparsed(_) => {
+                self.update_outline(prompt_id, true, cx);
+                if let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id)
+                    && std::mem::take(&mut rule_editor.fold_sections_on_parse)
+                {
+                    body_editor.update(cx, |editor, cx| {
+                        let Some(buffer) = editor.buffer().read(cx).as_singleton() else {
+                            return;
+                        };
+                        let folds = section_fold_ranges(&buffer.read(cx).snapshot());
+                        editor.fold_ranges(folds, false, window, cx);
+                    });
+                }
+            }
+            EditorEvent::Blurred => {
+                self.flush_deferred_picker_refresh(window, cx);
+                body_editor.update(cx, |body_editor, cx| {
+                    body_editor.change_selections(
+                        SelectionEffects::no_scroll(),
+                        window,
+                        cx,
+                        |selections| {
+                            let cursor = selections.oldest_anchor().head();
+                            selections.select_anchor_ranges([cursor..cursor]);
+                        },
+                    );
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_include_click_mouse_down(
+        &mut self,
+        event: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.include_click_pending =
+            event.button == MouseButton::Left && event.modifiers.secondary();
+        if self.unresolved_include.take().is_some() {
+            cx.notify();
+        }
+    }
+
+    /// Forgets a cmd-click that didn't move the cursor, so the next selection
+    /// made with the keyboard doesn't open an include. Clicks that do move it
+    /// are handled on mouse down, and the editor only stops a mouse up from
+    /// reaching this when it moves the cursor itself.
+    fn handle_include_click_mouse_up(
+        &mut self,
+        _: &MouseUpEvent,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+        self.include_click_pending = false;
+    }
+
+    /// Opens the rule referred to by the `@include(...)` that was just
+    /// cmd-clicked in `editor`, if any. Clicks anywhere else, including on
+    /// markdown links, are left to the editor.
+    fn open_clicked_include(
+        &mut self,
+        editor: &Entity<Editor>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !std::mem::take(&mut self.include_click_pending) {
+            return;
+        }
+
+        let editor = editor.read(cx);
+        let cursor = editor.selections.newest_anchor().head();
+        let Some(buffer) = editor.buffer().read(cx).as_singleton() else {
+            return;
+        };
+        let buffer = buffer.read(cx);
+        let offset = cursor.text_anchor.to_offset(buffer);
+        let text = buffer.text();
+        let Some(reference) = parse_includes(&text)
+            .into_iter()
+            .find(|reference| reference.range.contains(&offset))
+        else {
+            return;
+        };
+
+        let metadata = self.store.read(cx).all_prompt_metadata();
+        if let Some(prompt_id) = reference.target.resolve(&metadata) {
+            self.default_rules_preview = None;
+            self.load_rule(prompt_id, true, window, cx);
+        } else {
+            self.unresolved_include =
+                Some(format!("{} doesn't match any rule", &text[reference.range.clone()]).into());
+            cx.notify();
+        }
+    }
+
+    fn render_unresolved_include(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let message = self.unresolved_include.clone()?;
+        Some(
+            h_flex()
+                .absolute()
+                .bottom_2()
+                .right_2()
+                .gap_1()
+                .px_2()
+                .py_1()
+                .rounded_sm()
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().elevated_surface_background)
+                .child(
+                    Icon::new(IconName::Warning)
+                        .size(IconSize::Small)
+                        .color(Color::Warning),
+                )
+                .child(Label::new(message).size(LabelSize::Small)),
+        )
+    }
+
+    /// Undoes or redoes the title and body together when the change about to be
+    /// undone in the focused editor was made alongside a change to the other one.
+    /// Otherwise the action propagates to the focused editor as usual.
+    fn undo_or_redo_linked_edit(
+        &mut self,
+        redo: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(rule_editor) = self
+            .active_rule_id
+            .and_then(|prompt_id| self.rule_editors.get(&prompt_id))
+        else {
+            return;
+        };
+        let title_editor = rule_editor.title_editor.clone();
+        let body_editor = rule_editor.body_editor.clone();
+        if ![&title_editor, &body_editor]
+            .into_iter()
+            .any(|editor| editor.read(cx).focus_handle(cx).is_focused(window))
+        {
+            return;
+        }
+
+        let title_edited_at = next_transaction(&title_editor, redo, cx)
+            .and_then(|transaction_id| rule_editor.title_edit_times.get(&transaction_id));
+        let body_edited_at = next_transaction(&body_editor, redo, cx)
+            .and_then(|transaction_id| rule_editor.body_edit_times.get(&transaction_id));
+        let (Some(&title_edited_at), Some(&body_edited_at)) = (title_edited_at, body_edited_at)
+        else {
+            return;
+        };
+        let elapsed = title_edited_at
+            .max(body_edited_at)
+            .duration_since(title_edited_at.min(body_edited_at));
+        if elapsed > LINKED_EDIT_WINDOW {
+            return;
+        }
+
+        for editor in [title_editor, body_editor] {
+            editor.update(cx, |editor, cx| {
+                if redo {
+                    editor.redo(&Redo, window, cx);
+                } else {
+                    editor.undo(&Undo, window, cx);
+                }
+            });
+        }
+        cx.stop_propagation();
+    }
+
+    pub fn toggle_default_rules_preview(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.default_rules_preview.take().is_none() {
+            self.library_summary = None;
+            let editor = cx.new(|cx| {
+                let mut editor = Editor::multi_line(window, cx);
+                editor.set_read_only(true);
+                editor.set_show_edit_predictions(Some(false), window, cx);
+                editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
+                editor.set_show_gutter(false, cx);
+                editor.set_show_wrap_guides(false, cx);
+                editor.set_show_indent_guides(false, cx);
+                editor.set_current_line_highlight(Some(CurrentLineHighlight::None));
+                editor
+            });
+            let _editor_subscription =
+                cx.subscribe_in(&editor, window, |this, editor, event, window, cx| {
+                    if let EditorEvent::SelectionsChanged { local: true } = event {
+                        this.open_clicked_include(editor, window, cx);
+                    }
+                });
+            self.default_rules_preview = Some(DefaultRulesPreview {
+                editor,
+                token_count: None,
+                pending_refresh: Task::ready(None),
+                _editor_subscription,
+            });
+            self.refresh_default_rules_preview(window, cx);
+        }
+        cx.notify();
     }
 
-    pub fn inline_assist(
+    fn refresh_default_rules_preview(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.default_rules_preview.is_none() {
+            return;
+        }
+        let rules = self.store.read(cx).default_prompts_with_bodies(cx);
+        let model = LanguageModelRegistry::read_global(cx)
+            .default_model()
+            .map(|configured| configured.model)
+            .filter(|_| self.show_token_count);
+        let Some(preview) = self.default_rules_preview.as_mut() else {
+            return;
+        };
+        preview.pending_refresh = cx.spawn_in(window, async move |this, cx| {
+            async move {
+                let rules = rules.await?;
+                let text = default_rules_preview_text(&rules);
+                this.update_in(cx, |this, window, cx| {
+                    if let Some(preview) = &mut this.default_rules_preview {
+                        preview.token_count = None;
+                        preview
+                            .editor
+                            .update(cx, |editor, cx| editor.set_text(text, window, cx));
+                        cx.notify();
+                    }
+                })?;
+
+                let Some(model) = model else {
+                    return Ok(());
+                };
+                let body = rules
+                    .iter()
+                    .map(|(_, body)| body.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                let token_count = cx
+                    .update(|_, cx| model.count_tokens(token_count_request(body), cx))?
+                    .await?;
+                this.update(cx, |this, cx| {
+                    if let Some(preview) = &mut this.default_rules_preview {
+                        preview.token_count = Some(token_count);
+                        cx.notify();
+                    }
+                })
+            }
+            .log_err()
+            .await
+        });
+    }
+
+    fn render_default_rules_preview(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let preview = self.default_rules_preview.as_ref()?;
+        let preview = v_flex()
+            .id("default-rules-preview")
+            .h_full()
+            .flex_1()
+            .border_l_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().editor_background)
+            .child(
+                h_flex()
+                    .py_2()
+                    .px_2p5()
+                    .gap_2()
+                    .justify_between()
+                    .child(Headline::new("Default Rules").size(HeadlineSize::Small))
+                    .child(
+                        h_flex()
+                            .gap_1()
+                            .children(preview.token_count.map(|token_count| {
+                                Label::new(format!("{token_count} tokens")).color(Color::Muted)
+                            }))
+                            .child(
+                                IconButton::new("close-default-rules-preview", IconName::Close)
+                                    .tooltip(move |_window, cx| {
+                                        Tooltip::for_action(
+                                            "Close Preview",
+                                            &PreviewDefaultRules,
+                                            cx,
+                                        )
+                                    })
+                                    .on_click(|_, window, cx| {
+                                        window.dispatch_action(Box::new(PreviewDefaultRules), cx);
+                                    }),
+                            ),
+                    ),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .px_2p5()
+                    .py_2()
+                    .relative()
+                    .capture_any_mouse_down(cx.listener(Self::handle_include_click_mouse_down))
+                    .on_mouse_up(
+                        MouseButton::Left,
+                        cx.listener(Self::handle_include_click_mouse_up),
+                    )
+                    .on_mouse_up_out(
+                        MouseButton::Left,
+                        cx.listener(Self::handle_include_click_mouse_up),
+                    )
+                    .child(preview.editor.clone())
+                    .children(self.render_unresolved_include(cx)),
+            );
+        Some(preview)
+    }
+
+    fn render_library_summary_button(&self) -> IconButton {
+        IconButton::new("library-summary", IconName::Info)
+            .toggle_state(self.library_summary.is_some())
+            .tooltip(move |_window, cx| {
+                Tooltip::for_action("Library Summary", &ShowLibrarySummary, cx)
+            })
+            .on_click(|_, window, cx| {
+                window.dispatch_action(Box::new(ShowLibrarySummary), cx);
+            })
+    }
+
+    fn render_preview_default_rules_button(&self) -> IconButton {
+        IconButton::new("preview-default-rules", IconName::Eye)
+            .toggle_state(self.default_rules_preview.is_some())
+            .tooltip(move |_window, cx| {
+                Tooltip::for_action("Preview Default Rules", &PreviewDefaultRules, cx)
+            })
+            .on_click(|_, window, cx| {
+                window.dispatch_action(Box::new(PreviewDefaultRules), cx);
+            })
+    }
+
+    pub fn toggle_token_count(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_token_count = !self.show_token_count;
+        if self.show_token_count {
+            if let Some(prompt_id) = self.active_rule_id {
+                self.count_tokens(prompt_id, false, window, cx);
+            }
+        } else {
+            for rule_editor in self.rule_editors.values_mut() {
+                rule_editor.pending_token_count = Task::ready(None);
+                rule_editor.pending_cursor_token_count = Task::ready(None);
+            }
+        }
+        cx.notify();
+    }
+
+    pub fn toggle_cursor_token_count(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_cursor_token_count = !self.show_cursor_token_count;
+        if self.show_cursor_token_count {
+            if let Some(prompt_id) = self.active_rule_id {
+                self.count_tokens_before_cursor(prompt_id, false, window, cx);
+            }
+        } else {
+            for rule_editor in self.rule_editors.values_mut() {
+                rule_editor.cursor_token_count = None;
+                rule_editor.pending_cursor_token_count = Task::ready(None);
+            }
+        }
+        cx.notify();
+    }
+
+    pub fn toggle_save_metrics(&mut self) {
+        if self.save_metrics.take().is_none() {
+            self.save_metrics = Some(SaveMetrics::default());
+        }
+        for rule_editor in self.rule_editors.values_mut() {
+            rule_editor.next_save_queued_at = None;
+        }
+    }
+
+    fn toggle_always_on_top(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let always_on_top = !cx.default_global::<AlwaysOnTop>().0;
+        cx.set_global(AlwaysOnTop(always_on_top));
+        window.set_always_on_top(always_on_top);
+    }
+
+    pub fn show_save_metrics(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let detail = match &self.save_metrics {
+            Some(save_metrics) => save_metrics.summary(),
+            None => "Save metrics are disabled. Enable them with the \"rules library: toggle save metrics\" action.".to_string(),
+        };
+        drop(window.prompt(
+            PromptLevel::Info,
+            "Rule Save Metrics",
+            Some(&detail),
+            &["Ok"],
+            cx,
+        ));
+    }
+
+    pub fn recount_tokens(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(prompt_id) = self.active_rule_id {
+            // The recount happens when the store reports the invalidation.
+            self.store
+                .update(cx, |store, cx| store.invalidate_token_count(prompt_id, cx));
+        }
+    }
+
+    fn count_tokens(
         &mut self,
-        action: &InlineAssist,
+        prompt_id: PromptId,
+        debounce: bool,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let Some(active_rule_id) = self.active_rule_id else {
-            cx.propagate();
+        if !self.show_token_count {
+            return;
+        }
+        let Some(ConfiguredModel { model, .. }) =
+            LanguageModelRegistry::read_global(cx).default_model()
+        else {
             return;
         };
+        if let Some(rule) = self.rule_editors.get_mut(&prompt_id) {
+            let editor = &rule.body_editor.read(cx);
+            let buffer = &editor.buffer().read(cx).as_singleton().unwrap().read(cx);
+            let body = buffer.as_rope().clone();
+            rule.pending_token_count = cx.spawn_in(window, async move |this, cx| {
+                async move {
+                    const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(1);
 
-        let rule_editor = &self.rule_editors[&active_rule_id].body_editor;
-        let Some(ConfiguredModel { provider, .. }) =
-            LanguageModelRegistry::read_global(cx).inline_assistant_model()
+                    if debounce {
+                        cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+                    }
+                    let token_count = cx
+                        .update(|_, cx| {
+                            model.count_tokens(token_count_request(body.to_string()), cx)
+                        })?
+                        .await?;
+
+                    this.update(cx, |this, cx| {
+                        let rule_editor = this.rule_editors.get_mut(&prompt_id).unwrap();
+                        rule_editor.token_count = Some(token_count);
+                        this.store
+                            .update(cx, |store, _| store.set_token_count(prompt_id, token_count));
+                        cx.notify();
+                    })
+                }
+                .log_err()
+                .await
+            });
+        }
+    }
+
+    fn count_words_and_chars(
+        &mut self,
+        prompt_id: PromptId,
+        debounce: bool,
+        cx: &mut Context<Self>,
+    ) {
+        const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let Some(buffer) = rule_editor
+            .body_editor
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()
         else {
             return;
         };
+        let body = buffer.read(cx).as_rope().clone();
+        rule_editor.pending_text_count = cx.spawn(async move |this, cx| {
+            if debounce {
+                cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+            }
+            let (word_count, char_count) = cx
+                .background_spawn(async move { (count_words(&body), body.summary().chars) })
+                .await;
+            this.update(cx, |this, cx| {
+                if let Some(rule_editor) = this.rule_editors.get_mut(&prompt_id) {
+                    rule_editor.word_count = word_count;
+                    rule_editor.char_count = char_count;
+                    cx.notify();
+                }
+            })
+            .ok();
+        });
+    }
 
-        let initial_prompt = action.prompt.clone();
-        if provider.is_authenticated(cx) {
-            self.inline_assist_delegate
-                .assist(rule_editor, initial_prompt, window, cx);
-        } else {
-            for window in cx.windows() {
-                if let Some(workspace) = window.downcast::<Workspace>() {
-                    let panel = workspace
-                        .update(cx, |workspace, window, cx| {
-                            window.activate_window();
-                            self.inline_assist_delegate
-                                .focus_agent_panel(workspace, window, cx)
-                        })
-                        .ok();
-                    if panel == Some(true) {
-                        return;
-                    }
+    /// Counts the tokens in the body before the newest cursor, using the same
+    /// model as [`Self::count_tokens`].
+    fn count_tokens_before_cursor(
+        &mut self,
+        prompt_id: PromptId,
+        debounce: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.show_token_count || !self.show_cursor_token_count {
+            return;
+        }
+        let Some(ConfiguredModel { model, .. }) =
+            LanguageModelRegistry::read_global(cx).default_model()
+        else {
+            return;
+        };
+        let Some(rule) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let editor = rule.body_editor.read(cx);
+        let cursor = editor.selections.newest_anchor().head();
+        let Some(buffer) = editor.buffer().read(cx).as_singleton() else {
+            return;
+        };
+        let buffer = buffer.read(cx);
+        let text_before_cursor = buffer
+            .text_for_range(0..cursor.text_anchor.to_offset(buffer))
+            .collect::<String>();
+        rule.pending_cursor_token_count = cx.spawn_in(window, async move |this, cx| {
+            async move {
+                const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+                if debounce {
+                    cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
                 }
+                let token_count = cx
+                    .update(|_, cx| {
+                        model.count_tokens(token_count_request(text_before_cursor), cx)
+                    })?
+                    .await?;
+
+                this.update(cx, |this, cx| {
+                    if let Some(rule_editor) = this.rule_editors.get_mut(&prompt_id) {
+                        rule_editor.cursor_token_count = Some(token_count);
+                        cx.notify();
+                    }
+                })
+            }
+            .log_err()
+            .await
+        });
+    }
+
+    fn new_rule_preview(&self, body: String, cx: &mut Context<Self>) -> Entity<Markdown> {
+        let language_registry = self.language_registry.clone();
+        cx.new(|cx| Markdown::new(body.into(), Some(language_registry), None, cx))
+    }
+
+    pub fn toggle_rule_preview(&mut self, cx: &mut Context<Self>) {
+        let Some(prompt_id) = self.active_rule_id else {
+            return;
+        };
+        let Some(rule_editor) = self.rule_editors.get(&prompt_id) else {
+            return;
+        };
+        let preview = match rule_editor.preview {
+            Some(_) => None,
+            None => {
+                let body = rule_editor.body_editor.read(cx).text(cx);
+                Some(self.new_rule_preview(body, cx))
             }
+        };
+        if let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) {
+            rule_editor.preview = preview;
         }
+        cx.notify();
     }
 
-    fn move_down_from_title(
+    pub fn toggle_rule_outline(&mut self, cx: &mut Context<Self>) {
+        self.show_outline = !self.show_outline;
+        if let Some(prompt_id) = self.active_rule_id {
+            self.update_outline(prompt_id, false, cx);
+        }
+        cx.notify();
+    }
+
+    fn update_outline(&mut self, prompt_id: PromptId, debounce: bool, cx: &mut Context<Self>) {
+        const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+        if !self.show_outline {
+            return;
+        }
+        let Some(rule_editor) = self.rule_editors.get_mut(&prompt_id) else {
+            return;
+        };
+        let Some(buffer) = rule_editor
+            .body_editor
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()
+        else {
+            return;
+        };
+        let snapshot = buffer.read(cx).snapshot();
+        rule_editor.pending_outline = cx.spawn(async move |this, cx| {
+            if debounce {
+                cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+            }
+            let outline = cx
+                .background_spawn(async move { rule_outline(&snapshot) })
+                .await;
+            this.update(cx, |this, cx| {
+                if let Some(rule_editor) = this.rule_editors.get_mut(&prompt_id) {
+                    rule_editor.outline = outline;
+                    cx.notify();
+                }
+            })
+            .ok();
+        });
+    }
+
+    /// Scrolls the body so the heading is at the top, without moving the cursor.
+    fn scroll_to_outline_entry(
         &mut self,
-        _: &editor::actions::MoveDown,
+        prompt_id: PromptId,
+        row: u32,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        if let Some(rule_id) = self.active_rule_id
-            && let Some(rule_editor) = self.rule_editors.get(&rule_id)
-        {
-            window.focus(&rule_editor.body_editor.focus_handle(cx));
+        if let Some(rule_editor) = self.rule_editors.get(&prompt_id) {
+            rule_editor.body_editor.update(cx, |editor, cx| {
+                let anchor = editor
+                    .buffer()
+                    .read(cx)
+                    .snapshot(cx)
+                    .anchor_before(Point::new(row, 0));
+                editor.set_scroll_anchor(
+                    ScrollAnchor {
+                        anchor,
+                        offset: Default::default(),
+                    },
+                    window,
+                    cx,
+                );
+            });
+        }
+    }
+
+    fn render_rule_outline(
+        &self,
+        prompt_id: PromptId,
+        cx: &mut Context<Self>,
+    ) -> Option<impl IntoElement> {
+        if !self.show_outline {
+            return None;
         }
+        let rule_editor = self.rule_editors.get(&prompt_id)?;
+
+        Some(
+            v_flex()
+                .id("rule-outline")
+                .h_full()
+                .w_48()
+                .flex_none()
+                .py_2()
+                .overflow_y_scroll()
+                .border_l_1()
+                .border_color(cx.theme().colors().border_variant)
+                .when(rule_editor.outline.is_empty(), |this| {
+                    this.child(
+                        div().px_2().child(
+                            Label::new("No headings")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        ),
+                    )
+                })
+                .children(rule_editor.outline.iter().enumerate().map(|(ix, entry)| {
+                    let row = entry.row;
+                    ListItem::new(("rule-outline-entry", ix))
+                        .inset(true)
+                        .spacing(ListItemSpacing::Dense)
+                        .indent_level(entry.depth)
+                        .indent_step_size(px(8.))
+                        .child(
+                            Label::new(entry.title.clone())
+                                .size(LabelSize::Small)
+                                .truncate(),
+                        )
+                        .on_click(cx.listener(move |this, _, window, cx| {
+                            this.scroll_to_outline_entry(prompt_id, row, window, cx)
+                        }))
+                })),
+        )
+    }
+
+    fn toggle_default_only_filter(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.picker.update(cx, |picker, cx| {
+            picker.delegate.default_only = !picker.delegate.default_only;
+            picker.refresh(window, cx);
+        });
+        cx.notify();
+    }
+
+    fn render_default_only_filter_button(&self, cx: &mut Context<Self>) -> IconButton {
+        let default_only = self.picker.read(cx).delegate.default_only;
+        IconButton::new("default-only-filter", IconName::Paperclip)
+            .toggle_state(default_only)
+            .icon_color(if default_only {
+                Color::Accent
+            } else {
+                Color::Muted
+            })
+            .tooltip(Tooltip::text(if default_only {
+                "Show All Rules"
+            } else {
+                "Show Only Default Rules"
+            }))
+            .on_click(
+                cx.listener(|this, _, window, cx| this.toggle_default_only_filter(window, cx)),
+            )
     }
 
-    fn move_up_from_body(
+    fn set_sort_order(
         &mut self,
-        _: &editor::actions::MoveUp,
+        sort_order: RulesLibrarySortOrder,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        if let Some(rule_id) = self.active_rule_id
-            && let Some(rule_editor) = self.rule_editors.get(&rule_id)
-        {
-            window.focus(&rule_editor.title_editor.focus_handle(cx));
-        }
+        let fs = <dyn Fs>::global(cx);
+        self.store
+            .update(cx, |store, cx| store.set_sort_order(sort_order, fs, cx));
+        self.picker
+            .update(cx, |picker, cx| picker.refresh(window, cx));
     }
 
-    fn handle_rule_title_editor_event(
-        &mut self,
-        prompt_id: PromptId,
-        title_editor: &Entity<Editor>,
-        event: &EditorEvent,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        match event {
-            EditorEvent::BufferEdited => {
-                self.save_rule(prompt_id, window, cx);
-                self.count_tokens(prompt_id, window, cx);
-            }
-            EditorEvent::Blurred => {
-                title_editor.update(cx, |title_editor, cx| {
-                    title_editor.change_selections(
-                        SelectionEffects::no_scroll(),
-                        window,
-                        cx,
-                        |selections| {
-                            let cursor = selections.oldest_anchor().head();
-                            selections.select_anchor_ranges([cursor..cursor]);
-                        },
-                    );
-                });
-            }
-            _ => {}
-        }
+    fn render_sort_order_menu(&self, cx: &Context<Self>) -> impl IntoElement {
+        let this = cx.entity().downgrade();
+        let current_sort_order = self.store.read(cx).sort_order();
+        PopoverMenu::new("rule-sort-order-menu")
+            .trigger_with_tooltip(
+                IconButton::new("rule-sort-order", IconName::ArrowDown10).icon_color(Color::Muted),
+                Tooltip::text("Sort Rules"),
+            )
+            .menu(move |window, cx| {
+                let this = this.clone();
+                Some(ContextMenu::build(window, cx, move |mut menu, _, _| {
+                    for (label, sort_order) in [
+                        ("Title", RulesLibrarySortOrder::TitleAsc),
+                        ("Recently Updated", RulesLibrarySortOrder::UpdatedDesc),
+                        ("Recently Created", RulesLibrarySortOrder::CreatedDesc),
+                        ("Most Used", RulesLibrarySortOrder::MostUsed),
+                    ] {
+                        let this = this.clone();
+                        menu = menu.toggleable_entry(
+                            label,
+                            sort_order == current_sort_order,
+                            IconPosition::Start,
+                            None,
+                            move |window, cx| {
+                                this.update(cx, |this, cx| {
+                                    this.set_sort_order(sort_order, window, cx)
+                                })
+                                .ok();
+                            },
+                        );
+                    }
+                    menu
+                }))
+            })
+            .anchor(Corner::TopRight)
     }
 
-    fn handle_rule_body_editor_event(
-        &mut self,
-        prompt_id: PromptId,
-        body_editor: &Entity<Editor>,
-        event: &EditorEvent,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        match event {
-            EditorEvent::BufferEdited => {
-                self.save_rule(prompt_id, window, cx);
-                self.count_tokens(prompt_id, window, cx);
-            }
-            EditorEvent::Blurred => {
-                body_editor.update(cx, |body_editor, cx| {
-                    body_editor.change_selections(
-                        SelectionEffects::no_scroll(),
-                        window,
-                        cx,
-                        |selections| {
-                            let cursor = selections.oldest_anchor().head();
-                            selections.select_anchor_ranges([cursor..cursor]);
-                        },
-                    );
-                });
-            }
-            _ => {}
-        }
+    fn toggle_archived_rules(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.picker.update(cx, |picker, cx| {
+            picker.delegate.include_archived = !picker.delegate.include_archived;
+            picker.refresh(window, cx);
+        });
+        cx.notify();
     }
 
-    fn count_tokens(&mut self, prompt_id: PromptId, window: &mut Window, cx: &mut Context<Self>) {
-        let Some(ConfiguredModel { model, .. }) =
-            LanguageModelRegistry::read_global(cx).default_model()
-        else {
-            return;
-        };
-        if let Some(rule) = self.rule_editors.get_mut(&prompt_id) {
-            let editor = &rule.body_editor.read(cx);
-            let buffer = &editor.buffer().read(cx).as_singleton().unwrap().read(cx);
-            let body = buffer.as_rope().clone();
-            rule.pending_token_count = cx.spawn_in(window, async move |this, cx| {
-                async move {
-                    const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(1);
+    fn render_archived_rules_button(&self, cx: &mut Context<Self>) -> IconButton {
+        let include_archived = self.picker.read(cx).delegate.include_archived;
+        IconButton::new("include-archived-rules", IconName::Eye)
+            .toggle_state(include_archived)
+            .icon_color(if include_archived {
+                Color::Accent
+            } else {
+                Color::Muted
+            })
+            .tooltip(move |_window, cx| {
+                Tooltip::for_action(
+                    if include_archived {
+                        "Hide Archived Rules"
+                    } else {
+                        "Show Archived Rules"
+                    },
+                    &ToggleArchivedRules,
+                    cx,
+                )
+            })
+            .on_click(cx.listener(|this, _, window, cx| this.toggle_archived_rules(window, cx)))
+    }
 
-                    cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
-                    let token_count = cx
-                        .update(|_, cx| {
-                            model.count_tokens(
-                                LanguageModelRequest {
-                                    thread_id: None,
-                                    prompt_id: None,
-                                    intent: None,
-                                    mode: None,
-                                    messages: vec![LanguageModelRequestMessage {
-                                        role: Role::System,
-                                        content: vec![body.to_string().into()],
-                                        cache: false,
-                                        reasoning_details: None,
-                                    }],
-                                    tools: Vec::new(),
-                                    tool_choice: None,
-                                    stop: Vec::new(),
-                                    temperature: None,
-                                    thinking_allowed: true,
-                                },
-                                cx,
-                            )
-                        })?
-                        .await?;
+    fn render_import_rules_button() -> IconButton {
+        IconButton::new("import-rules", IconName::FolderOpen)
+            .tooltip(move |_window, cx| {
+                Tooltip::for_action("Import Rules from Directory", &ImportRulesFromDirectory, cx)
+            })
+            .on_click(|_, window, cx| {
+                window.dispatch_action(Box::new(ImportRulesFromDirectory), cx);
+            })
+    }
 
-                    this.update(cx, |this, cx| {
-                        let rule_editor = this.rule_editors.get_mut(&prompt_id).unwrap();
-                        rule_editor.token_count = Some(token_count);
-                        cx.notify();
-                    })
-                }
-                .log_err()
-                .await
-            });
-        }
+    fn render_export_rules_button() -> IconButton {
+        IconButton::new("export-rules", IconName::ArrowUpRight)
+            .tooltip(move |_window, cx| {
+                Tooltip::for_action("Export Rules to Directory", &ExportRulesToDirectory, cx)
+            })
+            .on_click(|_, window, cx| {
+                window.dispatch_action(Box::new(ExportRulesToDirectory), cx);
+            })
     }
 
-    fn render_rule_list(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_rule_list(&mut self, compact: bool, cx: &mut Context<Self>) -> impl IntoElement {
         v_flex()
             .id("rule-list")
             .capture_action(cx.listener(Self::focus_active_rule))
             .px_1p5()
             .h_full()
-            .w_64()
+            .map(|this| if compact { this.flex_1() } else { this.w_64() })
             .overflow_x_hidden()
             .bg(cx.theme().colors().panel_background)
             .map(|this| {
@@ -1115,36 +3866,145 @@ impl RulesLibrary {
                             .w_full()
                             .flex_none()
                             .justify_end()
-                            .child(
-                                IconButton::new("new-rule", IconName::Plus)
-                                    .tooltip(move |_window, cx| {
-                                        Tooltip::for_action("New Rule", &NewRule, cx)
-                                    })
-                                    .on_click(|_, window, cx| {
-                                        window.dispatch_action(Box::new(NewRule), cx);
-                                    }),
-                            ),
+                            .child(self.render_sort_order_menu(cx))
+                            .child(self.render_default_only_filter_button(cx))
+                            .child(self.render_archived_rules_button(cx))
+                            .child(self.render_preview_default_rules_button())
+                            .child(self.render_library_summary_button())
+                            .child(Self::render_export_rules_button())
+                            .when(!self.read_only, |this| {
+                                this.child(Self::render_import_rules_button()).child(
+                                    IconButton::new("new-rule", IconName::Plus)
+                                        .tooltip(move |_window, cx| {
+                                            Tooltip::for_action("New Rule", &NewRule, cx)
+                                        })
+                                        .on_click(|_, window, cx| {
+                                            window.dispatch_action(Box::new(NewRule), cx);
+                                        }),
+                                )
+                            }),
                     )
                 } else {
                     this.child(
-                        h_flex().p_1().w_full().child(
-                            Button::new("new-rule", "New Rule")
-                                .full_width()
-                                .style(ButtonStyle::Outlined)
-                                .icon(IconName::Plus)
-                                .icon_size(IconSize::Small)
-                                .icon_position(IconPosition::Start)
-                                .icon_color(Color::Muted)
-                                .on_click(|_, window, cx| {
-                                    window.dispatch_action(Box::new(NewRule), cx);
-                                }),
-                        ),
+                        h_flex()
+                            .p_1()
+                            .w_full()
+                            .gap_1()
+                            .when(!self.read_only, |this| {
+                                this.child(
+                                    Button::new("new-rule", "New Rule")
+                                        .full_width()
+                                        .style(ButtonStyle::Outlined)
+                                        .icon(IconName::Plus)
+                                        .icon_size(IconSize::Small)
+                                        .icon_position(IconPosition::Start)
+                                        .icon_color(Color::Muted)
+                                        .on_click(|_, window, cx| {
+                                            window.dispatch_action(Box::new(NewRule), cx);
+                                        }),
+                                )
+                            })
+                            .child(self.render_sort_order_menu(cx))
+                            .child(self.render_default_only_filter_button(cx))
+                            .child(self.render_archived_rules_button(cx))
+                            .child(self.render_preview_default_rules_button())
+                            .child(self.render_library_summary_button())
+                            .child(Self::render_export_rules_button())
+                            .when(!self.read_only, |this| {
+                                this.child(Self::render_import_rules_button())
+                            }),
                     )
                 }
             })
+            .children(self.deleting_rule_count.map(|count| {
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_1p5()
+                    .child(
+                        Icon::new(IconName::ArrowCircle)
+                            .size(IconSize::Small)
+                            .color(Color::Muted)
+                            .with_rotate_animation(2),
+                    )
+                    .child(
+                        Label::new(format!("Deleting {count} rules…"))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+            }))
+            .children(self.deleted_rules.as_ref().map(|deleted_rules| {
+                let count = deleted_rules.prompt_ids.len();
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_1p5()
+                    .justify_between()
+                    .child(
+                        Label::new(if count == 1 {
+                            "Rule deleted".to_string()
+                        } else {
+                            format!("{count} rules deleted")
+                        })
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                    )
+                    .child(
+                        Button::new("undo-delete-rules", "Undo")
+                            .label_size(LabelSize::Small)
+                            .on_click(
+                                cx.listener(|this, _, window, cx| this.undo_delete(window, cx)),
+                            ),
+                    )
+            }))
+            .children(self.render_recent_rules(cx))
             .child(div().flex_grow().child(self.picker.clone()))
     }
 
+    /// The rules opened most recently this session, above the list so that
+    /// they stay reachable whatever the list is filtered by.
+    fn render_recent_rules(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let recent_rules = self.store.read(cx).recently_opened();
+        if recent_rules.is_empty() {
+            return None;
+        }
+
+        Some(
+            v_flex()
+                .px_1()
+                .py_1()
+                .gap_0p5()
+                .child(
+                    Label::new("Recent")
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .child(
+                    h_flex().flex_wrap().gap_1().children(
+                        recent_rules
+                            .into_iter()
+                            .take(RECENT_RULE_COUNT)
+                            .enumerate()
+                            .map(|(ix, metadata)| {
+                                let prompt_id = metadata.id;
+                                Button::new(
+                                    ("recent-rule", ix),
+                                    metadata.title.unwrap_or_else(|| "Untitled".into()),
+                                )
+                                .label_size(LabelSize::Small)
+                                .style(ButtonStyle::Subtle)
+                                .toggle_state(self.active_rule_id == Some(prompt_id))
+                                .on_click(cx.listener(
+                                    move |this, _, window, cx| {
+                                        this.load_rule(prompt_id, true, window, cx)
+                                    },
+                                ))
+                            }),
+                    ),
+                ),
+        )
+    }
+
     fn render_active_rule_editor(
         &self,
         editor: &Entity<Editor>,
@@ -1186,7 +4046,80 @@ impl RulesLibrary {
             ))
     }
 
-    fn render_active_rule(&mut self, cx: &mut Context<RulesLibrary>) -> gpui::Stateful<Div> {
+    fn render_rule_notes(
+        &self,
+        notes_editor: &Entity<Editor>,
+        has_notes: bool,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        v_flex()
+            .px_2p5()
+            .pt_1()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Disclosure::new("toggle-rule-notes", self.show_notes).on_click(
+                            cx.listener(|this, _, _, cx| {
+                                this.show_notes = !this.show_notes;
+                                cx.notify();
+                            }),
+                        ),
+                    )
+                    .child(
+                        Label::new("Notes")
+                            .size(LabelSize::Small)
+                            .color(if has_notes {
+                                Color::Default
+                            } else {
+                                Color::Muted
+                            }),
+                    ),
+            )
+            .when(self.show_notes, |this| {
+                this.child(
+                    div()
+                        .pl_5()
+                        .pb_1()
+                        .border_b_1()
+                        .border_color(cx.theme().colors().border_variant)
+                        .child(notes_editor.clone()),
+                )
+            })
+    }
+
+    fn render_rule_description(&self, description_editor: &Entity<Editor>) -> impl IntoElement {
+        h_flex()
+            .px_2p5()
+            .pt_1()
+            .gap_1()
+            .child(
+                Icon::new(IconName::Quote)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(div().flex_1().child(description_editor.clone()))
+    }
+
+    fn render_rule_tags(&self, tags_editor: &Entity<Editor>) -> impl IntoElement {
+        h_flex()
+            .px_2p5()
+            .pt_1()
+            .gap_1()
+            .child(
+                Icon::new(IconName::Hash)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(div().flex_1().child(tags_editor.clone()))
+    }
+
+    fn render_active_rule(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<RulesLibrary>,
+    ) -> gpui::Stateful<Div> {
         div()
             .id("rule-editor")
             .h_full()
@@ -1198,9 +4131,9 @@ impl RulesLibrary {
                 let rule_metadata = self.store.read(cx).metadata(prompt_id)?;
                 let rule_editor = &self.rule_editors[&prompt_id];
                 let focus_handle = rule_editor.body_editor.focus_handle(cx);
-                let model = LanguageModelRegistry::read_global(cx)
+                let has_model = LanguageModelRegistry::read_global(cx)
                     .default_model()
-                    .map(|default| default.model);
+                    .is_some();
 
                 Some(
                     v_flex()
@@ -1226,71 +4159,236 @@ impl RulesLibrary {
                                     h_flex()
                                         .h_full()
                                         .flex_shrink_0()
-                                        .children(rule_editor.token_count.map(|token_count| {
-                                            let token_count: SharedString =
-                                                token_count.to_string().into();
-                                            let label_token_count: SharedString =
-                                                token_count.to_string().into();
+                                        .children(render_rule_state_labels(&rule_metadata, cx))
+                                        .when(!has_model, |this| {
+                                            this.child(
+                                                Button::new(
+                                                    "select-model",
+                                                    "Select a model to count tokens",
+                                                )
+                                                .label_size(LabelSize::Small)
+                                                .color(Color::Muted)
+                                                .on_click(cx.listener(|this, _, window, cx| {
+                                                    this.select_model(window, cx)
+                                                })),
+                                            )
+                                        })
+                                        .child(
+                                            Label::new(format!(
+                                                "{} words, {} characters",
+                                                rule_editor.word_count, rule_editor.char_count
+                                            ))
+                                            .color(Color::Muted),
+                                        )
+                                        .when(
+                                            rule_editor.token_count.is_some()
+                                                && self.show_token_count,
+                                            |this| {
+                                                this.child(
+                                                    div().mx_1().child(
+                                                        Label::new("•")
+                                                            .alpha(0.5)
+                                                            .color(Color::Muted),
+                                                    ),
+                                                )
+                                            },
+                                        )
+                                        .children(
+                                            rule_editor
+                                                .token_count
+                                                .filter(|_| self.show_token_count)
+                                                .map(|token_count| {
+                                                    let label = match rule_editor
+                                                        .cursor_token_count
+                                                        .filter(|_| self.show_cursor_token_count)
+                                                    {
+                                                        Some(cursor_token_count) => format!(
+                                                            "{cursor_token_count} / {token_count} tokens"
+                                                        ),
+                                                        None => format!("{token_count} tokens"),
+                                                    };
 
-                                            div()
-                                                .id("token_count")
-                                                .mr_1()
-                                                .flex_shrink_0()
-                                                .tooltip(move |_window, cx| {
-                                                    Tooltip::with_meta(
-                                                        "Token Estimation",
-                                                        None,
+                                                    div()
+                                                        .id("token_count")
+                                                        .mr_1()
+                                                        .flex_shrink_0()
+                                                        .on_hover(cx.listener(
+                                                            move |this, hovered, window, cx| {
+                                                                this.set_show_model_token_counts(
+                                                                    prompt_id, *hovered, window,
+                                                                    cx,
+                                                                );
+                                                            },
+                                                        ))
+                                                        .children(self.render_model_token_counts(
+                                                            prompt_id, cx,
+                                                        ))
+                                                        .child(
+                                                            Label::new(label).color(Color::Muted),
+                                                        )
+                                                }),
+                                        )
+                                        .when(!self.is_rule_read_only(prompt_id, cx), |this| {
+                                            this.child(self.render_rule_language_menu(
+                                                prompt_id,
+                                                rule_metadata.language.clone(),
+                                                cx,
+                                            ))
+                                        })
+                                        .child(
+                                            IconButton::new(
+                                                "toggle-rule-outline",
+                                                IconName::ListTree,
+                                            )
+                                            .toggle_state(self.show_outline)
+                                            .tooltip(move |_window, cx| {
+                                                Tooltip::for_action(
+                                                    "Toggle Outline",
+                                                    &ToggleRuleOutline,
+                                                    cx,
+                                                )
+                                            })
+                                            .on_click(
+                                                |_, window, cx| {
+                                                    window.dispatch_action(
+                                                        Box::new(ToggleRuleOutline),
+                                                        cx,
+                                                    );
+                                                },
+                                            ),
+                                        )
+                                        .child(
+                                            IconButton::new(
+                                                "toggle-rule-preview",
+                                                IconName::FileMarkdown,
+                                            )
+                                            .toggle_state(rule_editor.preview.is_some())
+                                            .tooltip(move |_window, cx| {
+                                                Tooltip::for_action(
+                                                    "Toggle Preview",
+                                                    &ToggleRulePreview,
+                                                    cx,
+                                                )
+                                            })
+                                            .on_click(|_, window, cx| {
+                                                window.dispatch_action(
+                                                    Box::new(ToggleRulePreview),
+                                                    cx,
+                                                );
+                                            }),
+                                        )
+                                        .child({
+                                            let linked_path = rule_metadata.linked_path.clone();
+                                            IconButton::new("save-rule-to-file", IconName::Download)
+                                                .tooltip(move |_window, cx| match &linked_path {
+                                                    Some(linked_path) => Tooltip::with_meta(
+                                                        "Save Rule to File",
+                                                        Some(&SaveRuleToFile),
                                                         format!(
-                                                            "Model: {}",
-                                                            model
-                                                                .as_ref()
-                                                                .map(|model| model.name().0)
-                                                                .unwrap_or_default()
+                                                            "Linked to {}",
+                                                            linked_path.display()
                                                         ),
                                                         cx,
-                                                    )
+                                                    ),
+                                                    None => Tooltip::for_action(
+                                                        "Save Rule to File",
+                                                        &SaveRuleToFile,
+                                                        cx,
+                                                    ),
                                                 })
-                                                .child(
-                                                    Label::new(format!(
-                                                        "{} tokens",
-                                                        label_token_count
-                                                    ))
-                                                    .color(Color::Muted),
-                                                )
-                                        }))
-                                        .child(if prompt_id.is_built_in() {
-                                            div()
-                                                .id("built-in-rule")
-                                                .child(
-                                                    Icon::new(IconName::FileLock)
-                                                        .color(Color::Muted),
+                                                .on_click(|_, window, cx| {
+                                                    window.dispatch_action(
+                                                        Box::new(SaveRuleToFile),
+                                                        cx,
+                                                    );
+                                                })
+                                        })
+                                        .when(!prompt_id.is_built_in() && !self.read_only, |this| {
+                                            this.child(
+                                                IconButton::new(
+                                                    "toggle-rule-lock",
+                                                    IconName::LockOutlined,
                                                 )
+                                                .toggle_state(rule_metadata.locked)
+                                                .icon_color(if rule_metadata.locked {
+                                                    Color::Accent
+                                                } else {
+                                                    Color::Muted
+                                                })
                                                 .tooltip(move |_window, cx| {
-                                                    Tooltip::with_meta(
-                                                        "Built-in rule",
-                                                        None,
-                                                        BUILT_IN_TOOLTIP_TEXT,
+                                                    Tooltip::for_action(
+                                                        if rule_metadata.locked {
+                                                            "Unlock Rule"
+                                                        } else {
+                                                            "Lock Rule"
+                                                        },
+                                                        &ToggleRuleLock,
                                                         cx,
                                                     )
                                                 })
-                                                .into_any()
-                                        } else {
-                                            IconButton::new("delete-rule", IconName::Trash)
+                                                .on_click(|_, window, cx| {
+                                                    window.dispatch_action(
+                                                        Box::new(ToggleRuleLock),
+                                                        cx,
+                                                    );
+                                                }),
+                                            )
+                                        })
+                                        .child(
+                                            IconButton::new("copy-rule", IconName::Copy)
                                                 .tooltip(move |_window, cx| {
                                                     Tooltip::for_action(
-                                                        "Delete Rule",
-                                                        &DeleteRule,
+                                                        "Copy Rule",
+                                                        &CopyRule,
                                                         cx,
                                                     )
                                                 })
                                                 .on_click(|_, window, cx| {
-                                                    window
-                                                        .dispatch_action(Box::new(DeleteRule), cx);
-                                                })
-                                                .into_any_element()
-                                        })
-                                        .child(
-                                            IconButton::new("duplicate-rule", IconName::BookCopy)
+                                                    window.dispatch_action(
+                                                        Box::new(CopyRule),
+                                                        cx,
+                                                    );
+                                                }),
+                                        )
+                                        .when(!self.read_only, |this| {
+                                            this.child(if prompt_id.is_built_in() {
+                                                div()
+                                                    .id("built-in-rule")
+                                                    .child(
+                                                        Icon::new(IconName::FileLock)
+                                                            .color(Color::Muted),
+                                                    )
+                                                    .tooltip(move |_window, cx| {
+                                                        Tooltip::with_meta(
+                                                            "Built-in rule",
+                                                            None,
+                                                            BUILT_IN_TOOLTIP_TEXT,
+                                                            cx,
+                                                        )
+                                                    })
+                                                    .into_any()
+                                            } else {
+                                                IconButton::new("delete-rule", IconName::Trash)
+                                                    .tooltip(move |_window, cx| {
+                                                        Tooltip::for_action(
+                                                            "Delete Rule",
+                                                            &DeleteRule,
+                                                            cx,
+                                                        )
+                                                    })
+                                                    .on_click(|_, window, cx| {
+                                                        window.dispatch_action(
+                                                            Box::new(DeleteRule),
+                                                            cx,
+                                                        );
+                                                    })
+                                                    .into_any_element()
+                                            })
+                                            .child(
+                                                IconButton::new(
+                                                    "duplicate-rule",
+                                                    IconName::BookCopy,
+                                                )
                                                 .tooltip(move |_window, cx| {
                                                     Tooltip::for_action(
                                                         "Duplicate Rule",
@@ -1304,45 +4402,86 @@ impl RulesLibrary {
                                                         cx,
                                                     );
                                                 }),
-                                        )
-                                        .child(
-                                            IconButton::new(
-                                                "toggle-default-rule",
-                                                IconName::Paperclip,
                                             )
-                                            .toggle_state(rule_metadata.default)
-                                            .icon_color(if rule_metadata.default {
-                                                Color::Accent
-                                            } else {
-                                                Color::Muted
-                                            })
-                                            .map(|this| {
-                                                if rule_metadata.default {
-                                                    this.tooltip(Tooltip::text(
-                                                        "Remove from Default Rules",
-                                                    ))
-                                                } else {
-                                                    this.tooltip(move |_window, cx| {
-                                                        Tooltip::with_meta(
-                                                            "Add to Default Rules",
-                                                            None,
-                                                            "Always included in every thread.",
+                                            .when(rule_metadata.default, |this| {
+                                                this.child(
+                                                    IconButton::new(
+                                                        "toggle-rule-disabled",
+                                                        IconName::Power,
+                                                    )
+                                                    .toggle_state(!rule_metadata.disabled)
+                                                    .icon_color(if rule_metadata.disabled {
+                                                        Color::Muted
+                                                    } else {
+                                                        Color::Accent
+                                                    })
+                                                    .tooltip(move |_window, cx| {
+                                                        Tooltip::for_action(
+                                                            if rule_metadata.disabled {
+                                                                "Enable Rule"
+                                                            } else {
+                                                                "Disable Rule"
+                                                            },
+                                                            &ToggleRuleDisabled,
                                                             cx,
                                                         )
                                                     })
-                                                }
+                                                    .on_click(|_, window, cx| {
+                                                        window.dispatch_action(
+                                                            Box::new(ToggleRuleDisabled),
+                                                            cx,
+                                                        );
+                                                    }),
+                                                )
                                             })
-                                            .on_click(
-                                                |_, window, cx| {
+                                            .child(
+                                                IconButton::new(
+                                                    "toggle-default-rule",
+                                                    IconName::Paperclip,
+                                                )
+                                                .toggle_state(rule_metadata.default)
+                                                .icon_color(if rule_metadata.default {
+                                                    Color::Accent
+                                                } else {
+                                                    Color::Muted
+                                                })
+                                                .map(|this| {
+                                                    if rule_metadata.default {
+                                                        this.tooltip(Tooltip::text(
+                                                            "Remove from Default Rules",
+                                                        ))
+                                                    } else {
+                                                        this.tooltip(move |_window, cx| {
+                                                            Tooltip::with_meta(
+                                                                "Add to Default Rules",
+                                                                None,
+                                                                "Always included in every thread.",
+                                                                cx,
+                                                            )
+                                                        })
+                                                    }
+                                                })
+                                                .on_click(|_, window, cx| {
                                                     window.dispatch_action(
                                                         Box::new(ToggleDefaultRule),
                                                         cx,
                                                     );
-                                                },
-                                            ),
-                                        ),
+                                                }),
+                                            )
+                                        }),
                                 ),
                         )
+                        .when(!prompt_id.is_built_in(), |this| {
+                            this.child(
+                                self.render_rule_description(&rule_editor.description_editor),
+                            )
+                            .child(self.render_rule_notes(
+                                &rule_editor.notes_editor,
+                                rule_metadata.notes.is_some(),
+                                cx,
+                            ))
+                            .child(self.render_rule_tags(&rule_editor.tags_editor))
+                        })
                         .child(
                             div()
                                 .on_action(cx.listener(Self::focus_picker))
@@ -1352,11 +4491,48 @@ impl RulesLibrary {
                                 .flex_grow()
                                 .child(
                                     h_flex()
-                                        .py_2()
-                                        .pl_2p5()
-                                        .h_full()
-                                        .flex_1()
-                                        .child(rule_editor.body_editor.clone()),
+                                        .size_full()
+                                        .child(
+                                            h_flex()
+                                                .py_2()
+                                                .pl_2p5()
+                                                .h_full()
+                                                .flex_1()
+                                                .relative()
+                                                .capture_any_mouse_down(cx.listener(
+                                                    Self::handle_include_click_mouse_down,
+                                                ))
+                                                .on_mouse_up(
+                                                    MouseButton::Left,
+                                                    cx.listener(
+                                                        Self::handle_include_click_mouse_up,
+                                                    ),
+                                                )
+                                                .on_mouse_up_out(
+                                                    MouseButton::Left,
+                                                    cx.listener(
+                                                        Self::handle_include_click_mouse_up,
+                                                    ),
+                                                )
+                                                .child(rule_editor.body_editor.clone())
+                                                .children(self.render_unresolved_include(cx)),
+                                        )
+                                        .children(rule_editor.preview.clone().map(|preview| {
+                                            div()
+                                                .id("rule-preview")
+                                                .py_2()
+                                                .px_2p5()
+                                                .h_full()
+                                                .flex_1()
+                                                .overflow_y_scroll()
+                                                .border_l_1()
+                                                .border_color(cx.theme().colors().border_variant)
+                                                .child(MarkdownElement::new(
+                                                    preview,
+                                                    hover_markdown_style(window, cx),
+                                                ))
+                                        }))
+                                        .children(self.render_rule_outline(prompt_id, cx)),
                                 ),
                         ),
                 )
@@ -1364,43 +4540,423 @@ impl RulesLibrary {
     }
 }
 
+/// Splits `tag:` filters out of a search query, returning the rest of the query
+/// and the tags a rule must all have to match, e.g. `tag:tests tag:docs`.
+/// `\tag:` searches for the text `tag:` instead.
+fn parse_tag_filters(query: &str) -> (String, Vec<String>) {
+    let mut tags = Vec::new();
+    let mut rest = Vec::new();
+    let mut has_escapes = false;
+    for word in query.split_whitespace() {
+        if let Some(escaped) = word
+            .strip_prefix('\\')
+            .filter(|word| word.starts_with("tag:"))
+        {
+            has_escapes = true;
+            rest.push(escaped);
+            continue;
+        }
+        match word.strip_prefix("tag:").filter(|tag| !tag.is_empty()) {
+            Some(tag) => tags.push(tag.to_string()),
+            None => rest.push(word),
+        }
+    }
+    if tags.is_empty() && !has_escapes {
+        (query.to_string(), tags)
+    } else {
+        (rest.join(" "), tags)
+    }
+}
+
+/// Splits a `tokens:` filter out of a search query, returning the rest of the
+/// query and the range of token counts to match. Supports `tokens:>500`,
+/// `tokens:>=500`, `tokens:<500`, `tokens:<=500`, `tokens:100..500` and
+/// `tokens:500`. A filter that doesn't parse is left in the query as text.
+fn parse_token_range(query: &str) -> (String, Option<RangeInclusive<u64>>) {
+    let mut range = None;
+    let mut rest = Vec::new();
+    for word in query.split_whitespace() {
+        let parsed = word.strip_prefix("tokens:").and_then(|filter| {
+            if let Some(count) = filter.strip_prefix(">=") {
+                Some(count.parse().ok()?..=u64::MAX)
+            } else if let Some(count) = filter.strip_prefix('>') {
+                Some(count.parse::<u64>().ok()?.checked_add(1)?..=u64::MAX)
+            } else if let Some(count) = filter.strip_prefix("<=") {
+                Some(0..=count.parse().ok()?)
+            } else if let Some(count) = filter.strip_prefix('<') {
+                Some(0..=count.parse::<u64>().ok()?.checked_sub(1)?)
+            } else if let Some((start, end)) = filter.split_once("..") {
+                Some(start.parse().ok()?..=end.parse().ok()?)
+            } else {
+                let count = filter.parse().ok()?;
+                Some(count..=count)
+            }
+        });
+        match parsed {
+            Some(parsed) if range.is_none() => range = Some(parsed),
+            _ => rest.push(word),
+        }
+    }
+    match range {
+        Some(range) => (rest.join(" "), Some(range)),
+        None => (query.to_string(), None),
+    }
+}
+
+/// Spells out whether a rule is a default, built-in, locked or archived rule,
+/// with the `show_state_labels` setting. Otherwise, those are only shown with
+/// icons and colors.
+fn render_rule_state_labels(metadata: &PromptMetadata, cx: &App) -> Option<impl IntoElement> {
+    if !RulesLibrarySettings::get_global(cx).show_state_labels {
+        return None;
+    }
+    let labels = [
+        (metadata.default, "Default"),
+        (metadata.id.is_built_in(), "Built-in"),
+        (metadata.locked, "Locked"),
+        (metadata.archived, "Archived"),
+        (metadata.default && metadata.disabled, "Disabled"),
+    ];
+    Some(
+        h_flex().gap_1().flex_shrink_0().children(
+            labels
+                .into_iter()
+                .filter(|(applies, _)| *applies)
+                .map(|(_, label)| {
+                    div()
+                        .px_1()
+                        .border_1()
+                        .border_color(cx.theme().colors().border)
+                        .rounded_sm()
+                        .child(Label::new(label).size(LabelSize::XSmall))
+                }),
+        ),
+    )
+}
+
+/// Counts runs of non-whitespace characters, chunk by chunk so that the body
+/// isn't copied into one string.
+fn count_words(text: &Rope) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for chunk in text.chunks() {
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Whether to override edit predictions in a rule's body editor, per the
+/// `edit_predictions` setting. Read-only rules never show them.
+fn body_edit_predictions(read_only: bool, cx: &App) -> Option<bool> {
+    (read_only || !RulesLibrarySettings::get_global(cx).edit_predictions).then_some(false)
+}
+
+fn token_count_request(text: String) -> LanguageModelRequest {
+    LanguageModelRequest {
+        thread_id: None,
+        prompt_id: None,
+        intent: None,
+        mode: None,
+        messages: vec![LanguageModelRequestMessage {
+            role: Role::System,
+            content: vec![text.into()],
+            cache: false,
+            reasoning_details: None,
+        }],
+        tools: Vec::new(),
+        tool_choice: None,
+        stop: Vec::new(),
+        temperature: None,
+        thinking_allowed: true,
+    }
+}
+
+/// Joins the default rules' bodies in order, labeling where each one starts.
+fn default_rules_preview_text(rules: &[(PromptMetadata, String)]) -> String {
+    if rules.is_empty() {
+        return "No default rules.".to_string();
+    }
+    let mut text = String::new();
+    for (metadata, body) in rules {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        let title = metadata.title.as_deref().unwrap_or("Untitled");
+        text.push_str(&format!("──── {title} ────\n"));
+        text.push_str(body.trim_end());
+    }
+    text
+}
+
+fn rule_outline(snapshot: &BufferSnapshot) -> Vec<RuleOutlineEntry> {
+    snapshot
+        .outline(None)
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            let title = item.text.trim_start_matches('#');
+            let level = item.text.len() - title.len();
+            let title = title.trim();
+            if level == 0 || title.is_empty() {
+                return None;
+            }
+            Some(RuleOutlineEntry {
+                row: item.range.start.to_point(snapshot).row,
+                depth: level - 1,
+                title: title.to_string().into(),
+            })
+        })
+        .collect()
+}
+
+/// Ranges that fold away the contents of every top-level section but the first,
+/// leaving the headings themselves visible.
+fn section_fold_ranges(snapshot: &BufferSnapshot) -> Vec<Range<Point>> {
+    let outline = rule_outline(snapshot);
+    let Some(min_depth) = outline.iter().map(|entry| entry.depth).min() else {
+        return Vec::new();
+    };
+    let section_rows = outline
+        .iter()
+        .filter(|entry| entry.depth == min_depth)
+        .map(|entry| entry.row)
+        .collect::<Vec<_>>();
+    section_rows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(ix, &row)| {
+            let start = Point::new(row, snapshot.line_len(row));
+            let end = match section_rows.get(ix + 1) {
+                Some(&next_row) => Point::new(next_row - 1, snapshot.line_len(next_row - 1)),
+                None => snapshot.max_point(),
+            };
+            (start < end).then_some(start..end)
+        })
+        .collect()
+}
+
+/// Joins the display lines of a soft-wrapped body with newlines. Lines in fenced
+/// code blocks, and lines whose display doesn't match the buffer (e.g. because
+/// they're folded), are copied verbatim so the markdown structure is preserved.
+fn wrapped_text(text: &str, snapshot: &DisplaySnapshot) -> String {
+    let buffer_lines = text.split('\n').collect::<Vec<_>>();
+    let mut display_lines_by_buffer_row = vec![Vec::new(); buffer_lines.len()];
+    for (display_row, row_info) in snapshot.row_infos(DisplayRow(0)).enumerate() {
+        if let Some(buffer_row) = row_info.buffer_row.or(row_info.wrapped_buffer_row)
+            && let Some(display_lines) = display_lines_by_buffer_row.get_mut(buffer_row as usize)
+        {
+            display_lines.push(snapshot.line(DisplayRow(display_row as u32)));
+        }
+    }
+
+    let mut in_code_block = false;
+    let mut lines = Vec::new();
+    for (line, display_lines) in buffer_lines.into_iter().zip(display_lines_by_buffer_row) {
+        let trimmed_line = line.trim_start();
+        let is_fence = trimmed_line.starts_with("```") || trimmed_line.starts_with("~~~");
+        if is_fence {
+            in_code_block = !in_code_block;
+        }
+
+        let matches_buffer = display_lines
+            .concat()
+            .split_whitespace()
+            .eq(line.split_whitespace());
+        if is_fence || in_code_block || display_lines.len() < 2 || !matches_buffer {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let last_ix = display_lines.len() - 1;
+        for (ix, display_line) in display_lines.iter().enumerate() {
+            let mut display_line = display_line.as_str();
+            if ix > 0 {
+                display_line = display_line.trim_start();
+            }
+            if ix < last_ix {
+                display_line = display_line.trim_end();
+            }
+            lines.push(display_line.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
 impl Render for RulesLibrary {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let ui_font = theme::setup_ui_font(window, cx);
         let theme = cx.theme().clone();
+        let compact = window.viewport_size().width < COMPACT_LAYOUT_MAX_WIDTH;
 
         client_side_decorations(
             v_flex()
                 .id("rules-library")
                 .key_context("RulesLibrary")
-                .on_action(cx.listener(|this, &NewRule, window, cx| this.new_rule(window, cx)))
+                .capture_action(cx.listener(|this, _: &Undo, window, cx| {
+                    this.undo_or_redo_linked_edit(false, window, cx)
+                }))
+                .capture_action(cx.listener(|this, _: &Redo, window, cx| {
+                    this.undo_or_redo_linked_edit(true, window, cx)
+                }))
+                .when(!self.read_only, |this| {
+                    this.on_action(
+                        cx.listener(|this, &NewRule, window, cx| this.new_rule(window, cx)),
+                    )
+                    .on_action(cx.listener(|this, &DeleteRule, window, cx| {
+                        this.delete_selected_rules(window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &DuplicateRule, window, cx| {
+                        this.duplicate_active_rule(window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ToggleDefaultRule, window, cx| {
+                        this.toggle_default_for_active_rule(window, cx)
+                    }))
+                    .on_action(
+                        cx.listener(|this, &ToggleDefaultForSelectedRules, window, cx| {
+                            this.toggle_default_for_selected_rules(window, cx)
+                        }),
+                    )
+                    .on_action(cx.listener(|this, &ToggleRuleDisabled, window, cx| {
+                        this.toggle_disabled_for_active_rule(window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ToggleRuleLock, window, cx| {
+                        this.toggle_lock_for_active_rule(window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ArchiveRule, window, cx| {
+                        this.set_active_rule_archived(true, window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &UnarchiveRule, window, cx| {
+                        this.set_active_rule_archived(false, window, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ImportRulesFromDirectory, window, cx| {
+                        this.import_rules_from_directory(window, cx)
+                    }))
+                    .on_action(
+                        cx.listener(|this, &RestoreRules, window, cx| {
+                            this.restore_rules(window, cx)
+                        }),
+                    )
+                    .on_action(cx.listener(|this, &DeleteEmptyRules, window, cx| {
+                        this.delete_empty_rules(window, cx)
+                    }))
+                    .on_action(
+                        cx.listener(|this, &PasteRules, window, cx| this.paste_rules(window, cx)),
+                    )
+                })
+                .on_action(cx.listener(|this, &ToggleArchivedRules, window, cx| {
+                    this.toggle_archived_rules(window, cx)
+                }))
+                .on_action(cx.listener(|this, &ExportRulesToDirectory, window, cx| {
+                    this.export_rules_to_directory(window, cx)
+                }))
+                .on_action(
+                    cx.listener(|this, &BackupRules, window, cx| this.backup_rules(window, cx)),
+                )
+                .on_action(cx.listener(|this, &ToggleTokenCount, window, cx| {
+                    this.toggle_token_count(window, cx)
+                }))
                 .on_action(
-                    cx.listener(|this, &DeleteRule, window, cx| {
-                        this.delete_active_rule(window, cx)
+                    cx.listener(|this, &RecountTokens, window, cx| this.recount_tokens(window, cx)),
+                )
+                .on_action(cx.listener(|this, &ToggleCursorTokenCount, window, cx| {
+                    this.toggle_cursor_token_count(window, cx)
+                }))
+                .on_action(cx.listener(|this, &PreviewDefaultRules, window, cx| {
+                    this.toggle_default_rules_preview(window, cx)
+                }))
+                .on_action(
+                    cx.listener(|this, &FocusSearch, window, cx| this.focus_search(window, cx)),
+                )
+                .on_action(cx.listener(|this, &ShowLibrarySummary, window, cx| {
+                    this.toggle_library_summary(window, cx)
+                }))
+                .on_action(
+                    cx.listener(|this, &ToggleSaveMetrics, _window, _cx| {
+                        this.toggle_save_metrics()
                     }),
                 )
-                .on_action(cx.listener(|this, &DuplicateRule, window, cx| {
-                    this.duplicate_active_rule(window, cx)
+                .on_action(cx.listener(|this, &ShowSaveMetrics, window, cx| {
+                    this.show_save_metrics(window, cx)
+                }))
+                .on_action(
+                    cx.listener(|this, &CopyRules, _window, cx| this.copy_selected_rules(cx)),
+                )
+                .on_action(cx.listener(|this, &SaveRuleToFile, window, cx| {
+                    this.save_active_rule_to_file(window, cx)
                 }))
-                .on_action(cx.listener(|this, &ToggleDefaultRule, window, cx| {
-                    this.toggle_default_for_active_rule(window, cx)
+                .on_action(cx.listener(|this, &ToggleAlwaysOnTop, window, cx| {
+                    this.toggle_always_on_top(window, cx)
                 }))
+                .on_action(cx.listener(|this, &CopyRule, _window, cx| this.copy_active_rule(cx)))
+                .on_action(cx.listener(|this, &CopyRuleWrapped, _window, cx| {
+                    this.copy_active_rule_wrapped(cx)
+                }))
+                .on_action(
+                    cx.listener(|this, &ToggleRuleOutline, _window, cx| {
+                        this.toggle_rule_outline(cx)
+                    }),
+                )
+                .on_action(
+                    cx.listener(|this, &ToggleRulePreview, _window, cx| {
+                        this.toggle_rule_preview(cx)
+                    }),
+                )
                 .size_full()
                 .overflow_hidden()
                 .font(ui_font)
                 .text_color(theme.colors().text)
                 .children(self.title_bar.clone())
                 .bg(theme.colors().background)
+                .when(self.read_only, |this| {
+                    this.child(
+                        div()
+                            .p_1()
+                            .child(Banner::new().severity(Severity::Info).child(Label::new(
+                                "Read-only: rules can't be edited, created or deleted.",
+                            ))),
+                    )
+                })
                 .child(
                     h_flex()
                         .flex_1()
                         .when(!cfg!(target_os = "macos"), |this| {
                             this.border_t_1().border_color(cx.theme().colors().border)
                         })
-                        .child(self.render_rule_list(cx))
                         .map(|el| {
-                            if self.store.read(cx).prompt_count() == 0 {
+                            if self.default_rules_preview.is_some() {
+                                if compact {
+                                    el.children(self.render_default_rules_preview(cx))
+                                } else {
+                                    el.child(self.render_rule_list(false, cx))
+                                        .children(self.render_default_rules_preview(cx))
+                                }
+                            } else if self.library_summary.is_some() {
+                                if compact {
+                                    el.children(self.render_library_summary(cx))
+                                } else {
+                                    el.child(self.render_rule_list(false, cx))
+                                        .children(self.render_library_summary(cx))
+                                }
+                            } else if compact && self.show_rule_in_compact_layout {
                                 el.child(
+                                    v_flex()
+                                        .h_full()
+                                        .flex_1()
+                                        .child(self.render_compact_rule_header(cx))
+                                        .child(self.render_active_rule(window, cx)),
+                                )
+                            } else if compact {
+                                el.child(self.render_rule_list(true, cx))
+                            } else if self.store.read(cx).prompt_count() == 0 {
+                                el.child(self.render_rule_list(false, cx)).child(
                                     v_flex()
                                         .h_full()
                                         .flex_1()
@@ -1409,18 +4965,25 @@ impl Render for RulesLibrary {
                                         .border_l_1()
                                         .border_color(cx.theme().colors().border)
                                         .bg(cx.theme().colors().editor_background)
-                                        .child(
-                                            Button::new("create-rule", "New Rule")
-                                                .style(ButtonStyle::Outlined)
-                                                .key_binding(KeyBinding::for_action(&NewRule, cx))
-                                                .on_click(|_, window, cx| {
-                                                    window
-                                                        .dispatch_action(NewRule.boxed_clone(), cx)
-                                                }),
-                                        ),
+                                        .when(!self.read_only, |this| {
+                                            this.child(
+                                                Button::new("create-rule", "New Rule")
+                                                    .style(ButtonStyle::Outlined)
+                                                    .key_binding(KeyBinding::for_action(
+                                                        &NewRule, cx,
+                                                    ))
+                                                    .on_click(|_, window, cx| {
+                                                        window.dispatch_action(
+                                                            NewRule.boxed_clone(),
+                                                            cx,
+                                                        )
+                                                    }),
+                                            )
+                                        }),
                                 )
                             } else {
-                                el.child(self.render_active_rule(cx))
+                                el.child(self.render_rule_list(false, cx))
+                                    .child(self.render_active_rule(window, cx))
                             }
                         }),
                 ),
@@ -1429,3 +4992,483 @@ impl Render for RulesLibrary {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{TestAppContext, VisualTestContext};
+    use std::path::Path;
+
+    struct TestInlineAssistDelegate;
+
+    impl InlineAssistDelegate for TestInlineAssistDelegate {
+        fn assist(
+            &self,
+            _prompt_editor: &Entity<Editor>,
+            _initial_prompt: Option<String>,
+            _window: &mut Window,
+            _cx: &mut Context<RulesLibrary>,
+        ) {
+        }
+
+        fn focus_agent_panel(
+            &self,
+            _workspace: &mut Workspace,
+            _window: &mut Window,
+            _cx: &mut Context<Workspace>,
+        ) -> bool {
+            false
+        }
+    }
+
+    struct TestCompletionProvider;
+
+    impl CompletionProvider for TestCompletionProvider {
+        fn completions(
+            &self,
+            _excerpt_id: editor::ExcerptId,
+            _buffer: &Entity<Buffer>,
+            _buffer_position: language::Anchor,
+            _trigger: editor::CompletionContext,
+            _window: &mut Window,
+            _cx: &mut Context<Editor>,
+        ) -> Task<Result<Vec<project::CompletionResponse>>> {
+            Task::ready(Ok(Vec::new()))
+        }
+
+        fn is_completion_trigger(
+            &self,
+            _buffer: &Entity<Buffer>,
+            _position: language::Anchor,
+            _text: &str,
+            _trigger_in_words: bool,
+            _cx: &mut Context<Editor>,
+        ) -> bool {
+            false
+        }
+    }
+
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            LanguageModelRegistry::test(cx);
+        });
+    }
+
+    async fn open_store(db_path: &Path, cx: &mut TestAppContext) -> Entity<PromptStore> {
+        let store = cx
+            .update(|cx| PromptStore::new(db_path.to_path_buf(), Arc::default(), cx))
+            .await
+            .unwrap();
+        cx.new(|_| store)
+    }
+
+    async fn save_rule(
+        store: &Entity<PromptStore>,
+        title: &str,
+        body: &str,
+        cx: &mut TestAppContext,
+    ) -> PromptId {
+        let rule_id = PromptId::new();
+        store
+            .update(cx, |store, cx| {
+                store.save(
+                    rule_id,
+                    Some(title.to_string().into()),
+                    false,
+                    body.into(),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        rule_id
+    }
+
+    fn open_rules_library(
+        store: Entity<PromptStore>,
+        cx: &mut TestAppContext,
+    ) -> (Entity<RulesLibrary>, &mut VisualTestContext) {
+        let language_registry = Arc::new(LanguageRegistry::test(cx.executor()));
+        cx.add_window_view(|window, cx| {
+            RulesLibrary::new(
+                store,
+                language_registry,
+                Box::new(TestInlineAssistDelegate),
+                Rc::new(|| Rc::new(TestCompletionProvider)),
+                None,
+                false,
+                window,
+                cx,
+            )
+        })
+    }
+
+    #[gpui::test]
+    async fn test_focus_search(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let rule_id = save_rule(&store, "Rule", "body", cx).await;
+
+        let (rules_library, cx) = open_rules_library(store, cx);
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            rules_library.load_rule(rule_id, true, window, cx)
+        });
+        cx.run_until_parked();
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            let body_editor = &rules_library.rule_editors[&rule_id].body_editor;
+            assert!(body_editor.focus_handle(cx).is_focused(window));
+        });
+
+        cx.dispatch_action(FocusSearch);
+        cx.run_until_parked();
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            assert!(rules_library.picker.focus_handle(cx).is_focused(window));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_awaitable_rule_operations(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let rule_id = save_rule(&store, "Rule", "body", cx).await;
+        let (rules_library, cx) = open_rules_library(store.clone(), cx);
+
+        let new_rule_id = rules_library
+            .update_in(cx, |rules_library, window, cx| {
+                rules_library.create_rule(window, cx)
+            })
+            .await
+            .unwrap();
+        store.read_with(cx, |store, _| {
+            assert!(store.metadata(new_rule_id).unwrap().title.is_none())
+        });
+        // An untitled rule is reused rather than creating another one.
+        let reused_rule_id = rules_library
+            .update_in(cx, |rules_library, window, cx| {
+                rules_library.create_rule(window, cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(reused_rule_id, new_rule_id);
+
+        // Only open rules can be duplicated.
+        let result = rules_library
+            .update_in(cx, |rules_library, window, cx| {
+                rules_library.create_duplicate_rule(rule_id, window, cx)
+            })
+            .await;
+        assert!(result.is_err());
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            rules_library.load_rule(rule_id, true, window, cx)
+        });
+        cx.run_until_parked();
+        let duplicate_id = rules_library
+            .update_in(cx, |rules_library, window, cx| {
+                rules_library.create_duplicate_rule(rule_id, window, cx)
+            })
+            .await
+            .unwrap();
+        store.read_with(cx, |store, _| {
+            let metadata = store.metadata(duplicate_id).unwrap();
+            assert_eq!(metadata.title.as_deref(), Some("Rule copy"));
+        });
+        let body = store
+            .read_with(cx, |store, cx| store.load(duplicate_id, cx))
+            .await
+            .unwrap();
+        assert_eq!(body, "body");
+
+        cx.run_until_parked();
+        rules_library.read_with(cx, |rules_library, _| {
+            assert_eq!(rules_library.active_rule_id, Some(duplicate_id))
+        });
+        rules_library
+            .update_in(cx, |rules_library, window, cx| {
+                rules_library.delete_rule_without_confirmation(duplicate_id, window, cx)
+            })
+            .await
+            .unwrap();
+        store.read_with(cx, |store, _| {
+            assert!(store.metadata(duplicate_id).is_none())
+        });
+        rules_library.read_with(cx, |rules_library, _| {
+            assert_eq!(rules_library.active_rule_id, None);
+            assert!(!rules_library.rule_editors.contains_key(&duplicate_id));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_delete_empty_rules(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let rule_id = save_rule(&store, "Rule", "body", cx).await;
+        let empty_rule_id = PromptId::new();
+        store
+            .update(cx, |store, cx| {
+                store.save(empty_rule_id, None, false, "".into(), cx)
+            })
+            .await
+            .unwrap();
+        let (rules_library, cx) = open_rules_library(store.clone(), cx);
+
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            rules_library.delete_empty_rules(window, cx)
+        });
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer("Delete");
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert!(store.metadata(empty_rule_id).is_none());
+            assert!(store.metadata(rule_id).is_some());
+        });
+
+        // With nothing left to delete, no prompt is shown.
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            rules_library.delete_empty_rules(window, cx)
+        });
+        cx.run_until_parked();
+        assert!(!cx.has_pending_prompt());
+    }
+
+    #[gpui::test]
+    async fn test_set_rule_language_on_locked_rule(cx: &mut TestAppContext) {
+        init_test(cx);
+        let db_dir = tempfile::tempdir().unwrap();
+        let store = open_store(db_dir.path(), cx).await;
+        let rule_id = save_rule(&store, "Rule", "body", cx).await;
+        store
+            .update(cx, |store, cx| store.set_locked(rule_id, true, cx))
+            .await
+            .unwrap();
+
+        let (rules_library, cx) = open_rules_library(store.clone(), cx);
+        rules_library.update_in(cx, |rules_library, window, cx| {
+            rules_library.set_rule_language(rule_id, Some("Rust".into()), window, cx)
+        });
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert_eq!(store.metadata(rule_id).unwrap().language, None)
+        });
+    }
+
+    #[gpui::test]
+    fn test_wrapped_text(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(|cx| assets::Assets.load_test_fonts(cx));
+        let prose = "Prefer small focused functions and name every variable after what it holds";
+        let code = "let result = compute_something_long(first_argument, second_argument);";
+        let text = format!("{prose}\n```\n{code}\n```\nShort line");
+
+        let window = cx.add_window(|window, cx| {
+            let mut editor = Editor::multi_line(window, cx);
+            editor.set_text(text.clone(), window, cx);
+            editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
+            editor
+        });
+        let cx = &mut VisualTestContext::from_window(*window, cx);
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|_, cx| editor.read(cx).style().unwrap().clone());
+        // Laying the editor out at a narrow width is what sets its wrap width.
+        cx.draw(Default::default(), size(px(200.), px(500.)), |_, _| {
+            EditorElement::new(&editor, style)
+        });
+        let wrapped = editor.update(cx, |editor, cx| {
+            let snapshot = editor.display_snapshot(cx);
+            assert!(
+                snapshot.max_point().row().0 > 4,
+                "expected the body to soft wrap"
+            );
+            wrapped_text(&editor.text(cx), &snapshot)
+        });
+
+        let lines = wrapped.lines().collect::<Vec<_>>();
+        let fence_ix = lines.iter().position(|line| *line == "```").unwrap();
+        // The prose is split where it wraps, without losing or adding words,
+        // and without whitespace left at the wrap points.
+        assert!(fence_ix > 1, "prose wasn't split: {wrapped:?}");
+        assert!(
+            lines[..fence_ix]
+                .iter()
+                .all(|line| !line.is_empty() && line.trim() == *line)
+        );
+        assert!(
+            lines[..fence_ix]
+                .iter()
+                .flat_map(|line| line.split_whitespace())
+                .eq(prose.split_whitespace())
+        );
+        // Code blocks, and lines that don't wrap, are copied verbatim.
+        assert_eq!(
+            lines[fence_ix..],
+            ["```", code, "```", "Short line"],
+            "{wrapped:?}"
+        );
+    }
+
+    #[gpui::test]
+    fn test_record_edit_time(cx: &mut TestAppContext) {
+        init_test(cx);
+        let window = cx.add_window(|window, cx| Editor::multi_line(window, cx));
+        let editor = window.root(cx).unwrap();
+        let mut edit_times = HashMap::default();
+        let mut transaction_ids = Vec::new();
+        for _ in 0..MAX_RECORDED_EDIT_TIMES + 10 {
+            window
+                .update(cx, |editor, window, cx| {
+                    editor.insert("a", window, cx);
+                    let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+                    buffer.update(cx, |buffer, _| buffer.finalize_last_transaction());
+                })
+                .unwrap();
+            cx.update(|cx| {
+                record_edit_time(&editor, &mut edit_times, cx);
+                transaction_ids.push(next_transaction(&editor, false, cx).unwrap());
+            });
+        }
+
+        // Only the most recent transactions are kept.
+        assert_eq!(edit_times.len(), MAX_RECORDED_EDIT_TIMES);
+        assert!(
+            transaction_ids[10..]
+                .iter()
+                .all(|transaction_id| edit_times.contains_key(transaction_id))
+        );
+
+        // Undoing doesn't record anything.
+        window
+            .update(cx, |editor, window, cx| editor.undo(&Undo, window, cx))
+            .unwrap();
+        cx.update(|cx| record_edit_time(&editor, &mut edit_times, cx));
+        assert_eq!(edit_times.len(), MAX_RECORDED_EDIT_TIMES);
+    }
+
+    #[gpui::test]
+    fn test_section_fold_ranges(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fold_ranges = |text: &str, cx: &mut TestAppContext| {
+            let buffer =
+                cx.new(|cx| Buffer::local(text, cx).with_language(language::markdown_lang(), cx));
+            cx.run_until_parked();
+            buffer.read_with(cx, |buffer, _| section_fold_ranges(&buffer.snapshot()))
+        };
+
+        // Nested headings fold with their section, an empty section has
+        // nothing to fold, and the last section runs to the end of the buffer.
+        assert_eq!(
+            fold_ranges(
+                "Intro\n# One\nbody one\n# Two\n## Nested\nnested body\n# Three\n# Four\nlast\n",
+                cx
+            ),
+            [
+                Point::new(3, 5)..Point::new(5, 11),
+                Point::new(7, 6)..Point::new(9, 0),
+            ]
+        );
+
+        // Sections are split at the shallowest heading level in the rule.
+        assert_eq!(
+            fold_ranges("## A\na\n### A.1\na.1\n## B\nb", cx),
+            [Point::new(4, 4)..Point::new(5, 1)]
+        );
+
+        assert!(fold_ranges("# Only\nbody", cx).is_empty());
+        assert!(fold_ranges("No headings\n", cx).is_empty());
+    }
+
+    #[test]
+    fn test_parse_token_range() {
+        assert_eq!(
+            parse_token_range("tokens:>500 style"),
+            ("style".to_string(), Some(501..=u64::MAX))
+        );
+        assert_eq!(
+            parse_token_range("tokens:>=500"),
+            (String::new(), Some(500..=u64::MAX))
+        );
+        assert_eq!(
+            parse_token_range("tokens:<500"),
+            (String::new(), Some(0..=499))
+        );
+        assert_eq!(
+            parse_token_range("tokens:<=500"),
+            (String::new(), Some(0..=500))
+        );
+        assert_eq!(
+            parse_token_range("a tokens:100..500 b"),
+            ("a b".to_string(), Some(100..=500))
+        );
+        assert_eq!(
+            parse_token_range("tokens:500"),
+            (String::new(), Some(500..=500))
+        );
+        // Only the first filter applies; later ones are searched for as text.
+        assert_eq!(
+            parse_token_range("tokens:>1 tokens:<5 rust"),
+            ("tokens:<5 rust".to_string(), Some(2..=u64::MAX))
+        );
+        // Filters that don't parse, or would overflow, leave the query alone.
+        for query in [
+            "tokens:<0",
+            "tokens:>18446744073709551615",
+            "tokens:abc  rust",
+            "tokens:",
+            "tokens:1..",
+        ] {
+            assert_eq!(parse_token_range(query), (query.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn test_parse_tag_filters() {
+        assert_eq!(
+            parse_tag_filters("tag:review  refactor tag:Tests"),
+            (
+                "refactor".to_string(),
+                vec!["review".to_string(), "Tests".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_tag_filters("  plain  query "),
+            ("  plain  query ".to_string(), Vec::new())
+        );
+        assert_eq!(
+            parse_tag_filters("tag: tag:"),
+            ("tag: tag:".to_string(), Vec::new())
+        );
+        assert_eq!(
+            parse_tag_filters(r"\tag:foo tag:bar"),
+            ("tag:foo".to_string(), vec!["bar".to_string()])
+        );
+        assert_eq!(
+            parse_tag_filters(r"\tag: foo \tagged"),
+            (r"tag: foo \tagged".to_string(), Vec::new())
+        );
+        // Tags keep their case and punctuation; matching ignores case later.
+        assert_eq!(
+            parse_tag_filters("tag:C++ tag:c++ tag:wip!"),
+            (
+                String::new(),
+                vec!["C++".to_string(), "c++".to_string(), "wip!".to_string()]
+            )
+        );
+        // Only words that start with `tag:` are filters.
+        assert_eq!(
+            parse_tag_filters("mytag:a tags:b tag:c"),
+            ("mytag:a tags:b".to_string(), vec!["c".to_string()])
+        );
+        // Once a filter is split out, the rest of the query is rejoined with
+        // single spaces, whatever whitespace separated it.
+        assert_eq!(
+            parse_tag_filters("\tfoo  tag:a\nbar"),
+            ("foo bar".to_string(), vec!["a".to_string()])
+        );
+        assert_eq!(parse_tag_filters(""), (String::new(), Vec::new()));
+    }
+}
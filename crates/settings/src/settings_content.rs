@@ -107,6 +107,9 @@ pub struct SettingsContent {
 
     pub journal: Option<JournalSettingsContent>,
 
+    /// Settings for the rules library.
+    pub rules_library: Option<RulesLibrarySettingsContent>,
+
     /// A map of log scopes to the desired log level.
     /// Useful for filtering out noisy logs or enabling more verbose logging.
     ///
@@ -731,6 +734,89 @@ pub struct JournalSettingsContent {
     pub hour_format: Option<HourFormat>,
 }
 
+/// Settings for the rules library.
+#[with_fallible_options]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq)]
+pub struct RulesLibrarySettingsContent {
+    /// Whether to remove trailing whitespace from each line of a rule, and trailing
+    /// blank lines, when it's saved. Lines inside fenced code blocks are left alone.
+    ///
+    /// Default: false
+    pub trim_trailing_whitespace_on_save: Option<bool>,
+    /// How to soft wrap the body of a rule. `preferred_line_length` wraps at
+    /// the Markdown language's preferred line length.
+    ///
+    /// Default: editor_width
+    pub soft_wrap: Option<SoftWrap>,
+    /// Whether to open long rules with every markdown section but the first
+    /// folded. Folding never changes the rule's saved body.
+    ///
+    /// Default: false
+    pub fold_sections_on_load: Option<bool>,
+    /// How many lines a rule needs to have for its sections to be folded when
+    /// it's opened.
+    ///
+    /// Default: 100
+    pub fold_sections_min_lines: Option<u32>,
+    /// Whether to keep a rule in place in the rule list while its title or body
+    /// is being edited, rather than moving it to its sorted position on every
+    /// save. The list is re-sorted when the editor loses focus or the search
+    /// query changes.
+    ///
+    /// Default: false
+    pub freeze_order_while_editing: Option<bool>,
+    /// Whether to show edit predictions while writing a rule's body. They're
+    /// never shown for built-in or read-only rules.
+    ///
+    /// Default: true
+    pub edit_predictions: Option<bool>,
+    /// Where focus goes when the rules library opens.
+    ///
+    /// Default: picker
+    pub focus_on_open: Option<RulesLibraryFocus>,
+    /// Whether to label default, built-in, locked and archived rules with text,
+    /// rather than only telling them apart by icons and their colors.
+    ///
+    /// Default: false
+    pub show_state_labels: Option<bool>,
+    /// How to sort the rule list. Default rules are listed first either way.
+    ///
+    /// Default: title_asc
+    pub sort_order: Option<RulesLibrarySortOrder>,
+}
+
+/// How the rule list is sorted.
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RulesLibrarySortOrder {
+    /// By title, alphabetically.
+    #[default]
+    TitleAsc,
+    /// By when the rule was last saved, most recent first.
+    UpdatedDesc,
+    /// By when the rule was created, most recent first. Rules created before
+    /// this was recorded are sorted by when they were last saved.
+    CreatedDesc,
+    /// By how many times the rule was inserted into a thread, most used first.
+    MostUsed,
+}
+
+/// Where focus goes when the rules library opens.
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RulesLibraryFocus {
+    /// The search field of the rule list.
+    #[default]
+    Picker,
+    /// The body of the most recently opened rule, falling back to the search
+    /// field if there are no rules.
+    Body,
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum HourFormat {
@@ -203,6 +203,7 @@ impl VsCodeSettings {
             proxy: self.read_string("http.proxy"),
             remote: RemoteSettingsContent::default(),
             repl: None,
+            rules_library: None,
             server_url: None,
             session: None,
             status_bar: self.status_bar_settings_content(),
@@ -27,6 +27,48 @@ pub struct SystemSpecs {
     commit_sha: Option<String>,
     bundle_type: Option<String>,
     gpu_specs: Option<String>,
+    project_summary: Option<ProjectSummary>,
+}
+
+/// A redacted description of the project a report was filed from. Never
+/// contains file paths or names.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProjectSummary {
+    primary_languages: Vec<String>,
+    approximate_file_count: usize,
+    is_remote: bool,
+}
+
+impl ProjectSummary {
+    /// The file count is rounded down to its order of magnitude.
+    pub fn new(primary_languages: Vec<String>, file_count: usize, is_remote: bool) -> Self {
+        let approximate_file_count = match file_count.checked_ilog10() {
+            Some(exponent) => 10usize.pow(exponent),
+            None => 0,
+        };
+        Self {
+            primary_languages,
+            approximate_file_count,
+            is_remote,
+        }
+    }
+}
+
+impl Display for ProjectSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let languages = if self.primary_languages.is_empty() {
+            "Unknown".to_string()
+        } else {
+            self.primary_languages.join(", ")
+        };
+        write!(
+            f,
+            "{}, {}+ files, {}",
+            languages,
+            self.approximate_file_count,
+            if self.is_remote { "remote" } else { "local" }
+        )
+    }
 }
 
 impl SystemSpecs {
@@ -66,6 +108,7 @@ impl SystemSpecs {
                 architecture,
                 commit_sha,
                 gpu_specs,
+                project_summary: None,
             }
         })
     }
@@ -98,8 +141,14 @@ impl SystemSpecs {
             commit_sha,
             bundle_type,
             gpu_specs: try_determine_available_gpus(),
+            project_summary: None,
         }
     }
+
+    pub fn with_project_summary(mut self, project_summary: ProjectSummary) -> Self {
+        self.project_summary = Some(project_summary);
+        self
+    }
 }
 
 impl Display for SystemSpecs {
@@ -135,6 +184,11 @@ impl Display for SystemSpecs {
                 .as_ref()
                 .map(|specs| format!("GPU: {}", specs)),
         )
+        .chain(
+            self.project_summary
+                .as_ref()
+                .map(|summary| format!("Project: {}", summary)),
+        )
         .collect::<Vec<String>>()
         .join("\n");
 
@@ -51,7 +51,7 @@ use zed::{
     OpenListener, OpenRequest, RawOpenRequest, app_menus, build_window_options,
     derive_paths_with_position, edit_prediction_registry, handle_cli_connection,
     handle_keymap_file_changes, handle_settings_file_changes, initialize_workspace,
-    open_paths_with_positions,
+    open_paths_with_positions, refresh_menus_on_recent_rules_change,
 };
 
 use crate::zed::{OpenRequestKind, eager_load_active_theme_and_icon_theme};
@@ -696,6 +696,7 @@ pub fn main() {
 
         let menus = app_menus(cx);
         cx.set_menus(menus);
+        refresh_menus_on_recent_rules_change(cx);
         initialize_workspace(app_state.clone(), prompt_builder, cx);
 
         cx.activate(true);
@@ -51,7 +51,7 @@ use paths::{
 };
 use project::{DirectoryLister, DisableAiSettings, ProjectItem};
 use project_panel::ProjectPanel;
-use prompt_store::PromptBuilder;
+use prompt_store::{PromptBuilder, PromptStore, PromptsUpdatedEvent};
 use quick_action_bar::QuickActionBar;
 use recent_projects::open_remote_project;
 use release_channel::{AppCommitSha, AppVersion, ReleaseChannel};
@@ -66,7 +66,9 @@ use settings::{
 use std::time::Duration;
 use std::{
     borrow::Cow,
+    cell::RefCell,
     path::{Path, PathBuf},
+    rc::Rc,
     sync::Arc,
     sync::atomic::{self, AtomicBool},
 };
@@ -1720,6 +1722,37 @@ fn show_markdown_app_notification<F>(
     .detach();
 }
 
+/// Rebuilds the application menus when the recently opened rules change, so the
+/// "Open Recent Rule" submenu stays current once the prompt store has loaded.
+/// Rules are reopened and saved much more often than the submenu changes, so
+/// the menus are only rebuilt when the rules it lists do.
+pub fn refresh_menus_on_recent_rules_change(cx: &mut App) {
+    let store = PromptStore::global(cx);
+    cx.spawn(async move |cx| {
+        let store = store.await?;
+        cx.update(|cx| {
+            // The menus were first built before the store loaded, without any rules.
+            let listed_rules = RefCell::new(Vec::new());
+            let refresh = Rc::new(move |cx: &mut App| {
+                let rules = recent_rules(cx);
+                if *listed_rules.borrow() != rules {
+                    listed_rules.replace(rules);
+                    cx.set_menus(app_menus(cx));
+                }
+            });
+            refresh(cx);
+            cx.observe(&store, {
+                let refresh = refresh.clone();
+                move |_, cx| refresh(cx)
+            })
+            .detach();
+            cx.subscribe(&store, move |_, _: &PromptsUpdatedEvent, cx| refresh(cx))
+                .detach();
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
 fn reload_keymaps(cx: &mut App, mut user_key_bindings: Vec<KeyBinding>) {
     cx.clear_key_bindings();
     load_default_keymap(cx);
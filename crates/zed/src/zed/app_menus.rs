@@ -1,12 +1,55 @@
 use collab_ui::collab_panel;
-use gpui::{App, Menu, MenuItem, OsAction};
+use gpui::{App, Menu, MenuItem, OsAction, SharedString};
+use prompt_store::{PromptId, PromptStore};
 use release_channel::ReleaseChannel;
 use terminal_view::terminal_panel;
+use uuid::Uuid;
 use zed_actions::{ToggleFocus as ToggleDebugPanel, dev};
 
 pub fn app_menus(cx: &mut App) -> Vec<Menu> {
     use zed_actions::Quit;
 
+    let mut help_items = vec![
+        MenuItem::action(
+            "View Release Notes Locally",
+            auto_update_ui::ViewReleaseNotesLocally,
+        ),
+        MenuItem::action("View Telemetry", zed_actions::OpenTelemetryLog),
+        MenuItem::action("View Dependency Licenses", zed_actions::OpenLicenses),
+        MenuItem::action("Show Welcome", onboarding::ShowWelcome),
+        MenuItem::separator(),
+        MenuItem::action("File Bug Report...", zed_actions::feedback::FileBugReport),
+        MenuItem::action("Request Feature...", zed_actions::feedback::RequestFeature),
+        MenuItem::action("Email Us...", zed_actions::feedback::EmailZed),
+    ];
+    help_items.extend(
+        feedback::feedback_links(cx)
+            .into_iter()
+            .map(|link| MenuItem::action(link.label.clone(), link.action())),
+    );
+    help_items.extend([
+        MenuItem::separator(),
+        MenuItem::action(
+            "Documentation",
+            super::OpenBrowser {
+                url: "https://zed.dev/docs".into(),
+            },
+        ),
+        MenuItem::action("Zed Repository", feedback::OpenZedRepo),
+        MenuItem::action(
+            "Zed Twitter",
+            super::OpenBrowser {
+                url: "https://twitter.com/zeddotdev".into(),
+            },
+        ),
+        MenuItem::action(
+            "Join the Team",
+            super::OpenBrowser {
+                url: "https://zed.dev/jobs".into(),
+            },
+        ),
+    ]);
+
     let mut view_items = vec![
         MenuItem::action(
             "Zoom In",
@@ -140,6 +183,7 @@ pub fn app_menus(cx: &mut App) -> Vec<Menu> {
                         from_existing_connection: false,
                     },
                 ),
+                recent_rules_menu(cx),
                 MenuItem::separator(),
                 MenuItem::action("Add Folder to Project…", workspace::AddFolderToProject),
                 MenuItem::separator(),
@@ -293,39 +337,52 @@ pub fn app_menus(cx: &mut App) -> Vec<Menu> {
         },
         Menu {
             name: "Help".into(),
-            items: vec![
-                MenuItem::action(
-                    "View Release Notes Locally",
-                    auto_update_ui::ViewReleaseNotesLocally,
-                ),
-                MenuItem::action("View Telemetry", zed_actions::OpenTelemetryLog),
-                MenuItem::action("View Dependency Licenses", zed_actions::OpenLicenses),
-                MenuItem::action("Show Welcome", onboarding::ShowWelcome),
-                MenuItem::separator(),
-                MenuItem::action("File Bug Report...", zed_actions::feedback::FileBugReport),
-                MenuItem::action("Request Feature...", zed_actions::feedback::RequestFeature),
-                MenuItem::action("Email Us...", zed_actions::feedback::EmailZed),
-                MenuItem::separator(),
-                MenuItem::action(
-                    "Documentation",
-                    super::OpenBrowser {
-                        url: "https://zed.dev/docs".into(),
-                    },
-                ),
-                MenuItem::action("Zed Repository", feedback::OpenZedRepo),
-                MenuItem::action(
-                    "Zed Twitter",
-                    super::OpenBrowser {
-                        url: "https://twitter.com/zeddotdev".into(),
-                    },
-                ),
-                MenuItem::action(
-                    "Join the Team",
-                    super::OpenBrowser {
-                        url: "https://zed.dev/jobs".into(),
-                    },
-                ),
-            ],
+            items: help_items,
         },
     ]
 }
+
+fn recent_rules_menu(cx: &App) -> MenuItem {
+    let mut items = vec![MenuItem::action(
+        "Open Rules Library",
+        zed_actions::assistant::OpenRulesLibrary::default(),
+    )];
+    let rules = recent_rules(cx);
+    if !rules.is_empty() {
+        items.push(MenuItem::separator());
+    }
+    items.extend(rules.into_iter().map(|(uuid, title)| {
+        MenuItem::action(
+            title,
+            zed_actions::assistant::OpenRulesLibrary {
+                prompt_to_select: Some(uuid),
+                ..Default::default()
+            },
+        )
+    }));
+
+    MenuItem::submenu(Menu {
+        name: "Open Recent Rule".into(),
+        items,
+    })
+}
+
+/// The rules listed in the "Open Recent Rule" submenu, most recent first.
+/// `OpenRulesLibrary` can only select user rules, so built-in rules are left
+/// out.
+pub(crate) fn recent_rules(cx: &App) -> Vec<(Uuid, SharedString)> {
+    let Some(store) = PromptStore::try_global(cx) else {
+        return Vec::new();
+    };
+    store
+        .read(cx)
+        .recently_opened()
+        .into_iter()
+        .filter_map(|rule| {
+            let PromptId::User { uuid } = rule.id else {
+                return None;
+            };
+            Some((uuid.0, rule.title.unwrap_or_else(|| "Untitled".into())))
+        })
+        .collect()
+}
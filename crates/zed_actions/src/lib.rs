@@ -383,6 +383,10 @@ pub mod assistant {
     pub struct OpenRulesLibrary {
         #[serde(skip)]
         pub prompt_to_select: Option<Uuid>,
+        /// Opens the library for browsing, without allowing rules to be edited,
+        /// created or deleted.
+        #[serde(default)]
+        pub read_only: bool,
     }
 
     /// Deploys the assistant interface with the specified configuration.